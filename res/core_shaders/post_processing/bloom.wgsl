@@ -0,0 +1,121 @@
+// jimenez's bloom from call of duty: advanced warfare, like learnopengl's "physically based
+// bloom". a bright pass, a 13 tap downsample down a chain of mips, then a tent filter back up
+struct Immediates {
+    threshold: f32,
+    knee: f32,
+    // in uv, of the upsample's tent filter
+    radius: f32,
+    intensity: f32,
+};
+var<immediate> config: Immediates; // SIZE: 16
+
+struct Uniform {
+    time: f32,
+    res: vec2<f32>
+};
+@group(0) @binding(0)
+var<uniform> post_uniform: Uniform;
+
+@group(1) @binding(0)
+var src_tex: texture_2d<f32>;
+@group(1) @binding(1)
+var src_sampler: sampler;
+
+// only used by `fs_composite`; the top of the mip chain
+@group(2) @binding(0)
+var bloom_tex: texture_2d<f32>;
+@group(2) @binding(1)
+var bloom_sampler: sampler;
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VsOut {
+    var positions = array<vec2<f32>,3>(
+        vec2(-1.0,-1.0),
+        vec2( 3.0,-1.0),
+        vec2(-1.0, 3.0)
+    );
+
+    var out: VsOut;
+
+    let p = positions[i];
+
+    out.pos = vec4(p,0.0,1.0);
+    out.uv = p * 0.5 + 0.5;
+    out.uv.y = 1.0 - out.uv.y;
+
+    return out;
+}
+
+fn tap(uv: vec2<f32>, x: f32, y: f32, texel: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(src_tex, src_sampler, uv + vec2(x, y) * texel, 0.0);
+}
+
+fn downsample(uv: vec2<f32>) -> vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(src_tex));
+
+    let a = tap(uv, -2.0, 2.0, texel);
+    let b = tap(uv, 0.0, 2.0, texel);
+    let c = tap(uv, 2.0, 2.0, texel);
+    let d = tap(uv, -2.0, 0.0, texel);
+    let e = tap(uv, 0.0, 0.0, texel);
+    let f = tap(uv, 2.0, 0.0, texel);
+    let g = tap(uv, -2.0, -2.0, texel);
+    let h = tap(uv, 0.0, -2.0, texel);
+    let i = tap(uv, 2.0, -2.0, texel);
+    let j = tap(uv, -1.0, 1.0, texel);
+    let k = tap(uv, 1.0, 1.0, texel);
+    let l = tap(uv, -1.0, -1.0, texel);
+    let m = tap(uv, 1.0, -1.0, texel);
+
+    return e * 0.125 + (a + c + g + i) * 0.03125 + (b + d + f + h) * 0.0625
+        + (j + k + l + m) * 0.125;
+}
+
+// what's left of `c` past `threshold`, easing in over `knee`
+fn bright(c: vec4<f32>) -> vec4<f32> {
+    let brightness = max(c.r, max(c.g, c.b));
+    var soft = clamp(brightness - config.threshold + config.knee, 0.0, 2.0 * config.knee);
+    soft = soft * soft / (4.0 * config.knee + 0.0001);
+    let weight = max(soft, brightness - config.threshold) / max(brightness, 0.0001);
+    return c * weight;
+}
+
+@fragment
+fn fs_prefilter(in: VsOut) -> @location(0) vec4<f32> {
+    return bright(max(downsample(in.uv), vec4(0.0)));
+}
+
+@fragment
+fn fs_downsample(in: VsOut) -> @location(0) vec4<f32> {
+    return downsample(in.uv);
+}
+
+// 3x3 tent, added onto the next mip up
+@fragment
+fn fs_upsample(in: VsOut) -> @location(0) vec4<f32> {
+    let r = vec2(config.radius);
+
+    let a = tap(in.uv, -1.0, 1.0, r);
+    let b = tap(in.uv, 0.0, 1.0, r);
+    let c = tap(in.uv, 1.0, 1.0, r);
+    let d = tap(in.uv, -1.0, 0.0, r);
+    let e = tap(in.uv, 0.0, 0.0, r);
+    let f = tap(in.uv, 1.0, 0.0, r);
+    let g = tap(in.uv, -1.0, -1.0, r);
+    let h = tap(in.uv, 0.0, -1.0, r);
+    let i = tap(in.uv, 1.0, -1.0, r);
+
+    return (e * 4.0 + (b + d + f + h) * 2.0 + (a + c + g + i)) / 16.0;
+}
+
+@fragment
+fn fs_composite(in: VsOut) -> @location(0) vec4<f32> {
+    let scene = textureSampleLevel(src_tex, src_sampler, in.uv, 0.0);
+    let bloom = textureSampleLevel(bloom_tex, bloom_sampler, in.uv, 0.0);
+    return mix(scene, bloom, config.intensity);
+}
@@ -0,0 +1,56 @@
+struct Immediates {
+    // in pixels
+    radius: f32,
+};
+var<immediate> config: Immediates; // SIZE: 4
+
+struct Uniform {
+    time: f32,
+    res: vec2<f32>
+};
+@group(0) @binding(0)
+var<uniform> post_uniform: Uniform;
+
+@group(1) @binding(0)
+var scene_tex: texture_2d<f32>;
+@group(1) @binding(1)
+var scene_sampler: sampler;
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VsOut {
+    var positions = array<vec2<f32>,3>(
+        vec2(-1.0,-1.0),
+        vec2( 3.0,-1.0),
+        vec2(-1.0, 3.0)
+    );
+
+    var out: VsOut;
+
+    let p = positions[i];
+
+    out.pos = vec4(p,0.0,1.0);
+    out.uv = p * 0.5 + 0.5;
+    out.uv.y = 1.0 - out.uv.y;
+
+    return out;
+}
+
+// 5x5 box blur, the taps spread out to cover `radius`
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    let step = config.radius / 2.0 / post_uniform.res;
+
+    var sum = vec4<f32>(0.0);
+    for (var y = -2; y <= 2; y += 1) {
+        for (var x = -2; x <= 2; x += 1) {
+            let uv = in.uv + vec2<f32>(f32(x), f32(y)) * step;
+            sum += textureSampleLevel(scene_tex, scene_sampler, uv, 0.0);
+        }
+    }
+    return sum / 25.0;
+}
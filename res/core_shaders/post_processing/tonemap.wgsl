@@ -0,0 +1,97 @@
+struct Immediates {
+    exposure: f32,
+    // 0 reinhard, 1 aces, 2 filmic
+    tonemapper: u32,
+    // 1 when the output isn't an srgb format, so it has to be encoded here
+    encode_srgb: u32,
+};
+var<immediate> config: Immediates; // SIZE: 12
+
+struct Uniform {
+    time: f32,
+    res: vec2<f32>
+};
+@group(0) @binding(0)
+var<uniform> post_uniform: Uniform;
+
+@group(1) @binding(0)
+var scene_tex: texture_2d<f32>;
+@group(1) @binding(1)
+var scene_sampler: sampler;
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VsOut {
+    var positions = array<vec2<f32>,3>(
+        vec2(-1.0,-1.0),
+        vec2( 3.0,-1.0),
+        vec2(-1.0, 3.0)
+    );
+
+    var out: VsOut;
+
+    let p = positions[i];
+
+    out.pos = vec4(p,0.0,1.0);
+    out.uv = p * 0.5 + 0.5;
+    out.uv.y = 1.0 - out.uv.y;
+
+    return out;
+}
+
+fn reinhard(c: vec3<f32>) -> vec3<f32> {
+    return c / (1.0 + c);
+}
+
+// narkowicz's fit of the aces curve
+fn aces(c: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let d = 2.43;
+    let e = 0.59;
+    let f = 0.14;
+    return clamp((c * (a * c + b)) / (c * (d * c + e) + f), vec3(0.0), vec3(1.0));
+}
+
+// hable's uncharted 2 curve
+fn hable(c: vec3<f32>) -> vec3<f32> {
+    let a = 0.15;
+    let b = 0.50;
+    let cc = 0.10;
+    let d = 0.20;
+    let e = 0.02;
+    let f = 0.30;
+    return ((c * (a * c + cc * b) + d * e) / (c * (a * c + b) + d * f)) - e / f;
+}
+
+fn filmic(c: vec3<f32>) -> vec3<f32> {
+    let white = 11.2;
+    return hable(c * 2.0) / hable(vec3(white));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    let low = c * 12.92;
+    let high = 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055;
+    return select(high, low, c <= vec3(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    let scene = textureSampleLevel(scene_tex, scene_sampler, in.uv, 0.0);
+    let c = max(scene.rgb * config.exposure, vec3(0.0));
+
+    var mapped: vec3<f32>;
+    switch config.tonemapper {
+        case 0u: { mapped = reinhard(c); }
+        case 1u: { mapped = aces(c); }
+        default: { mapped = filmic(c); }
+    }
+    if config.encode_srgb == 1u {
+        mapped = linear_to_srgb(mapped);
+    }
+    return vec4(mapped, clamp(scene.a, 0.0, 1.0));
+}
@@ -75,8 +75,13 @@ impl<T: AppHandler + 'static> ApplicationHandler<Context> for App<T> {
                 match context.render(handler) {
                     Ok(_) => {}
                     // Reconfigure the surface if it's lost or outdated
-                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                        let size = context.renderer.window.inner_size();
+                    Err(e)
+                        if matches!(
+                            e.downcast_ref(),
+                            Some(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)
+                        ) =>
+                    {
+                        let size = context.renderer.window().inner_size();
                         context.renderer.resize(size.width, size.height);
                     }
                     Err(_) => {
@@ -84,7 +89,7 @@ impl<T: AppHandler + 'static> ApplicationHandler<Context> for App<T> {
                     }
                 };
             }
-            WindowEvent::Occluded(false) => context.renderer.window.request_redraw(),
+            WindowEvent::Occluded(false) => context.renderer.request_redraw(),
             e => {
                 println!("{e:?}");
                 context
@@ -0,0 +1,121 @@
+//! compares iterating `ecs::World`'s columns against the old layout, where every value and
+//! component was boxed separately and reached through the entity list.
+//!
+//! run with `cargo run --release --bin ecs_bench`
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use ostinato::ecs::{Component, EntityType, TypeIdHasher, World};
+
+const ENTITIES: usize = 100_000;
+const FRAMES: u32 = 200;
+
+struct Particle;
+impl EntityType for Particle {}
+
+#[derive(Clone, Copy)]
+struct Position([f32; 3]);
+impl Component for Position {}
+
+#[derive(Clone, Copy)]
+struct Velocity([f32; 3]);
+impl Component for Velocity {}
+
+fn main() {
+    // other allocations made while spawning, like a real app would have, so the boxed
+    // layout's values don't happen to end up next to each other
+    let mut noise: Vec<Box<[u8; 48]>> = Vec::with_capacity(ENTITIES * 2);
+
+    let mut boxed = Boxed::default();
+    let mut world = World::new();
+    for i in 0..ENTITIES {
+        let pos = Position([i as f32, 0., 0.]);
+        let vel = Velocity([0., 1., i as f32 * 0.001]);
+        boxed.spawn(Particle, pos, vel);
+        noise.push(Box::new([i as u8; 48]));
+        let h = world.spawn(Particle);
+        world.insert(h, pos).unwrap();
+        world.insert(h, vel).unwrap();
+        noise.push(Box::new([i as u8; 48]));
+    }
+
+    let boxed_time = time(|| {
+        for e in boxed.entities.iter_mut() {
+            let Some(vel) = e.get::<Velocity>().copied() else {
+                continue;
+            };
+            if let Some(pos) = e.get_mut::<Position>() {
+                step(pos, &vel);
+            }
+        }
+    });
+    let columnar_time = time(|| {
+        for (_, (pos, vel)) in world.query::<(&mut Position, &Velocity)>() {
+            step(pos, vel);
+        }
+    });
+
+    let per_entity = |d: Duration| d.as_nanos() as f64 / (ENTITIES as f64 * FRAMES as f64);
+    println!("ecs_bench :: {ENTITIES} entities, {FRAMES} frames");
+    println!("boxed    : {:.2} ns/entity", per_entity(boxed_time));
+    println!("columnar : {:.2} ns/entity", per_entity(columnar_time));
+    println!(
+        "speedup  : {:.2}x",
+        boxed_time.as_secs_f64() / columnar_time.as_secs_f64()
+    );
+    black_box(noise);
+}
+
+fn step(pos: &mut Position, vel: &Velocity) {
+    for i in 0..3 {
+        pos.0[i] += vel.0[i] * 0.016;
+    }
+}
+
+fn time(mut frame: impl FnMut()) -> Duration {
+    // warm up
+    frame();
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        frame();
+        black_box(&mut frame);
+    }
+    start.elapsed()
+}
+
+/// the layout `World` used before columns: one box per value and per component
+#[derive(Default)]
+struct Boxed {
+    entities: Vec<BoxedEntity>,
+}
+
+struct BoxedEntity {
+    _value: Box<dyn Any>,
+    components: HashMap<TypeId, Box<dyn Any>, BuildHasherDefault<TypeIdHasher>>,
+}
+
+impl Boxed {
+    fn spawn(&mut self, value: impl Any, pos: Position, vel: Velocity) {
+        let mut components: HashMap<TypeId, Box<dyn Any>, _> =
+            HashMap::with_hasher(BuildHasherDefault::new());
+        components.insert(TypeId::of::<Position>(), Box::new(pos));
+        components.insert(TypeId::of::<Velocity>(), Box::new(vel));
+        self.entities.push(BoxedEntity {
+            _value: Box::new(value),
+            components,
+        });
+    }
+}
+
+impl BoxedEntity {
+    fn get<C: 'static>(&self) -> Option<&C> {
+        self.components.get(&TypeId::of::<C>())?.downcast_ref()
+    }
+    fn get_mut<C: 'static>(&mut self) -> Option<&mut C> {
+        self.components.get_mut(&TypeId::of::<C>())?.downcast_mut()
+    }
+}
@@ -1,3 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::tests::headless;
+
+    fn light(x: f32) -> LightUniform {
+        LightUniform::new([x, 0., 0.], [1.; 3], 1.)
+    }
+
+    #[test]
+    fn environment_grows_and_rebinds() {
+        let renderer = headless(8, 8);
+        let (device, queue) = (&renderer.device, &renderer.queue);
+        let mut environment = Environment::new(&[light(0.)], device);
+        let bind_group = environment.bind_group().clone();
+
+        // changing a light just writes the buffer
+        environment.light_mut(0).unwrap().position = [1., 2., 3.];
+        environment.update(device, queue);
+        assert_eq!(environment.bind_group(), &bind_group);
+
+        // more lights than fit make a bigger buffer
+        for x in 1..5 {
+            assert_eq!(environment.add_light(light(x as f32)), x);
+        }
+        environment.update(device, queue);
+        assert_ne!(environment.bind_group(), &bind_group);
+        assert!(environment.buffer.size() >= 5 * size_of::<LightUniform>() as u64);
+
+        assert_eq!(environment.remove_light(0).position, [1., 2., 3.]);
+        assert_eq!(environment.lights()[0].position, [1., 0., 0.]);
+        assert_eq!(environment.lights().len(), 4);
+    }
+
+    #[test]
+    fn empty_environment_still_binds() {
+        let renderer = headless(8, 8);
+        let (device, queue) = (&renderer.device, &renderer.queue);
+        let mut environment = Environment::new(&[], device);
+        environment.add_light(light(0.));
+        environment.update(device, queue);
+        environment.clear();
+        environment.update(device, queue);
+        assert!(environment.lights().is_empty());
+    }
+}
+
+use std::num::NonZero;
+
+use wgpu::{BindGroup, BindGroupLayoutEntry, Buffer, BufferUsages, util::DeviceExt};
+
+use crate::renderer::{Binding, Level};
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightUniform {
@@ -18,3 +71,141 @@ impl LightUniform {
         }
     }
 }
+
+/// a set of lights and the storage buffer they live in; the `Environment` level.
+///
+/// a scene can have more than one, e.g. a mirror that sees the room lit differently. bind
+/// whichever one a pass should use at `Level::Environment`.
+///
+/// changes only reach the gpu on `update`
+pub struct Environment {
+    lights: Vec<LightUniform>,
+    buffer: Buffer,
+    bind_group: BindGroup,
+    /// how many lights the bind group covers
+    bound: usize,
+    dirty: bool,
+}
+
+impl Environment {
+    /// shaders can't bind an empty array, so no lights is one of these
+    const DARK: LightUniform = LightUniform {
+        position: [0., 0., 1e9],
+        radius: 0.,
+        color: [0.; 3],
+        intensity: 0.,
+    };
+
+    pub fn new(lights: &[LightUniform], device: &wgpu::Device) -> Self {
+        let lights = lights.to_vec();
+        let buffer = Self::create_buffer(&lights, lights.len(), device);
+        let bind_group = Self::bind_buffer(&buffer, lights.len(), device);
+        Self {
+            bound: lights.len(),
+            lights,
+            buffer,
+            bind_group,
+            dirty: false,
+        }
+    }
+
+    pub fn lights(&self) -> &[LightUniform] {
+        &self.lights
+    }
+    /// returns the new light's index
+    pub fn add_light(&mut self, light: LightUniform) -> usize {
+        self.dirty = true;
+        self.lights.push(light);
+        self.lights.len() - 1
+    }
+    /// lights after `index` move down one
+    pub fn remove_light(&mut self, index: usize) -> LightUniform {
+        self.dirty = true;
+        self.lights.remove(index)
+    }
+    pub fn light_mut(&mut self, index: usize) -> Option<&mut LightUniform> {
+        self.dirty = true;
+        self.lights.get_mut(index)
+    }
+    pub fn set_lights(&mut self, lights: &[LightUniform]) {
+        self.dirty = true;
+        self.lights = lights.to_vec();
+    }
+    pub fn clear(&mut self) {
+        self.dirty = true;
+        self.lights.clear();
+    }
+
+    /// writes the lights to the gpu, if they changed. the bind group is remade when the number
+    /// of lights changes, so grab it again afterwards
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let size = (self.lights.len().max(1) * size_of::<LightUniform>()) as u64;
+        let grow = size > self.buffer.size();
+        if grow {
+            // room to spare, so adding lights one at a time doesn't remake it every time
+            let capacity = self.lights.len().next_power_of_two();
+            self.buffer = Self::create_buffer(&self.lights, capacity, device);
+        } else if self.lights.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&Self::DARK));
+        } else {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.lights));
+        }
+        if grow || self.bound != self.lights.len() {
+            self.bind_group = Self::bind_buffer(&self.buffer, self.lights.len(), device);
+            self.bound = self.lights.len();
+        }
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// `lights`, with room for `capacity`
+    fn create_buffer(lights: &[LightUniform], capacity: usize, device: &wgpu::Device) -> Buffer {
+        let mut contents = if lights.is_empty() {
+            vec![Self::DARK]
+        } else {
+            lights.to_vec()
+        };
+        contents.resize(capacity.max(contents.len()), Self::DARK);
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("lights"),
+            contents: bytemuck::cast_slice(&contents),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        })
+    }
+    /// only the first `len` lights, so `arrayLength` is right
+    fn bind_buffer(buffer: &Buffer, len: usize, device: &wgpu::Device) -> BindGroup {
+        let size = (len.max(1) * size_of::<LightUniform>()) as u64;
+        Self::create_bind_group(
+            device,
+            &[wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer,
+                offset: 0,
+                size: NonZero::new(size),
+            })],
+        )
+    }
+}
+
+/// lights go in a read-only storage buffer of `LightUniform`s
+impl Binding for Environment {
+    const LEVEL: Level = Level::Environment;
+    const LABEL: &'static str = "lights";
+    fn entries() -> Vec<BindGroupLayoutEntry> {
+        vec![BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }]
+    }
+}
@@ -10,6 +10,8 @@ pub const OPENGL_TO_WGPU_MATRIX: glam::Mat4 = glam::Mat4::from_cols(
 
 use wgpu::{BindGroupLayoutEntry, Device, util::DeviceExt};
 
+use crate::renderer::{Binding, Level};
+
 macro_rules! derive_camera_matrix {
     ($struct:ident) => {
         impl CameraMatrix for $struct {
@@ -92,6 +94,15 @@ impl From<(CameraConfig, f32)> for CameraData {
     }
 }
 
+/// the camera is the same for everything on the pass
+impl Binding for Camera {
+    const LEVEL: Level = Level::Global;
+    const LABEL: &'static str = "camera";
+    fn entries() -> Vec<BindGroupLayoutEntry> {
+        vec![CameraUniform::binding_generator(0)]
+    }
+}
+
 impl Camera {
     pub fn new(config: CameraConfig, aspect: f32, device: &Device) -> Self {
         let config: CameraData = (config, aspect).into();
@@ -105,18 +116,7 @@ impl Camera {
                 | wgpu::BufferUsages::COPY_SRC
                 | wgpu::BufferUsages::COPY_DST,
         });
-        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("camera"),
-            entries: &[CameraUniform::binding_generator(0)],
-        });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("camera"),
-            layout: &bgl,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-        });
+        let bind_group = Self::create_bind_group(device, &[camera_buffer.as_entire_binding()]);
 
         Self {
             eye: config.eye,
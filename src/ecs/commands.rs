@@ -0,0 +1,362 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{Component, EntityType};
+
+    struct Foo(i32);
+    impl EntityType for Foo {}
+
+    struct Bar;
+    impl EntityType for Bar {}
+
+    #[derive(Debug, PartialEq)]
+    struct Pos(f32);
+    impl Component for Pos {}
+
+    #[test]
+    fn nothing_happens_until_applied() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        let mut commands = Commands::new();
+        commands.kill(h);
+        commands.spawn(Foo(2));
+        assert!(w.entity(h).is_ok());
+        assert_eq!(w.iter::<Foo>().count(), 1);
+        assert_eq!(commands.len(), 2);
+
+        commands.apply(&mut w).unwrap();
+        assert!(commands.is_empty());
+        assert!(w.entity(h).is_err());
+        assert_eq!(
+            w.iter::<Foo>().map(|(_, f)| f.0).collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn apply_returns_spawned_handles_in_order() {
+        let mut w = World::new();
+        let mut commands = Commands::new();
+        let a = commands.spawn(Foo(1));
+        let b = commands.spawn(Foo(2));
+        assert!(matches!(a, Target::Pending { index: 0, .. }));
+        assert!(matches!(b, Target::Pending { index: 1, .. }));
+
+        let spawned = commands.apply(&mut w).unwrap();
+        assert_eq!(spawned.len(), 2);
+        assert_eq!(
+            w.entity(spawned[0]).unwrap().downcast::<Foo>().unwrap().0,
+            1
+        );
+        assert_eq!(
+            w.entity(spawned[1]).unwrap().downcast::<Foo>().unwrap().0,
+            2
+        );
+    }
+
+    #[test]
+    fn spawn_then_attach() {
+        let mut w = World::new();
+        let parent = w.spawn(Bar);
+        let mut commands = Commands::new();
+        let child = commands.spawn(Foo(5));
+        commands.attach_child::<Foo>(parent, child);
+        let spawned = commands.apply(&mut w).unwrap();
+
+        let kids: Vec<_> = w.children_of::<Foo>(parent).unwrap().collect();
+        assert_eq!(kids.len(), 1);
+        assert_eq!(kids[0].0, spawned[0]);
+        assert_eq!(kids[0].1.0, 5);
+        assert_eq!(w.entity(spawned[0]).unwrap().parent, parent);
+    }
+
+    #[test]
+    fn spawn_then_attach_between_pending() {
+        let mut w = World::new();
+        let mut commands = Commands::new();
+        let parent = commands.spawn(Bar);
+        let child = commands.spawn(Foo(1));
+        commands.attach_child::<Foo>(parent, child);
+        commands.insert(child, Pos(3.));
+        let spawned = commands.apply(&mut w).unwrap();
+
+        assert_eq!(w.entity(spawned[1]).unwrap().parent, spawned[0]);
+        assert_eq!(w.get::<Pos>(spawned[1]).unwrap(), &Pos(3.));
+    }
+
+    #[test]
+    fn kill_then_attach_is_skipped() {
+        let mut w = World::new();
+        let parent = w.spawn(Bar);
+        let child = w.spawn(Foo(1));
+        let mut commands = Commands::new();
+        commands.kill(parent);
+        commands.attach_child::<Foo>(parent, child);
+        commands.apply(&mut w).unwrap();
+
+        assert!(w.entity(parent).is_err());
+        // the attach never happened, so the child didn't die with its would-be parent
+        let child = w.entity(child).unwrap();
+        assert_eq!(child.parent, child.handle);
+    }
+
+    #[test]
+    fn attach_then_kill_takes_child_down() {
+        let mut w = World::new();
+        let parent = w.spawn(Bar);
+        let child = w.spawn(Foo(1));
+        let mut commands = Commands::new();
+        commands.attach_child::<Foo>(parent, child);
+        commands.kill(parent);
+        commands.apply(&mut w).unwrap();
+
+        assert!(w.entity(parent).is_err());
+        assert!(w.entity(child).is_err());
+    }
+
+    #[test]
+    fn kill_while_iterating() {
+        let mut w = World::new();
+        for i in 0..10 {
+            w.spawn(Foo(i));
+        }
+        let mut commands = Commands::new();
+        for (h, foo) in w.iter::<Foo>() {
+            if foo.0 % 2 == 0 {
+                commands.kill(h);
+            }
+        }
+        commands.apply(&mut w).unwrap();
+        let mut left: Vec<_> = w.iter::<Foo>().map(|(_, f)| f.0).collect();
+        left.sort();
+        assert_eq!(left, vec![1, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn double_kill_is_skipped() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        let mut commands = Commands::new();
+        commands.kill(h);
+        commands.kill(h);
+        commands.apply(&mut w).unwrap();
+        assert!(w.entity(h).is_err());
+    }
+
+    #[test]
+    fn remove_child_detaches() {
+        let mut w = World::new();
+        let parent = w.spawn(Bar);
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+        let mut commands = Commands::new();
+        commands.remove_child(parent, child);
+        commands.apply(&mut w).unwrap();
+        assert_eq!(w.entity(child).unwrap().parent, child);
+        assert_eq!(w.children_of::<Foo>(parent).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn pending_targets_stay_in_their_queue() {
+        let mut w = World::new();
+        let mut first = Commands::new();
+        let mut second = Commands::new();
+        let theirs = first.spawn(Foo(1));
+        second.spawn(Foo(2));
+        second.insert(theirs, Pos(1.));
+        second.apply(&mut w).unwrap();
+        assert_eq!(w.query::<&Pos>().count(), 0);
+
+        // and targets from before an apply don't carry over to the next batch
+        first.apply(&mut w).unwrap();
+        first.spawn(Foo(3));
+        first.insert(theirs, Pos(2.));
+        first.apply(&mut w).unwrap();
+        assert_eq!(w.query::<&Pos>().count(), 0);
+        assert_eq!(w.iter::<Foo>().count(), 3);
+    }
+
+    #[test]
+    fn structural_error_reported_after_rest_applied() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let mut commands = Commands::new();
+        commands.attach_child::<Foo>(a, a);
+        let b = commands.spawn(Foo(2));
+        commands.insert(b, Pos(1.));
+        assert!(matches!(
+            commands.apply(&mut w),
+            Err(Error::InvalidStructure)
+        ));
+        assert_eq!(w.iter::<Foo>().count(), 2);
+        assert_eq!(w.query::<&Pos>().count(), 1);
+        assert!(commands.is_empty());
+    }
+}
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{Component, EntityType, Error, Handle, World};
+
+/// an entity a command refers to: one that already exists, or one spawned earlier in the same
+/// `Commands`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Target {
+    Live(Handle),
+    /// the `index`th `spawn` recorded in the queue with id `queue`
+    Pending {
+        queue: u64,
+        index: usize,
+    },
+}
+
+impl From<Handle> for Target {
+    fn from(handle: Handle) -> Self {
+        Self::Live(handle)
+    }
+}
+
+impl Target {
+    /// a pending target from some other queue, or from before this one was last applied, is
+    /// `Dead`
+    fn resolve(self, spawned: &Spawned) -> Result<Handle, Error> {
+        match self {
+            Self::Live(handle) => Ok(handle),
+            Self::Pending { queue, index } if queue == spawned.queue => {
+                spawned.handles.get(index).copied().ok_or(Error::Dead)
+            }
+            Self::Pending { .. } => Err(Error::Dead),
+        }
+    }
+}
+
+/// what's been spawned so far while applying the queue with id `queue`
+struct Spawned {
+    queue: u64,
+    handles: Vec<Handle>,
+}
+
+type Command = Box<dyn FnOnce(&mut World, &mut Spawned) -> Result<(), Error> + Send>;
+
+/// a new id for every queue, and every time one is applied
+fn next_queue_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// structural changes to a `World`, recorded now and applied later with `apply`.
+///
+/// lets you spawn, kill and re-parent entities while iterating over them.
+/// every system gets its own from the `Schedule`, which applies them at the end of every stage
+pub struct Commands {
+    queue: Vec<Command>,
+    spawns: usize,
+    /// what this queue's `Target::Pending`s are tagged with
+    id: u64,
+}
+
+impl Default for Commands {
+    fn default() -> Self {
+        Self {
+            queue: Vec::new(),
+            spawns: 0,
+            id: next_queue_id(),
+        }
+    }
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(
+        &mut self,
+        command: impl FnOnce(&mut World, &mut Spawned) -> Result<(), Error> + Send + 'static,
+    ) {
+        self.queue.push(Box::new(command));
+    }
+
+    /// spawns `value`. the returned target can be used by later commands in this queue
+    pub fn spawn<T: EntityType<DowncastType = T>>(&mut self, value: T) -> Target {
+        self.spawn_marker::<T>(value)
+    }
+    pub fn spawn_marker<T: EntityType>(&mut self, value: T::DowncastType) -> Target {
+        self.push(move |world, spawned| {
+            spawned.handles.push(world.spawn_marker::<T>(value));
+            Ok(())
+        });
+        self.spawns += 1;
+        Target::Pending {
+            queue: self.id,
+            index: self.spawns - 1,
+        }
+    }
+    pub fn kill(&mut self, target: impl Into<Target>) {
+        let target = target.into();
+        self.push(move |world, spawned| world.kill(target.resolve(spawned)?));
+    }
+    pub fn attach_child<T: EntityType>(
+        &mut self,
+        parent: impl Into<Target>,
+        child: impl Into<Target>,
+    ) {
+        let (parent, child) = (parent.into(), child.into());
+        self.push(move |world, spawned| {
+            world
+                .attach_child::<T>(parent.resolve(spawned)?, child.resolve(spawned)?)
+                .map(drop)
+        });
+    }
+    pub fn remove_child(&mut self, parent: impl Into<Target>, child: impl Into<Target>) {
+        let (parent, child) = (parent.into(), child.into());
+        self.push(move |world, spawned| {
+            let child = child.resolve(spawned)?;
+            let child_ty = world.entity(child)?.ty;
+            world.remove_child(parent.resolve(spawned)?, child, child_ty)
+        });
+    }
+    /// inserts a component, replacing (and dropping) any it already had
+    pub fn insert<C: Component>(&mut self, target: impl Into<Target>, component: C) {
+        let target = target.into();
+        self.push(move |world, spawned| {
+            world.insert(target.resolve(spawned)?, component).map(drop)
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// runs every recorded command in order and empties the queue.
+    /// returns the handles of the spawned entities, in the order they were recorded.
+    ///
+    /// commands on entities that are dead by then (e.g. killed earlier in the queue) are
+    /// skipped, as are ones on pending targets from other queues. any other failure doesn't stop
+    /// the rest of the queue; the first one is returned once everything has been applied.
+    /// targets this queue handed out before are stale afterwards
+    pub fn apply(&mut self, world: &mut World) -> Result<Vec<Handle>, Error> {
+        let mut spawned = Spawned {
+            queue: self.id,
+            handles: Vec::with_capacity(self.spawns),
+        };
+        let mut first_error = None;
+        for command in self.queue.drain(..) {
+            match command(world, &mut spawned) {
+                Ok(()) | Err(Error::Dead) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        self.spawns = 0;
+        self.id = next_queue_id();
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(spawned.handles),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! entity types and components the scene and prefab tests share
+
+use serde::{Deserialize, Serialize};
+
+use super::{Component, EntityType, SceneRegistry};
+
+/// value stored as something else, like `spawn_marker` allows
+pub(crate) struct Light;
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct LightValue {
+    pub strength: f32,
+}
+impl EntityType for Light {
+    type DowncastType = LightValue;
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct Health(pub u32);
+impl Component for Health {}
+
+/// knows `Light` as "light" and `Health` as "health". add the test's own types to it
+pub(crate) fn registry() -> SceneRegistry {
+    let mut registry = SceneRegistry::new();
+    registry.register::<Light>("light");
+    registry.register_component::<Health>("health");
+    registry
+}
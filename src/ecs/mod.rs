@@ -0,0 +1,2223 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // ── Test EntityType implementations ─────────────────────────────────────
+
+    struct Foo(i32);
+    impl EntityType for Foo {}
+
+    struct Bar(String);
+    impl EntityType for Bar {}
+
+    /// Lets us observe whether drop was called
+    struct Tracked(Arc<Mutex<Vec<&'static str>>>, &'static str);
+    impl EntityType for Tracked {}
+    impl Component for Tracked {}
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push(self.1);
+        }
+    }
+
+    // / EntityType where DowncastType differs from Self
+    struct Wrapper;
+    struct Inner(u64);
+    impl EntityType for Wrapper {
+        type DowncastType = Inner;
+    }
+    // spawn_marker::<Wrapper> requires value: Wrapper; but ptr stores Inner.
+    // We test this path by keeping DowncastType=Self for most tests and only
+    // verifying the type check uses TypeId::of::<T>() vs TypeId::of::<T::DowncastType>().
+
+    #[derive(Debug, PartialEq)]
+    struct Pos(f32);
+    impl Component for Pos {}
+
+    #[derive(Debug, PartialEq)]
+    struct Vel(f32);
+    impl Component for Vel {}
+
+    // ── Helpers ─────────────────────────────────────────────────────────────
+
+    fn drop_log() -> Arc<Mutex<Vec<&'static str>>> {
+        Arc::new(Mutex::new(Vec::new()))
+    }
+
+    // ── Handle ───────────────────────────────────────────────────────────────
+
+    #[test]
+    fn handle_index_and_generation() {
+        let h = Handle::new(42, 7);
+        assert_eq!(h.index(), 42);
+        assert_eq!(h.generation(), 7);
+    }
+
+    #[test]
+    fn handle_increment_changes_generation_not_index() {
+        let mut h = Handle::new(100, 0);
+        h.increment();
+        assert_eq!(h.index(), 100);
+        assert_eq!(h.generation(), 1);
+        h.increment();
+        assert_eq!(h.generation(), 2);
+    }
+
+    #[test]
+    fn handle_new_round_trips() {
+        let h = Handle::new(0xDEAD, 0xBEEF);
+        assert_eq!(h.index(), 0xDEAD);
+        assert_eq!(h.generation(), 0xBEEF & 0xFFFFF); // generation is 20 bits
+    }
+
+    // ── Spawn / entity retrieval ─────────────────────────────────────────────
+
+    #[test]
+    fn spawn_returns_live_handle() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        assert!(w.entity(h).is_ok());
+    }
+
+    #[test]
+    fn spawn_multiple_unique_handles() {
+        let mut w = World::new();
+        let h1 = w.spawn(Foo(1));
+        let h2 = w.spawn(Foo(2));
+        let h3 = w.spawn(Bar("x".into()));
+        assert_ne!(h1, h2);
+        assert_ne!(h1, h3);
+        assert_ne!(h2, h3);
+    }
+
+    #[test]
+    fn entity_mut_round_trips() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(99));
+        let e = w.entity_mut(h).unwrap();
+        assert_eq!(e.downcast::<Foo>().unwrap().0, 99);
+    }
+
+    #[test]
+    fn spawned_entity_is_self_parented() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        let e = w.entity(h).unwrap();
+        assert_eq!(e.parent, h);
+    }
+
+    #[test]
+    fn spawned_entity_has_no_children() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        assert!(w.entity(h).unwrap().children.is_empty());
+    }
+
+    // ── Downcast ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn downcast_correct_type() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(42));
+        let v = w.entity(h).unwrap().downcast::<Foo>().unwrap();
+        assert_eq!(v.0, 42);
+    }
+
+    #[test]
+    fn downcast_wrong_type_returns_error() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        let err = w.entity(h).unwrap().downcast::<Bar>();
+        assert!(matches!(err, Err(Error::WrongType(_))));
+    }
+
+    #[test]
+    fn downcast_mut_modifies_value() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        w.entity_mut(h).unwrap().downcast_mut::<Foo>().unwrap().0 = 77;
+        assert_eq!(w.entity(h).unwrap().downcast::<Foo>().unwrap().0, 77);
+    }
+
+    // ── Kill ─────────────────────────────────────────────────────────────────
+
+    #[test]
+    fn kill_invalidates_handle() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        w.kill(h).unwrap();
+        assert!(matches!(w.entity(h), Err(Error::Dead)));
+    }
+
+    #[test]
+    fn kill_drop_fn_is_called() {
+        let log = drop_log();
+        let mut w = World::new();
+        let h = w.spawn(Tracked(log.clone(), "a"));
+        assert!(log.lock().unwrap().is_empty());
+        w.kill(h).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a"]);
+    }
+
+    #[test]
+    fn kill_dead_handle_returns_error() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        w.kill(h).unwrap();
+        assert!(matches!(w.kill(h), Err(Error::Dead)));
+    }
+
+    #[test]
+    fn kill_recursively_drops_children() {
+        let log = drop_log();
+        let mut w = World::new();
+
+        let parent = w.spawn(Tracked(log.clone(), "parent"));
+        let child1 = w.spawn(Tracked(log.clone(), "child1"));
+        let child2 = w.spawn(Tracked(log.clone(), "child2"));
+
+        w.attach_child::<Tracked>(parent, child1).unwrap();
+        w.attach_child::<Tracked>(parent, child2).unwrap();
+
+        w.kill(parent).unwrap();
+
+        let mut dropped = log.lock().unwrap().clone();
+        dropped.sort();
+        assert_eq!(dropped, vec!["child1", "child2", "parent"]);
+
+        assert!(matches!(w.entity(parent), Err(Error::Dead)));
+        assert!(matches!(w.entity(child1), Err(Error::Dead)));
+        assert!(matches!(w.entity(child2), Err(Error::Dead)));
+    }
+
+    #[test]
+    fn kill_deep_tree_drops_all() {
+        let log = drop_log();
+        let mut w = World::new();
+
+        let root = w.spawn(Tracked(log.clone(), "root"));
+        let mid = w.spawn(Tracked(log.clone(), "mid"));
+        let leaf = w.spawn(Tracked(log.clone(), "leaf"));
+
+        w.attach_child::<Tracked>(root, mid).unwrap();
+        w.attach_child::<Tracked>(mid, leaf).unwrap();
+
+        w.kill(root).unwrap();
+
+        let mut dropped = log.lock().unwrap().clone();
+        dropped.sort();
+        assert_eq!(dropped, vec!["leaf", "mid", "root"]);
+    }
+
+    #[test]
+    fn kill_child_detaches_from_parent() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+
+        w.kill(child).unwrap();
+
+        let pe = w.entity(parent).unwrap();
+        assert!(pe.children.is_empty());
+        assert!(pe.type_map.is_empty());
+    }
+
+    #[test]
+    fn kill_removes_parent_from_world_types() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+
+        let child_ty = TypeId::of::<Foo>();
+        assert!(
+            w.types
+                .get(&child_ty)
+                .map_or(false, |v| v.contains(&parent))
+        );
+
+        w.kill(child).unwrap();
+        let still_listed = w
+            .types
+            .get(&child_ty)
+            .map_or(false, |v| v.contains(&parent));
+        assert!(
+            !still_listed,
+            "parent should be removed from world.types after last child killed"
+        );
+    }
+
+    // ── Slot reuse after kill ────────────────────────────────────────────────
+
+    #[test]
+    fn slot_reused_after_kill_old_handle_stays_dead() {
+        let mut w = World::new();
+        let h1 = w.spawn(Foo(1));
+        w.kill(h1).unwrap();
+
+        let h2 = w.spawn(Foo(2)); // should reuse h1's slot
+        assert_eq!(h2.index(), h1.index());
+        assert_ne!(h2.generation(), h1.generation()); // generation must differ
+
+        assert!(matches!(w.entity(h1), Err(Error::Dead)));
+        assert!(w.entity(h2).is_ok());
+    }
+
+    // ── attach_child ─────────────────────────────────────────────────────────
+
+    #[test]
+    fn attach_child_basic() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+
+        let was_new = w.attach_child::<Foo>(parent, child).unwrap();
+        assert!(was_new);
+
+        let pe = w.entity(parent).unwrap();
+        assert!(pe.children.contains(&child));
+        assert_eq!(w.entity(child).unwrap().parent, parent);
+    }
+
+    #[test]
+    fn attach_child_already_attached_returns_false() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+
+        w.attach_child::<Foo>(parent, child).unwrap();
+        let was_new = w.attach_child::<Foo>(parent, child).unwrap();
+        assert!(!was_new);
+    }
+
+    #[test]
+    fn attach_child_updates_world_types() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+
+        let ty = TypeId::of::<Foo>();
+        assert!(w.types.get(&ty).map_or(false, |v| v.contains(&parent)));
+    }
+
+    #[test]
+    fn attach_child_world_types_not_duplicated() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let c1 = w.spawn(Foo(1));
+        let c2 = w.spawn(Foo(2));
+        w.attach_child::<Foo>(parent, c1).unwrap();
+        w.attach_child::<Foo>(parent, c2).unwrap();
+
+        let ty = TypeId::of::<Foo>();
+        let count = w.types[&ty].iter().filter(|&&h| h == parent).count();
+        assert_eq!(count, 1, "parent should appear at most once in world.types");
+    }
+
+    #[test]
+    fn attach_child_reparents_correctly() {
+        let mut w = World::new();
+        let p1 = w.spawn(Foo(0));
+        let p2 = w.spawn(Foo(1));
+        let c = w.spawn(Foo(2));
+
+        w.attach_child::<Foo>(p1, c).unwrap();
+        w.attach_child::<Foo>(p2, c).unwrap(); // re-parent
+
+        // c should be under p2 now
+        assert_eq!(w.entity(c).unwrap().parent, p2);
+        assert!(w.entity(p2).unwrap().children.contains(&c));
+
+        // p1 should no longer have c
+        assert!(!w.entity(p1).unwrap().children.contains(&c));
+    }
+
+    #[test]
+    fn attach_child_reparent_cleans_old_parent_type_map() {
+        let mut w = World::new();
+        let p1 = w.spawn(Foo(0));
+        let p2 = w.spawn(Foo(1));
+        let c = w.spawn(Foo(2));
+
+        w.attach_child::<Foo>(p1, c).unwrap();
+        w.attach_child::<Foo>(p2, c).unwrap();
+
+        let ty = TypeId::of::<Foo>();
+        // p1 should no longer appear in world.types for Foo
+        let p1_listed = w.types.get(&ty).map_or(false, |v| v.contains(&p1));
+        assert!(!p1_listed);
+    }
+
+    #[test]
+    fn attach_child_multiple_types_tracked_separately() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let cf = w.spawn(Foo(1));
+        let cb = w.spawn(Bar("x".into()));
+
+        w.attach_child::<Foo>(parent, cf).unwrap();
+        w.attach_child::<Bar>(parent, cb).unwrap();
+
+        let foo_ty = TypeId::of::<Foo>();
+        let bar_ty = TypeId::of::<Bar>();
+        assert!(w.types.get(&foo_ty).map_or(false, |v| v.contains(&parent)));
+        assert!(w.types.get(&bar_ty).map_or(false, |v| v.contains(&parent)));
+    }
+
+    #[test]
+    fn attach_child_type_map_indices_correct() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let c1 = w.spawn(Foo(1));
+        let c2 = w.spawn(Bar("x".into()));
+        let c3 = w.spawn(Foo(3));
+
+        w.attach_child::<Foo>(parent, c1).unwrap(); // children[0]
+        w.attach_child::<Bar>(parent, c2).unwrap(); // children[1]
+        w.attach_child::<Foo>(parent, c3).unwrap(); // children[2]
+
+        let pe = w.entity(parent).unwrap();
+        let foo_indices = pe.type_map.get(&TypeId::of::<Foo>()).unwrap();
+        let mut fi = foo_indices.clone();
+        fi.sort();
+        assert_eq!(fi, vec![0, 2]);
+
+        let bar_indices = pe.type_map.get(&TypeId::of::<Bar>()).unwrap();
+        assert_eq!(*bar_indices, vec![1]);
+    }
+
+    // ── Cycle detection ──────────────────────────────────────────────────────
+
+    #[test]
+    fn attach_child_direct_cycle_rejected() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(0));
+        let b = w.spawn(Foo(1));
+        w.attach_child::<Foo>(a, b).unwrap();
+
+        // trying to make b the parent of a would loop
+        let err = w.attach_child::<Foo>(b, a);
+        assert!(matches!(err, Err(Error::InvalidStructure)));
+    }
+
+    #[test]
+    fn attach_child_indirect_cycle_rejected() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(0));
+        let b = w.spawn(Foo(1));
+        let c = w.spawn(Foo(2));
+
+        w.attach_child::<Foo>(a, b).unwrap(); // a → b
+        w.attach_child::<Foo>(b, c).unwrap(); // b → c
+
+        // c → a would create a → b → c → a loop
+        let err = w.attach_child::<Foo>(c, a);
+        assert!(matches!(err, Err(Error::InvalidStructure)));
+    }
+
+    #[test]
+    fn attach_self_as_child_rejected() {
+        // A self-parented root must not become its own child (immediate cycle)
+        let mut w = World::new();
+        let a = w.spawn(Foo(0));
+        let err = w.attach_child::<Foo>(a, a);
+        assert!(matches!(err, Err(Error::InvalidStructure)));
+    }
+
+    // ── remove_child ─────────────────────────────────────────────────────────
+
+    #[test]
+    fn remove_child_basic() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+
+        w.remove_child(parent, child, TypeId::of::<Foo>()).unwrap();
+
+        let pe = w.entity(parent).unwrap();
+        assert!(!pe.children.contains(&child));
+        assert!(
+            pe.type_map
+                .get(&TypeId::of::<Foo>())
+                .map_or(true, |v| v.is_empty())
+        );
+        assert_eq!(w.entity(child).unwrap().parent, child); // child becomes root
+    }
+
+    #[test]
+    fn remove_child_cleans_world_types_when_last() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+
+        w.remove_child(parent, child, TypeId::of::<Foo>()).unwrap();
+
+        let ty = TypeId::of::<Foo>();
+        let still_listed = w.types.get(&ty).map_or(false, |v| v.contains(&parent));
+        assert!(!still_listed);
+    }
+
+    #[test]
+    fn remove_child_keeps_world_types_when_sibling_remains() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let c1 = w.spawn(Foo(1));
+        let c2 = w.spawn(Foo(2));
+        w.attach_child::<Foo>(parent, c1).unwrap();
+        w.attach_child::<Foo>(parent, c2).unwrap();
+
+        w.remove_child(parent, c1, TypeId::of::<Foo>()).unwrap();
+
+        let ty = TypeId::of::<Foo>();
+        assert!(
+            w.types.get(&ty).map_or(false, |v| v.contains(&parent)),
+            "parent should stay in world.types while it still has a Foo child"
+        );
+    }
+
+    #[test]
+    fn remove_middle_child_swap_remove_fixup() {
+        // attach 3 Foo children; remove the middle one.
+        // The swap_remove moves children[2] → children[1].
+        // The type_map index for the moved element must update from 2 → 1.
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let c0 = w.spawn(Foo(10));
+        let c1 = w.spawn(Foo(20));
+        let c2 = w.spawn(Foo(30));
+
+        w.attach_child::<Foo>(parent, c0).unwrap(); // children[0]
+        w.attach_child::<Foo>(parent, c1).unwrap(); // children[1]
+        w.attach_child::<Foo>(parent, c2).unwrap(); // children[2]
+
+        w.remove_child(parent, c1, TypeId::of::<Foo>()).unwrap();
+
+        let pe = w.entity(parent).unwrap();
+        // After swap_remove of index 1, c2 lands at index 1
+        assert_eq!(pe.children.len(), 2);
+        assert!(pe.children.contains(&c0));
+        assert!(pe.children.contains(&c2));
+        assert!(!pe.children.contains(&c1));
+
+        // type_map must reflect the new positions exactly
+        let mut indices = pe.type_map[&TypeId::of::<Foo>()].clone();
+        indices.sort();
+        let expected: Vec<usize> = pe
+            .children
+            .iter()
+            .enumerate()
+            .filter(|&(_, &h)| h == c0 || h == c2)
+            .map(|(i, _)| i)
+            .collect();
+        let mut expected = expected;
+        expected.sort();
+        assert_eq!(indices, expected);
+    }
+
+    #[test]
+    fn remove_child_missing_returns_error() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let stray = w.spawn(Foo(1));
+        let err = w.remove_child(parent, stray, TypeId::of::<Foo>());
+        assert!(matches!(err, Err(Error::Missing)));
+    }
+
+    #[test]
+    fn remove_child_dead_parent_returns_error() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+        // kill parent first — child's remove_child target is now dead
+        // (we have to bypass the normal API; use the raw dead handle directly)
+        let dead_parent = parent;
+        w.kill(parent).unwrap();
+        let err = w.remove_child(dead_parent, child, TypeId::of::<Foo>());
+        assert!(matches!(err, Err(Error::Dead)));
+    }
+
+    // ── Dead handle rejection ────────────────────────────────────────────────
+
+    #[test]
+    fn entity_dead_handle_rejected() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        w.kill(h).unwrap();
+        assert!(matches!(w.entity(h), Err(Error::Dead)));
+    }
+
+    #[test]
+    fn entity_mut_dead_handle_rejected() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        w.kill(h).unwrap();
+        assert!(matches!(w.entity_mut(h), Err(Error::Dead)));
+    }
+
+    #[test]
+    fn stale_handle_after_slot_reuse_rejected() {
+        let mut w = World::new();
+        let h1 = w.spawn(Foo(1));
+        w.kill(h1).unwrap();
+        let h2 = w.spawn(Foo(2)); // reuses slot, higher generation
+        assert_eq!(h2.index(), h1.index());
+        assert!(matches!(w.entity(h1), Err(Error::Dead)));
+        assert!(w.entity(h2).is_ok());
+    }
+
+    // ── Out of range / exhausted handles ─────────────────────────────────────
+
+    #[test]
+    fn out_of_range_handle_is_dead() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        let far = Handle::new(1000, 0);
+        assert!(!w.is_alive(far));
+        assert!(matches!(w.entity(far), Err(Error::Dead)));
+        assert!(matches!(w.entity_mut(far), Err(Error::Dead)));
+        assert!(matches!(w.kill(far), Err(Error::Dead)));
+        assert!(matches!(w.attach_child::<Foo>(h, far), Err(Error::Dead)));
+        assert!(matches!(w.attach_child::<Foo>(far, h), Err(Error::Dead)));
+        assert!(matches!(
+            w.remove_child(far, h, TypeId::of::<Foo>()),
+            Err(Error::Dead)
+        ));
+        assert!(matches!(w.insert(far, Pos(0.)), Err(Error::Dead)));
+        assert!(matches!(w.get::<Pos>(far), Err(Error::Dead)));
+        assert!(matches!(w.has::<Pos>(far), Err(Error::Dead)));
+        assert!(w.children_of::<Foo>(far).is_err());
+        assert!(w.is_alive(h));
+    }
+
+    #[test]
+    fn is_alive_tracks_kills() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(1));
+        let child = w.spawn(Foo(2));
+        w.attach_child::<Foo>(parent, child).unwrap();
+        assert!(w.is_alive(parent) && w.is_alive(child));
+        w.kill(parent).unwrap();
+        assert!(!w.is_alive(parent));
+        assert!(!w.is_alive(child));
+        let reused = w.spawn(Foo(3));
+        assert!(w.is_alive(reused));
+        assert!(!w.is_alive(parent) && !w.is_alive(child));
+    }
+
+    #[test]
+    fn exhausted_slot_is_retired() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        w.kill(h).unwrap();
+        // fast-forward the slot to its last generation
+        let last = Handle::new(h.index(), MAX_GENERATION);
+        w.free_handles = vec![last];
+        w.entities[last].handle = last;
+
+        let old = w.spawn(Foo(2));
+        assert_eq!(old, last);
+        w.kill(old).unwrap();
+        assert!(w.free_handles.is_empty());
+
+        // the slot is never reused, and no generation of it resurrects
+        let fresh = w.spawn(Foo(3));
+        assert_ne!(fresh.index(), h.index());
+        for generation in [0, 1, MAX_GENERATION] {
+            let stale = Handle::new(h.index(), generation);
+            assert!(!w.is_alive(stale));
+            assert!(matches!(w.entity(stale), Err(Error::Dead)));
+        }
+        assert!(matches!(w.entity(RETIRED), Err(Error::Dead)));
+        assert_eq!(w.iter::<Foo>().count(), 1);
+    }
+
+    #[test]
+    fn query_skips_retired_slots() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        w.insert(h, Pos(0.)).unwrap();
+        w.kill(h).unwrap();
+        let last = Handle::new(h.index(), MAX_GENERATION);
+        w.free_handles = vec![last];
+        w.entities[last].handle = last;
+        let h = w.spawn(Foo(2));
+        w.insert(h, Pos(0.)).unwrap();
+        w.kill(h).unwrap();
+        assert_eq!(w.query::<Option<&Pos>>().count(), 0);
+        assert_eq!(w.query::<&Pos>().count(), 0);
+    }
+
+    // ── Change detection ─────────────────────────────────────────────────────
+
+    fn changed<T: EntityType>(w: &World) -> Vec<Handle> {
+        let mut hs: Vec<Handle> = w.query_changed::<T>().map(|(h, _)| h).collect();
+        hs.sort_by_key(|h| h.index());
+        hs
+    }
+
+    #[test]
+    fn spawned_values_count_as_changed() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let b = w.spawn(Foo(2));
+        assert_eq!(changed::<Foo>(&w), vec![a, b]);
+        w.advance_tick();
+        assert!(changed::<Foo>(&w).is_empty());
+    }
+
+    #[test]
+    fn downcast_mut_marks_changed() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let b = w.spawn(Foo(2));
+        w.advance_tick();
+
+        // reading doesn't count
+        w.entity(a).unwrap().downcast::<Foo>().unwrap();
+        w.entity_mut(a).unwrap().downcast::<Foo>().unwrap();
+        assert!(changed::<Foo>(&w).is_empty());
+
+        w.entity_mut(b).unwrap().downcast_mut::<Foo>().unwrap().0 = 5;
+        let hits: Vec<_> = w.query_changed::<Foo>().map(|(h, f)| (h, f.0)).collect();
+        assert_eq!(hits, vec![(b, 5)]);
+        assert!(changed::<Bar>(&w).is_empty());
+    }
+
+    #[test]
+    fn iter_mut_marks_everything() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let b = w.spawn(Foo(2));
+        w.spawn(Bar("x".into()));
+        w.advance_tick();
+        for _ in w.iter_mut::<Foo>() {}
+        assert_eq!(changed::<Foo>(&w), vec![a, b]);
+        assert!(changed::<Bar>(&w).is_empty());
+    }
+
+    #[test]
+    fn changed_since_older_tick() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let since = w.change_tick();
+        w.advance_tick();
+        let b = w.spawn(Foo(2));
+        w.advance_tick();
+        assert!(changed::<Foo>(&w).is_empty());
+        let mut hits: Vec<_> = w
+            .query_changed_since::<Foo>(since)
+            .map(|(h, _)| h)
+            .collect();
+        hits.sort_by_key(|h| h.index());
+        assert_eq!(hits, vec![a, b]);
+        let hits: Vec<_> = w
+            .query_changed_since::<Foo>(since + 1)
+            .map(|(h, _)| h)
+            .collect();
+        assert_eq!(hits, vec![b]);
+    }
+
+    #[test]
+    fn change_ticks_follow_swap_remove() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        w.spawn(Foo(2));
+        let c = w.spawn(Foo(3));
+        w.advance_tick();
+        w.entity_mut(c).unwrap().downcast_mut::<Foo>().unwrap();
+        // c's row moves into a's spot
+        w.kill(a).unwrap();
+        assert_eq!(changed::<Foo>(&w), vec![c]);
+    }
+
+    fn changed_pos(w: &World) -> Vec<Handle> {
+        let mut hs: Vec<Handle> = w.query_changed_component::<Pos>().map(|(h, _)| h).collect();
+        hs.sort_by_key(|h| h.index());
+        hs
+    }
+
+    #[test]
+    fn component_changes() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let b = w.spawn(Foo(2));
+        let c = w.spawn(Foo(3));
+        for h in [a, b, c] {
+            w.insert(h, Pos(0.)).unwrap();
+        }
+        w.insert(c, Vel(1.)).unwrap();
+        assert_eq!(changed_pos(&w), vec![a, b, c]);
+        w.advance_tick();
+        assert!(changed_pos(&w).is_empty());
+
+        // reading doesn't count
+        w.get::<Pos>(a).unwrap();
+        for _ in w.query::<&Pos>() {}
+        assert!(changed_pos(&w).is_empty());
+
+        w.get_mut::<Pos>(a).unwrap();
+        assert_eq!(changed_pos(&w), vec![a]);
+
+        // only what the query matched, not everything it walked past
+        for _ in w.query::<(&mut Pos, &Vel)>() {}
+        assert_eq!(changed_pos(&w), vec![a, c]);
+        w.advance_tick();
+        for _ in w.query::<(Option<&mut Pos>, &Vel)>() {}
+        assert_eq!(changed_pos(&w), vec![c]);
+    }
+
+    #[test]
+    fn bumping_keeps_the_frames_changes() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        w.insert(a, Pos(0.)).unwrap();
+        w.advance_tick();
+        w.get_mut::<Pos>(a).unwrap();
+        let since = w.bump_tick();
+        assert_eq!(changed_pos(&w), vec![a]);
+        assert_eq!(w.query_changed_component_since::<Pos>(since).count(), 0);
+        w.get_mut::<Pos>(a).unwrap();
+        assert_eq!(w.query_changed_component_since::<Pos>(since).count(), 1);
+    }
+
+    // ── Resources ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn resource_insert_get_replace_remove() {
+        let mut w = World::new();
+        assert!(matches!(w.resource::<Pos>(), Err(Error::Missing)));
+        assert!(w.insert_resource(Pos(1.)).is_none());
+        assert_eq!(w.resource::<Pos>().unwrap(), &Pos(1.));
+
+        w.resource_mut::<Pos>().unwrap().0 = 2.;
+        assert_eq!(w.insert_resource(Pos(3.)), Some(Pos(2.)));
+        assert_eq!(w.remove_resource::<Pos>().unwrap(), Pos(3.));
+        assert!(matches!(w.resource_mut::<Pos>(), Err(Error::Missing)));
+        assert!(matches!(w.remove_resource::<Pos>(), Err(Error::Missing)));
+    }
+
+    #[test]
+    fn resources_are_separate_from_components() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        w.insert(h, Pos(1.)).unwrap();
+        w.insert_resource(Pos(9.));
+        w.insert_resource(Vel(4.));
+        assert_eq!(w.get::<Pos>(h).unwrap(), &Pos(1.));
+        assert_eq!(w.resource::<Pos>().unwrap(), &Pos(9.));
+        assert_eq!(w.query::<&Pos>().count(), 1);
+
+        // a resource outlives every entity
+        w.kill(h).unwrap();
+        assert_eq!(w.resource::<Vel>().unwrap(), &Vel(4.));
+    }
+
+    #[test]
+    fn resources_drop_with_world() {
+        let log = drop_log();
+        let mut w = World::new();
+        w.insert_resource(Tracked(log.clone(), "resource"));
+        w.insert_resource(Tracked(log.clone(), "replacement"));
+        assert_eq!(*log.lock().unwrap(), vec!["resource"]);
+        drop(w);
+        assert_eq!(*log.lock().unwrap(), vec!["resource", "replacement"]);
+    }
+
+    // ── Debug dump ──────────────────────────────────────────────────────────
+
+    #[derive(Debug)]
+    struct Shown(i32);
+    impl EntityType for Shown {
+        fn preview_fn(this: &Self) -> Option<String> {
+            Some(format!("{this:?}"))
+        }
+    }
+    #[derive(Debug)]
+    struct Hp(u32);
+    impl Component for Hp {
+        fn preview_fn(this: &Self) -> Option<String> {
+            Some(format!("{this:?}"))
+        }
+    }
+    struct Tag;
+    impl Component for Tag {}
+
+    /// `dump` without the test module's path on every type
+    fn short(dump: String) -> String {
+        dump.replace("ostinato::ecs::tests::", "")
+    }
+
+    #[test]
+    fn dump_tree_shows_hierarchy_components_and_previews() {
+        let mut w = World::new();
+        let a = w.spawn(Shown(1));
+        let b = w.spawn(Foo(2));
+        let c = w.spawn(Shown(3));
+        w.spawn(Bar("x".into()));
+        w.attach_child::<Foo>(a, b).unwrap();
+        w.attach_child::<Shown>(b, c).unwrap();
+        w.insert(a, Tag).unwrap();
+        w.insert(a, Hp(10)).unwrap();
+        w.insert(c, Hp(5)).unwrap();
+        // previews show what's there now
+        w.get_mut::<Hp>(c).unwrap().0 -= 1;
+        w.entity_mut(c).unwrap().downcast_mut::<Shown>().unwrap().0 += 1;
+        let lines = |lines: &[&str]| lines.iter().map(|l| format!("{l}\n")).collect::<String>();
+        assert_eq!(
+            short(w.dump_tree()),
+            lines(&[
+                "Shown #0 gen 0: Shown(1)",
+                "  + Hp: Hp(10)",
+                "  + Tag",
+                "  Foo #1 gen 0",
+                "    Shown #2 gen 0: Shown(4)",
+                "      + Hp: Hp(4)",
+                "Bar #3 gen 0",
+            ])
+        );
+        assert_eq!(
+            short(w.dump_entity(b).unwrap()),
+            lines(&[
+                "Foo #1 gen 0",
+                "  Shown #2 gen 0: Shown(4)",
+                "    + Hp: Hp(4)"
+            ])
+        );
+    }
+
+    #[test]
+    fn dump_tree_skips_dead_and_shows_generations() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        w.kill(a).unwrap();
+        assert_eq!(w.dump_tree(), "");
+        assert!(w.dump_entity(a).is_err());
+        w.spawn(Foo(2));
+        assert_eq!(short(w.dump_tree()), "Foo #0 gen 1\n");
+    }
+
+    // ── Threads ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn world_is_send_and_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<World>();
+        check::<Handle>();
+        check::<Entity>();
+    }
+
+    // ── Complex scenarios ────────────────────────────────────────────────────
+
+    #[test]
+    fn killing_sibling_does_not_corrupt_parent_type_map() {
+        // parent has c1 and c2; kill c1; c2's index in type_map must still be valid
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let c1 = w.spawn(Foo(10));
+        let c2 = w.spawn(Foo(20));
+        w.attach_child::<Foo>(parent, c1).unwrap();
+        w.attach_child::<Foo>(parent, c2).unwrap();
+
+        w.kill(c1).unwrap();
+
+        let pe = w.entity(parent).unwrap();
+        assert_eq!(pe.children.len(), 1);
+        assert_eq!(pe.children[0], c2);
+
+        let indices = pe.type_map.get(&TypeId::of::<Foo>()).unwrap();
+        assert_eq!(*indices, vec![0]);
+    }
+
+    #[test]
+    fn many_children_kill_then_query_consistent() {
+        let log = drop_log();
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+
+        let names = ["a", "b", "c", "d", "e"];
+        let children: Vec<Handle> = names
+            .iter()
+            .map(|&n| {
+                let h = w.spawn(Tracked(log.clone(), n));
+                w.attach_child::<Tracked>(parent, h).unwrap();
+                h
+            })
+            .collect();
+
+        // Remove the middle child individually, then kill the parent
+        w.kill(children[2]).unwrap(); // kills "c"
+
+        let pe = w.entity(parent).unwrap();
+        assert_eq!(pe.children.len(), 4);
+        // type_map indices must all be in-bounds
+        for &idx in pe.type_map.get(&TypeId::of::<Tracked>()).unwrap() {
+            assert!(idx < pe.children.len());
+        }
+
+        w.kill(parent).unwrap();
+        let mut dropped = log.lock().unwrap().clone();
+        dropped.sort();
+        assert_eq!(dropped, vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn mixed_type_children_correct_isolation() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let f1 = w.spawn(Foo(1));
+        let b1 = w.spawn(Bar("x".into()));
+        let f2 = w.spawn(Foo(2));
+        let b2 = w.spawn(Bar("y".into()));
+
+        w.attach_child::<Foo>(parent, f1).unwrap();
+        w.attach_child::<Bar>(parent, b1).unwrap();
+        w.attach_child::<Foo>(parent, f2).unwrap();
+        w.attach_child::<Bar>(parent, b2).unwrap();
+
+        // Remove one Foo; Bar indices must be untouched
+        let bar_indices_before = w.entity(parent).unwrap().type_map[&TypeId::of::<Bar>()].clone();
+
+        w.remove_child(parent, f1, TypeId::of::<Foo>()).unwrap();
+
+        let pe = w.entity(parent).unwrap();
+        for &idx in pe.type_map.get(&TypeId::of::<Bar>()).unwrap() {
+            assert!(
+                idx < pe.children.len(),
+                "Bar index out of bounds after removing a Foo"
+            );
+            // the handle at that index must actually be a Bar
+            let h = pe.children[idx];
+            assert!(h == b1 || h == b2);
+        }
+        // Bar count unchanged
+        assert_eq!(
+            pe.type_map[&TypeId::of::<Bar>()].len(),
+            bar_indices_before.len()
+        );
+    }
+
+    #[test]
+    fn world_types_empty_after_all_parents_killed() {
+        let mut w = World::new();
+        let p = w.spawn(Foo(0));
+        let c = w.spawn(Foo(1));
+        w.attach_child::<Foo>(p, c).unwrap();
+        w.kill(p).unwrap();
+
+        let ty = TypeId::of::<Foo>();
+        let leftover = w.types.get(&ty).map_or(0, |v| v.len());
+        assert_eq!(leftover, 0);
+    }
+
+    #[test]
+    fn reattach_to_original_parent_after_reparent() {
+        let mut w = World::new();
+        let p1 = w.spawn(Foo(0));
+        let p2 = w.spawn(Foo(1));
+        let c = w.spawn(Foo(2));
+
+        w.attach_child::<Foo>(p1, c).unwrap();
+        w.attach_child::<Foo>(p2, c).unwrap(); // p1 → p2
+        w.attach_child::<Foo>(p1, c).unwrap(); // back to p1
+
+        assert_eq!(w.entity(c).unwrap().parent, p1);
+        assert!(w.entity(p1).unwrap().children.contains(&c));
+        assert!(!w.entity(p2).unwrap().children.contains(&c));
+    }
+    // ── Bar(String) ──────────────────────────────────────────────────────────
+
+    #[test]
+    fn bar_downcast_reads_string() {
+        let mut w = World::new();
+        let h = w.spawn_marker::<Bar>(Bar("hello".into()));
+        assert_eq!(w.entity(h).unwrap().downcast::<Bar>().unwrap().0, "hello");
+    }
+
+    #[test]
+    fn bar_downcast_mut_modifies_string() {
+        let mut w = World::new();
+        let h = w.spawn_marker::<Bar>(Bar("before".into()));
+        w.entity_mut(h).unwrap().downcast_mut::<Bar>().unwrap().0 = "after".into();
+        assert_eq!(w.entity(h).unwrap().downcast::<Bar>().unwrap().0, "after");
+    }
+
+    #[test]
+    fn bar_string_dropped_on_kill() {
+        let mut w = World::new();
+        let h = w.spawn_marker::<Bar>(Bar("x".repeat(10_000)));
+        w.kill(h).unwrap();
+        assert!(matches!(w.entity(h), Err(Error::Dead)));
+    }
+
+    // ── Wrapper / Inner (DowncastType != Self) ────────────────────────────────
+
+    #[test]
+    fn wrapper_downcast_reaches_inner() {
+        let mut w = World::new();
+        let h = w.spawn_marker::<Wrapper>(Inner(99));
+        assert_eq!(w.entity(h).unwrap().downcast::<Wrapper>().unwrap().0, 99);
+    }
+
+    #[test]
+    fn wrapper_downcast_mut_modifies_inner() {
+        let mut w = World::new();
+        let h = w.spawn_marker::<Wrapper>(Inner(0));
+        w.entity_mut(h)
+            .unwrap()
+            .downcast_mut::<Wrapper>()
+            .unwrap()
+            .0 = 42;
+        assert_eq!(w.entity(h).unwrap().downcast::<Wrapper>().unwrap().0, 42);
+    }
+
+    #[test]
+    fn wrapper_wrong_type_rejected() {
+        let mut w = World::new();
+        let h = w.spawn_marker::<Wrapper>(Inner(1));
+        assert!(matches!(
+            w.entity(h).unwrap().downcast::<Foo>(),
+            Err(Error::WrongType(_))
+        ));
+    }
+
+    #[test]
+    fn wrapper_ty_is_typeid_of_wrapper_not_inner() {
+        // Pins the contract: entity.ty is the label type T, not T::DowncastType.
+        // This is what makes Wrapper useful as a distinct child category from
+        // any other EntityType that also uses Inner as its DowncastType.
+        let mut w = World::new();
+        let h = w.spawn_marker::<Wrapper>(Inner(0));
+        let entity = w.entity(h).unwrap();
+        assert_eq!(entity.ty, TypeId::of::<Wrapper>());
+        assert_ne!(entity.ty, TypeId::of::<Inner>());
+    }
+
+    #[test]
+    fn wrapper_killed_without_leak() {
+        // drop_fn boxes as *mut Inner, which is what we allocated — no mismatch.
+        let mut w = World::new();
+        let h = w.spawn_marker::<Wrapper>(Inner(7));
+        w.kill(h).unwrap();
+        assert!(matches!(w.entity(h), Err(Error::Dead)));
+    }
+
+    #[test]
+    fn wrapper_label_separates_from_foo_in_type_map() {
+        // Both Wrapper and Foo children store Inner/Foo data, but they are
+        // filed under different TypeIds so queries for one don't return the other.
+        let mut w = World::new();
+        let parent = w.spawn_marker::<Foo>(Foo(0));
+        let wrapper = w.spawn_marker::<Wrapper>(Inner(1));
+        let foo = w.spawn_marker::<Foo>(Foo(2));
+
+        w.attach_child::<Wrapper>(parent, wrapper).unwrap();
+        w.attach_child::<Foo>(parent, foo).unwrap();
+
+        let pe = w.entity(parent).unwrap();
+        assert_eq!(pe.type_map[&TypeId::of::<Wrapper>()].len(), 1);
+        assert_eq!(pe.type_map[&TypeId::of::<Foo>()].len(), 1);
+
+        // The slot the type_map points to is actually the wrapper handle
+        let wrapper_idx = pe.type_map[&TypeId::of::<Wrapper>()][0];
+        assert_eq!(pe.children[wrapper_idx], wrapper);
+    }
+
+    #[test]
+    fn two_wrappers_same_parent_both_tracked() {
+        let mut w = World::new();
+        let parent = w.spawn_marker::<Foo>(Foo(0));
+        let w1 = w.spawn_marker::<Wrapper>(Inner(10));
+        let w2 = w.spawn_marker::<Wrapper>(Inner(20));
+
+        w.attach_child::<Wrapper>(parent, w1).unwrap();
+        w.attach_child::<Wrapper>(parent, w2).unwrap();
+
+        let pe = w.entity(parent).unwrap();
+        let indices = &pe.type_map[&TypeId::of::<Wrapper>()];
+        assert_eq!(indices.len(), 2);
+        let handles: Vec<Handle> = indices.iter().map(|&i| pe.children[i]).collect();
+        assert!(handles.contains(&w1));
+        assert!(handles.contains(&w2));
+    }
+
+    // ── Typed iteration ──────────────────────────────────────────────────────
+
+    #[test]
+    fn iter_yields_roots_and_children() {
+        let mut w = World::new();
+        let root = w.spawn(Foo(1));
+        let child = w.spawn(Foo(2));
+        let _bar = w.spawn(Bar("x".into()));
+        w.attach_child::<Foo>(root, child).unwrap();
+
+        let mut found: Vec<(Handle, i32)> = w.iter::<Foo>().map(|(h, f)| (h, f.0)).collect();
+        found.sort_by_key(|&(_, v)| v);
+        assert_eq!(found, vec![(root, 1), (child, 2)]);
+    }
+
+    #[test]
+    fn iter_skips_dead() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let b = w.spawn(Foo(2));
+        w.kill(a).unwrap();
+        let handles: Vec<Handle> = w.iter::<Foo>().map(|(h, _)| h).collect();
+        assert_eq!(handles, vec![b]);
+    }
+
+    #[test]
+    fn iter_empty_for_unspawned_type() {
+        let w = World::new();
+        assert_eq!(w.iter::<Foo>().count(), 0);
+    }
+
+    #[test]
+    fn iter_mut_modifies_values() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let b = w.spawn(Foo(2));
+        for (_, foo) in w.iter_mut::<Foo>() {
+            foo.0 *= 10;
+        }
+        assert_eq!(w.entity(a).unwrap().downcast::<Foo>().unwrap().0, 10);
+        assert_eq!(w.entity(b).unwrap().downcast::<Foo>().unwrap().0, 20);
+    }
+
+    #[test]
+    fn iter_uses_downcast_type() {
+        let mut w = World::new();
+        let h = w.spawn_marker::<Wrapper>(Inner(5));
+        let _foo = w.spawn(Foo(0));
+        let found: Vec<(Handle, u64)> = w.iter::<Wrapper>().map(|(h, i)| (h, i.0)).collect();
+        assert_eq!(found, vec![(h, 5)]);
+    }
+
+    #[test]
+    fn children_of_filters_by_type() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let f = w.spawn(Foo(1));
+        let b = w.spawn(Bar("b".into()));
+        let grandchild = w.spawn(Foo(2));
+        w.attach_child::<Foo>(parent, f).unwrap();
+        w.attach_child::<Bar>(parent, b).unwrap();
+        w.attach_child::<Foo>(f, grandchild).unwrap();
+
+        let foos: Vec<(Handle, i32)> = w
+            .children_of::<Foo>(parent)
+            .unwrap()
+            .map(|(h, f)| (h, f.0))
+            .collect();
+        assert_eq!(foos, vec![(f, 1)]);
+        let bars: Vec<Handle> = w
+            .children_of::<Bar>(parent)
+            .unwrap()
+            .map(|(h, _)| h)
+            .collect();
+        assert_eq!(bars, vec![b]);
+    }
+
+    #[test]
+    fn children_of_after_remove_child() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let c1 = w.spawn(Foo(1));
+        let c2 = w.spawn(Foo(2));
+        w.attach_child::<Foo>(parent, c1).unwrap();
+        w.attach_child::<Foo>(parent, c2).unwrap();
+        w.remove_child(parent, c1, TypeId::of::<Foo>()).unwrap();
+
+        let handles: Vec<Handle> = w
+            .children_of::<Foo>(parent)
+            .unwrap()
+            .map(|(h, _)| h)
+            .collect();
+        assert_eq!(handles, vec![c2]);
+        assert_eq!(w.children_of::<Bar>(parent).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn children_of_dead_parent_rejected() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        w.kill(parent).unwrap();
+        assert!(matches!(w.children_of::<Foo>(parent), Err(Error::Dead)));
+    }
+
+    // ── Components ───────────────────────────────────────────────────────────
+
+    #[test]
+    fn insert_and_get_component() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        assert_eq!(w.insert(h, Pos(1.)).unwrap(), None);
+        assert_eq!(*w.get::<Pos>(h).unwrap(), Pos(1.));
+        assert!(w.has::<Pos>(h).unwrap());
+        assert!(!w.has::<Vel>(h).unwrap());
+    }
+
+    #[test]
+    fn insert_replaces_and_returns_old_component() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        w.insert(h, Pos(1.)).unwrap();
+        assert_eq!(w.insert(h, Pos(2.)).unwrap(), Some(Pos(1.)));
+        assert_eq!(*w.get::<Pos>(h).unwrap(), Pos(2.));
+    }
+
+    #[test]
+    fn remove_component() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        w.insert(h, Pos(1.)).unwrap();
+        assert_eq!(w.remove::<Pos>(h).unwrap(), Pos(1.));
+        assert!(matches!(w.get::<Pos>(h), Err(Error::Missing)));
+        assert!(matches!(w.remove::<Pos>(h), Err(Error::Missing)));
+    }
+
+    #[test]
+    fn component_does_not_change_entity_value() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(5));
+        w.insert(h, Pos(1.)).unwrap();
+        w.get_mut::<Pos>(h).unwrap().0 = 3.;
+        assert_eq!(w.entity(h).unwrap().downcast::<Foo>().unwrap().0, 5);
+    }
+
+    #[test]
+    fn components_dead_handle_rejected() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        w.insert(h, Pos(1.)).unwrap();
+        w.kill(h).unwrap();
+        assert!(matches!(w.get::<Pos>(h), Err(Error::Dead)));
+        assert!(matches!(w.insert(h, Pos(2.)), Err(Error::Dead)));
+    }
+
+    #[test]
+    fn kill_drops_components() {
+        let log = drop_log();
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        w.insert(h, Tracked(log.clone(), "component")).unwrap();
+        w.kill(h).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["component"]);
+    }
+
+    #[test]
+    fn reused_slot_starts_without_components() {
+        let mut w = World::new();
+        let h1 = w.spawn(Foo(0));
+        w.insert(h1, Pos(1.)).unwrap();
+        w.kill(h1).unwrap();
+        let h2 = w.spawn(Foo(1));
+        assert_eq!(h2.index(), h1.index());
+        assert!(!w.has::<Pos>(h2).unwrap());
+    }
+
+    // ── Columnar storage ─────────────────────────────────────────────────────
+
+    #[test]
+    fn kill_keeps_other_values_in_column() {
+        // killing the first Foo moves the last Foo into its row
+        let mut w = World::new();
+        let a = w.spawn(Foo(1));
+        let b = w.spawn(Foo(2));
+        let c = w.spawn(Foo(3));
+        w.kill(a).unwrap();
+        assert_eq!(w.entity(b).unwrap().downcast::<Foo>().unwrap().0, 2);
+        assert_eq!(w.entity(c).unwrap().downcast::<Foo>().unwrap().0, 3);
+
+        let d = w.spawn(Foo(4));
+        w.entity_mut(c).unwrap().downcast_mut::<Foo>().unwrap().0 = 30;
+        assert_eq!(w.entity(d).unwrap().downcast::<Foo>().unwrap().0, 4);
+        assert_eq!(w.entity(c).unwrap().downcast::<Foo>().unwrap().0, 30);
+    }
+
+    #[test]
+    fn remove_component_keeps_other_rows() {
+        let mut w = World::new();
+        let hs: Vec<Handle> = (0..4)
+            .map(|i| {
+                let h = w.spawn(Foo(i));
+                w.insert(h, Pos(i as f32)).unwrap();
+                h
+            })
+            .collect();
+        w.remove::<Pos>(hs[0]).unwrap();
+        w.kill(hs[1]).unwrap();
+        assert_eq!(*w.get::<Pos>(hs[2]).unwrap(), Pos(2.));
+        assert_eq!(*w.get::<Pos>(hs[3]).unwrap(), Pos(3.));
+        assert_eq!(w.query::<&Pos>().count(), 2);
+    }
+
+    #[test]
+    fn values_of_different_types_do_not_mix() {
+        let mut w = World::new();
+        let f = w.spawn(Foo(1));
+        let b = w.spawn(Bar("b".into()));
+        let i = w.spawn_marker::<Wrapper>(Inner(3));
+        w.kill(f).unwrap();
+        assert_eq!(w.entity(b).unwrap().downcast::<Bar>().unwrap().0, "b");
+        assert_eq!(w.entity(i).unwrap().downcast::<Wrapper>().unwrap().0, 3);
+    }
+
+    // ── Query ────────────────────────────────────────────────────────────────
+
+    #[test]
+    fn query_single_component() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(0));
+        let b = w.spawn(Foo(1));
+        let _none = w.spawn(Foo(2));
+        w.insert(a, Pos(1.)).unwrap();
+        w.insert(b, Pos(2.)).unwrap();
+
+        let mut found: Vec<(Handle, f32)> = w.query::<&Pos>().map(|(h, p)| (h, p.0)).collect();
+        found.sort_by(|x, y| x.1.total_cmp(&y.1));
+        assert_eq!(found, vec![(a, 1.), (b, 2.)]);
+    }
+
+    #[test]
+    fn query_tuple_requires_all_components() {
+        let mut w = World::new();
+        let both = w.spawn(Foo(0));
+        let pos_only = w.spawn(Foo(1));
+        w.insert(both, Pos(0.)).unwrap();
+        w.insert(both, Vel(2.)).unwrap();
+        w.insert(pos_only, Pos(0.)).unwrap();
+
+        let hits: Vec<Handle> = w.query::<(&mut Pos, &Vel)>().map(|(h, _)| h).collect();
+        assert_eq!(hits, vec![both]);
+    }
+
+    #[test]
+    fn query_mut_writes_through() {
+        let mut w = World::new();
+        let h = w.spawn(Foo(0));
+        w.insert(h, Pos(1.)).unwrap();
+        w.insert(h, Vel(0.5)).unwrap();
+
+        for (_, (pos, vel)) in w.query::<(&mut Pos, &Vel)>() {
+            pos.0 += vel.0;
+        }
+        assert_eq!(*w.get::<Pos>(h).unwrap(), Pos(1.5));
+    }
+
+    #[test]
+    fn query_optional_component() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(0));
+        let b = w.spawn(Foo(1));
+        w.insert(a, Pos(0.)).unwrap();
+        w.insert(b, Pos(0.)).unwrap();
+        w.insert(b, Vel(1.)).unwrap();
+
+        let mut found: Vec<(Handle, bool)> = w
+            .query::<(&Pos, Option<&Vel>)>()
+            .map(|(h, (_, v))| (h, v.is_some()))
+            .collect();
+        found.sort_by_key(|(h, _)| h.index());
+        assert_eq!(found, vec![(a, false), (b, true)]);
+    }
+
+    #[test]
+    fn query_skips_dead_entities() {
+        let mut w = World::new();
+        let a = w.spawn(Foo(0));
+        let b = w.spawn(Foo(1));
+        w.insert(a, Pos(0.)).unwrap();
+        w.insert(b, Pos(0.)).unwrap();
+        w.kill(a).unwrap();
+
+        let hits: Vec<Handle> = w.query::<Option<&Pos>>().map(|(h, _)| h).collect();
+        assert_eq!(hits, vec![b]);
+    }
+
+    #[test]
+    fn query_includes_children() {
+        let mut w = World::new();
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+        w.insert(child, Pos(0.)).unwrap();
+
+        let hits: Vec<Handle> = w.query::<&Pos>().map(|(h, _)| h).collect();
+        assert_eq!(hits, vec![child]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn query_aliasing_mut_panics() {
+        let mut w = World::new();
+        let _ = w.query::<(&mut Pos, &Pos)>();
+    }
+}
+
+use std::any::{Any, TypeId, type_name};
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+
+mod commands;
+#[cfg(test)]
+mod fixtures;
+mod observer;
+mod prefab;
+mod query;
+mod scene;
+mod schedule;
+mod storage;
+mod transform;
+mod view;
+pub use commands::*;
+use observer::{Event, Observers};
+pub use prefab::*;
+pub use query::*;
+pub use scene::*;
+pub use schedule::*;
+use storage::{ColumnSource, Columns, column, column_mut, column_or_insert};
+pub use transform::*;
+pub use view::*;
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Handle(u64);
+impl Handle {
+    fn index(&self) -> usize {
+        (self.0 as u32) as usize
+    }
+    fn generation(&self) -> u64 {
+        (self.0 >> 32) & 0xFFFFF
+    }
+    fn with_generation(self, generation: u64) -> Self {
+        Self((self.0 & !(0xFFFFFu64 << 32)) | (generation << 32))
+    }
+    fn increment(&mut self) {
+        *self = self.with_generation(self.generation() + 1)
+    }
+    fn new(index: usize, generation: u64) -> Self {
+        Self(index as u64 & 0xFFFFFFFF | generation << 32)
+    }
+}
+
+/// the last generation a slot can have. killing an entity at this generation retires its slot
+/// for good, since incrementing would wrap around and bring old handles back to life
+const MAX_GENERATION: u64 = 0xFFFFF;
+/// stored as the handle of retired slots. its index doesn't match any slot, so no handle
+/// can compare equal to it
+const RETIRED: Handle = Handle(u64::MAX);
+
+impl<T> IndexMut<Handle> for Vec<T> {
+    fn index_mut(&mut self, index: Handle) -> &mut Self::Output {
+        &mut self[index.index()]
+    }
+}
+impl<T> Index<Handle> for Vec<T> {
+    type Output = T;
+    fn index(&self, index: Handle) -> &Self::Output {
+        &self[index.index()]
+    }
+}
+
+/// bookkeeping for one entity. its value and components live in the world's columns
+#[derive(Debug)]
+pub struct Entity {
+    parent: Handle,
+    handle: Handle,
+    /// typeid of t (the entitytype implementor), not t::downcasttype.
+    /// the value itself lives in `World::values[ty]`
+    ty: TypeId,
+    /// false once killed, until the slot is reused
+    alive: bool,
+    children: Vec<Handle>,
+    /// child typeid → indices into `self.children`
+    type_map: HashMap<TypeId, Vec<usize>, BuildHasherDefault<TypeIdHasher>>,
+}
+
+/// a live entity borrowed from a `World`. derefs to its `Entity`
+pub struct EntityRef<'w> {
+    entity: &'w Entity,
+    values: &'w Columns,
+}
+
+impl<'w> EntityRef<'w> {
+    pub fn downcast<T: EntityType>(&self) -> Result<&'w T::DowncastType, Error> {
+        if self.entity.ty != TypeId::of::<T>() {
+            return Err(Error::WrongType(self.entity.ty));
+        }
+        column::<T::DowncastType>(self.values, self.entity.ty)
+            .and_then(|c| c.get(self.entity.handle.index()))
+            .ok_or(Error::InvalidPtr)
+    }
+}
+
+impl Deref for EntityRef<'_> {
+    type Target = Entity;
+    fn deref(&self) -> &Entity {
+        self.entity
+    }
+}
+
+/// a live entity mutably borrowed from a `World`. derefs to its `Entity`
+pub struct EntityMut<'w> {
+    entity: &'w mut Entity,
+    values: &'w mut Columns,
+    /// the world's change tick, stamped on values borrowed through `downcast_mut`
+    tick: u64,
+}
+
+impl EntityMut<'_> {
+    pub fn downcast<T: EntityType>(&self) -> Result<&T::DowncastType, Error> {
+        EntityRef {
+            entity: self.entity,
+            values: self.values,
+        }
+        .downcast::<T>()
+    }
+    /// marks the value as changed, whether or not it's written to
+    pub fn downcast_mut<T: EntityType>(&mut self) -> Result<&mut T::DowncastType, Error> {
+        if self.entity.ty != TypeId::of::<T>() {
+            return Err(Error::WrongType(self.entity.ty));
+        }
+        column_mut::<T::DowncastType>(self.values, self.entity.ty)
+            .and_then(|c| c.get_mut(self.entity.handle.index(), self.tick))
+            .ok_or(Error::InvalidPtr)
+    }
+}
+
+impl Deref for EntityMut<'_> {
+    type Target = Entity;
+    fn deref(&self) -> &Entity {
+        self.entity
+    }
+}
+impl DerefMut for EntityMut<'_> {
+    fn deref_mut(&mut self) -> &mut Entity {
+        self.entity
+    }
+}
+
+pub struct World {
+    free_handles: Vec<Handle>,
+    /// child typeid → handles of parent entities that own ≥1 child of that type
+    types: HashMap<TypeId, Vec<Handle>, BuildHasherDefault<TypeIdHasher>>,
+    entities: Vec<Entity>,
+    /// entitytype typeid → every value of that type, packed
+    values: Columns,
+    /// component typeid → every component of that type, packed
+    components: Columns,
+    observers: Observers,
+    /// stamped on values when they're spawned or mutably borrowed. see `advance_tick`
+    tick: u64,
+    /// the tick `advance_tick` last started, where `query_changed` looks from
+    frame_tick: u64,
+    /// typeid → the one value of that type shared by the whole world
+    resources: Resources,
+}
+
+type Resources = HashMap<TypeId, Box<dyn Any + Send + Sync>, BuildHasherDefault<TypeIdHasher>>;
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            free_handles: Vec::new(),
+            types: HashMap::with_hasher(BuildHasherDefault::new()),
+            entities: Vec::new(),
+            values: HashMap::with_hasher(BuildHasherDefault::new()),
+            components: HashMap::with_hasher(BuildHasherDefault::new()),
+            observers: Observers::default(),
+            tick: 0,
+            frame_tick: 0,
+            resources: HashMap::with_hasher(BuildHasherDefault::new()),
+        }
+    }
+
+    /// returns `false` if `child` was already attached to `parent`.
+    /// returns `err(invalidstructure)` if attaching would create an ownership cycle.
+    /// detaches `child` from its current parent first (if it has one).
+    /// registers `parent` in `self.types[child_ty]` if not already present.
+    pub fn attach_child<T: EntityType>(
+        &mut self,
+        parent: Handle,
+        child: Handle,
+    ) -> Result<bool, Error> {
+        if parent == child {
+            return Err(Error::InvalidStructure);
+        }
+        // validate both handles
+        self.validate(parent)?;
+        self.validate(child)?;
+
+        // already attached?
+        if self.entities[child].parent == parent {
+            return Ok(false);
+        }
+
+        // cycle detection: walk the parent chain from `parent` upward.
+        // if we reach `child` before reaching a root, attaching would form a loop.
+        {
+            let mut cur = parent;
+            loop {
+                let p = self.entities[cur].parent;
+                if p == child {
+                    return Err(Error::InvalidStructure);
+                }
+                if p == cur {
+                    break; // self-parented root reached
+                }
+                cur = p;
+            }
+        }
+
+        // detach child from its current parent (if not already a root)
+        let old_parent = self.entities[child].parent;
+        if old_parent != child {
+            let child_ty = self.entities[child].ty;
+            self.remove_child(old_parent, child, child_ty)?;
+        }
+
+        // entity.ty is the canonical key; t should match it.
+        // the debug_assert catches caller mistakes in debug builds without
+        // paying the price in release.
+        let child_ty = self.entities[child].ty;
+        debug_assert_eq!(child_ty, TypeId::of::<T>());
+
+        let new_pos = self.entities[parent].children.len();
+        self.entities[parent].children.push(child);
+        self.entities[parent]
+            .type_map
+            .entry(child_ty)
+            .or_default()
+            .push(new_pos);
+
+        self.entities[child].parent = parent;
+
+        // register parent as owning a child of this type (idempotent)
+        let parents_for_type = self.types.entry(child_ty).or_default();
+        if !parents_for_type.contains(&parent) {
+            parents_for_type.push(parent);
+        }
+
+        self.notify(Event::Attach, child_ty, parent, child);
+        Ok(true)
+    }
+
+    /// detaches `child` from `parent`, making it a root, and updates all bookkeeping.
+    /// uses `swap_remove` for o(1) removal and patches up the displaced element's
+    /// `type_map` index.
+    /// removes `parent` from `self.types[child_ty]` when it has no remaining children
+    /// of that type.
+    pub fn remove_child(
+        &mut self,
+        parent: Handle,
+        child: Handle,
+        child_ty: TypeId,
+    ) -> Result<(), Error> {
+        self.unlink(parent, child, child_ty)?;
+        self.notify(Event::Detach, child_ty, parent, child);
+        Ok(())
+    }
+    /// `remove_child` without telling the observers, for kills
+    fn unlink(&mut self, parent: Handle, child: Handle, child_ty: TypeId) -> Result<(), Error> {
+        self.validate(parent)?;
+        self.validate(child)?;
+
+        let parent_idx = parent.index();
+
+        // find which slot in `children` holds this child
+        let child_pos = self.entities[parent_idx]
+            .children
+            .iter()
+            .position(|&h| h == child)
+            .ok_or(Error::Missing)?;
+
+        // remove child_pos from the type_map entry for this child's type
+        {
+            let type_indices = self.entities[parent_idx]
+                .type_map
+                .get_mut(&child_ty)
+                .ok_or(Error::Missing)?;
+            let type_pos = type_indices
+                .iter()
+                .position(|&i| i == child_pos)
+                .ok_or(Error::Missing)?;
+            type_indices.swap_remove(type_pos);
+        }
+
+        // swap_remove the child from `children`. if a different element was moved
+        // from `last_pos` into `child_pos`, patch its type_map index accordingly.
+        let last_pos = self.entities[parent_idx].children.len() - 1;
+        self.entities[parent_idx].children.swap_remove(child_pos);
+
+        if child_pos != last_pos {
+            // the element that was at `last_pos` is now at `child_pos`
+            let displaced = self.entities[parent_idx].children[child_pos];
+            let displaced_ty = self.entities[displaced].ty;
+            if let Some(indices) = self.entities[parent_idx].type_map.get_mut(&displaced_ty) {
+                if let Some(slot) = indices.iter().position(|&i| i == last_pos) {
+                    indices[slot] = child_pos;
+                }
+            }
+        }
+
+        // if parent now has no children of child_ty, clean up self.types
+        let now_empty = self.entities[parent_idx]
+            .type_map
+            .get(&child_ty)
+            .map_or(true, |v| v.is_empty());
+        if now_empty {
+            self.entities[parent_idx].type_map.remove(&child_ty);
+            if let Some(parents) = self.types.get_mut(&child_ty) {
+                if let Some(pos) = parents.iter().position(|&h| h == parent) {
+                    parents.swap_remove(pos);
+                }
+            }
+        }
+
+        // child becomes a root (self-parented)
+        self.entities[child].parent = child;
+
+        Ok(())
+    }
+
+    pub fn spawn<T: EntityType<DowncastType = T>>(&mut self, value: T) -> Handle {
+        self.spawn_marker::<T>(value)
+    }
+    /// spawns a `T` entity whose stored value is a `T::DowncastType`
+    pub fn spawn_marker<T: EntityType>(&mut self, value: T::DowncastType) -> Handle {
+        let mut extend = false;
+        let handle = self.free_handles.pop().unwrap_or_else(|| {
+            extend = true;
+            Handle::new(self.entities.len(), 0)
+        });
+
+        let ty = TypeId::of::<T>();
+        column_or_insert(
+            &mut self.values,
+            ty,
+            type_name::<T>(),
+            T::drop_fn,
+            T::preview_fn,
+        )
+        .insert(handle, value, self.tick);
+
+        let component = Entity {
+            parent: handle,
+            handle,
+            ty,
+            alive: true,
+            children: vec![],
+            type_map: HashMap::with_hasher(BuildHasherDefault::new()),
+        };
+        if extend {
+            self.entities.push(component);
+        } else {
+            self.entities[handle] = component;
+        }
+        self.notify(Event::Spawn, ty, handle, handle);
+        handle
+    }
+
+    /// detaches `handle` from its parent, unregisters it from `self.types`,
+    /// recursively kills all children, calls `drop_fn`, then retires the slot.
+    pub fn kill(&mut self, handle: Handle) -> Result<(), Error> {
+        self.validate(handle)?;
+        let ty = self.entities[handle].ty;
+        self.notify(Event::Kill, ty, handle, handle);
+
+        // detach from parent if not a root
+        let parent = self.entities[handle].parent;
+        if parent != handle {
+            self.unlink(parent, handle, ty)?;
+        }
+
+        // remove this entity from self.types (as a parent).
+        // mem::take moves the map out without cloning, leaving an empty map in place.
+        // the entity is dying so we don't need the map back.
+        let type_map = std::mem::take(&mut self.entities[handle].type_map);
+        for ty in type_map.keys() {
+            if let Some(parents) = self.types.get_mut(ty) {
+                if let Some(pos) = parents.iter().position(|&h| h == handle) {
+                    parents.swap_remove(pos);
+                }
+            }
+        }
+        // type_map is dropped here; its heap allocation is freed
+
+        // recursively kill all children.
+        // mem::take avoids cloning and is safe: the entity is being torn down.
+        // we set each child to self-parented first so the recursive kill
+        // does not attempt remove_child on `handle` (which we're mid-destroying).
+        let children = std::mem::take(&mut self.entities[handle].children);
+        for child_handle in children {
+            if self.entities[child_handle].handle == child_handle {
+                self.entities[child_handle].parent = child_handle;
+                self.kill(child_handle)?;
+            }
+        }
+        // children vec is dropped here; its heap allocation is freed
+
+        // components go with the entity
+        for column in self.components.values_mut() {
+            column.remove(handle.index());
+        }
+
+        // drop the stored value via its registered drop function
+        if let Some(column) = self.values.get_mut(&ty) {
+            column.remove(handle.index());
+        }
+        self.entities[handle].alive = false;
+
+        // retire the slot: bump the generation and add to free list.
+        // any existing handles pointing to this index now have a stale generation
+        // and will correctly fail the liveness check in entity()/entity_mut().
+        // a slot that has run out of generations is never handed out again
+        if handle.generation() < MAX_GENERATION {
+            let mut retired = handle;
+            retired.increment();
+            self.entities[handle].handle = retired;
+            self.free_handles.push(retired);
+        } else {
+            self.entities[handle].handle = RETIRED;
+        }
+
+        Ok(())
+    }
+
+    /// whether `handle` points at a live entity
+    pub fn is_alive(&self, handle: Handle) -> bool {
+        self.validate(handle).is_ok()
+    }
+    /// `Err(Dead)` unless `handle` is in range and its generation is the slot's current one
+    fn validate(&self, handle: Handle) -> Result<(), Error> {
+        match self.entities.get(handle.index()) {
+            Some(entry) if entry.handle == handle => Ok(()),
+            _ => Err(Error::Dead),
+        }
+    }
+    pub fn entity(&self, handle: Handle) -> Result<EntityRef<'_>, Error> {
+        self.validate(handle)?;
+        Ok(EntityRef {
+            entity: &self.entities[handle],
+            values: &self.values,
+        })
+    }
+    pub fn entity_mut(&mut self, handle: Handle) -> Result<EntityMut<'_>, Error> {
+        self.validate(handle)?;
+        Ok(EntityMut {
+            entity: &mut self.entities[handle],
+            values: &mut self.values,
+            tick: self.tick,
+        })
+    }
+
+    /// every live `T` entity, roots and children alike, in storage order
+    pub fn iter<T: EntityType>(&self) -> impl Iterator<Item = (Handle, &T::DowncastType)> {
+        column::<T::DowncastType>(&self.values, TypeId::of::<T>())
+            .into_iter()
+            .flat_map(|c| c.owners.iter().copied().zip(c.data.iter()))
+    }
+    /// every live `T` entity, roots and children alike, in storage order.
+    /// marks all of them as changed
+    pub fn iter_mut<T: EntityType>(
+        &mut self,
+    ) -> impl Iterator<Item = (Handle, &mut T::DowncastType)> {
+        let tick = self.tick;
+        column_mut::<T::DowncastType>(&mut self.values, TypeId::of::<T>())
+            .into_iter()
+            .flat_map(move |c| {
+                c.ticks.fill(tick);
+                c.owners.iter().copied().zip(c.data.iter_mut())
+            })
+    }
+
+    /// the current change tick. values spawned or mutably borrowed now are stamped with it
+    pub fn change_tick(&self) -> u64 {
+        self.tick
+    }
+    /// starts a new change tick, so `query_changed` forgets what changed before.
+    /// `Context` calls this once a frame, before `Stage::PreUpdate`
+    pub fn advance_tick(&mut self) {
+        self.tick += 1;
+        self.frame_tick = self.tick;
+    }
+    /// starts a new change tick without `query_changed` forgetting anything, so something
+    /// that runs more than once a frame can tell what changed since it last ran.
+    /// returns the new tick
+    pub fn bump_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+    /// every `T` spawned or mutably borrowed (through `downcast_mut` or `iter_mut`) since
+    /// the last `advance_tick`
+    pub fn query_changed<T: EntityType>(&self) -> impl Iterator<Item = (Handle, &T::DowncastType)> {
+        self.query_changed_since::<T>(self.frame_tick)
+    }
+    /// every `T` spawned or mutably borrowed at change tick `since` or later
+    pub fn query_changed_since<T: EntityType>(
+        &self,
+        since: u64,
+    ) -> impl Iterator<Item = (Handle, &T::DowncastType)> {
+        column::<T::DowncastType>(&self.values, TypeId::of::<T>())
+            .into_iter()
+            .flat_map(move |c| c.changed_since(since))
+    }
+    /// every `C` inserted or mutably borrowed (through `get_mut` or a `&mut C` query) since
+    /// the last `advance_tick`
+    pub fn query_changed_component<C: Component>(&self) -> impl Iterator<Item = (Handle, &C)> {
+        self.query_changed_component_since::<C>(self.frame_tick)
+    }
+    /// every `C` inserted or mutably borrowed at change tick `since` or later
+    pub fn query_changed_component_since<C: Component>(
+        &self,
+        since: u64,
+    ) -> impl Iterator<Item = (Handle, &C)> {
+        column::<C>(&self.components, TypeId::of::<C>())
+            .into_iter()
+            .flat_map(move |c| c.changed_since(since))
+    }
+    /// the children of `parent` that are `T`s, looked up through its `type_map`
+    pub fn children_of<T: EntityType>(
+        &self,
+        parent: Handle,
+    ) -> Result<impl Iterator<Item = (Handle, &T::DowncastType)>, Error> {
+        self.entity(parent)?;
+        let entity = &self.entities[parent];
+        let column = column::<T::DowncastType>(&self.values, TypeId::of::<T>());
+        let indices = entity
+            .type_map
+            .get(&TypeId::of::<T>())
+            .map_or(&[][..], Vec::as_slice);
+        Ok(indices.iter().filter_map(move |&i| {
+            let child = entity.children[i];
+            Some((child, column?.get(child.index())?))
+        }))
+    }
+
+    /// an indented report of every live entity: roots in storage order, each followed by its
+    /// components and then its children. every line shows the type name, slot index and
+    /// generation, plus a preview of the value for types with a `preview_fn`
+    pub fn dump_tree(&self) -> String {
+        let mut out = String::new();
+        for root in self
+            .entities
+            .iter()
+            .filter(|e| e.alive && e.parent == e.handle)
+        {
+            self.dump_into(&mut out, root.handle);
+        }
+        out
+    }
+    /// `dump_tree`, for just `handle` and everything under it
+    pub fn dump_entity(&self, handle: Handle) -> Result<String, Error> {
+        self.validate(handle)?;
+        let mut out = String::new();
+        self.dump_into(&mut out, handle);
+        Ok(out)
+    }
+    fn dump_into(&self, out: &mut String, handle: Handle) {
+        let preview = |p: Option<String>| p.map(|p| format!(": {p}")).unwrap_or_default();
+        let mut stack = vec![(handle, 0)];
+        while let Some((handle, depth)) = stack.pop() {
+            let entity = &self.entities[handle];
+            let indent = "  ".repeat(depth);
+            let value = &self.values[&entity.ty];
+            out.push_str(&format!(
+                "{indent}{} #{} gen {}{}\n",
+                value.type_name(),
+                handle.index(),
+                handle.generation(),
+                preview(value.preview(handle.index())),
+            ));
+            let mut components: Vec<_> = self
+                .components
+                .values()
+                .filter(|c| c.contains(handle.index()))
+                .collect();
+            components.sort_by_key(|c| c.type_name());
+            for c in components {
+                let shown = preview(c.preview(handle.index()));
+                out.push_str(&format!("{indent}  + {}{shown}\n", c.type_name()));
+            }
+            stack.extend(entity.children.iter().rev().map(|&c| (c, depth + 1)));
+        }
+    }
+
+    /// adds `component` to `handle`.
+    /// returns the component it replaced, if the entity already had one of this type.
+    pub fn insert<C: Component>(
+        &mut self,
+        handle: Handle,
+        component: C,
+    ) -> Result<Option<C>, Error> {
+        self.entity(handle)?;
+        Ok(column_or_insert::<C>(
+            &mut self.components,
+            TypeId::of::<C>(),
+            type_name::<C>(),
+            drop,
+            C::preview_fn,
+        )
+        .insert(handle, component, self.tick))
+    }
+    /// takes the `C` component off `handle`.
+    /// returns `err(missing)` if it doesn't have one.
+    pub fn remove<C: Component>(&mut self, handle: Handle) -> Result<C, Error> {
+        self.entity(handle)?;
+        column_mut::<C>(&mut self.components, TypeId::of::<C>())
+            .and_then(|c| c.take(handle.index()))
+            .ok_or(Error::Missing)
+    }
+    pub fn get<C: Component>(&self, handle: Handle) -> Result<&C, Error> {
+        self.entity(handle)?;
+        column::<C>(&self.components, TypeId::of::<C>())
+            .and_then(|c| c.get(handle.index()))
+            .ok_or(Error::Missing)
+    }
+    pub fn get_mut<C: Component>(&mut self, handle: Handle) -> Result<&mut C, Error> {
+        self.entity(handle)?;
+        column_mut::<C>(&mut self.components, TypeId::of::<C>())
+            .and_then(|c| c.get_mut(handle.index(), self.tick))
+            .ok_or(Error::Missing)
+    }
+    pub fn has<C: Component>(&self, handle: Handle) -> Result<bool, Error> {
+        self.entity(handle)?;
+        Ok(self
+            .components
+            .get(&TypeId::of::<C>())
+            .is_some_and(|c| c.contains(handle.index())))
+    }
+
+    /// stores `resource` as the world's only `R`, handing back the one it replaced
+    pub fn insert_resource<R: Send + Sync + 'static>(&mut self, resource: R) -> Option<R> {
+        self.resources
+            .insert(TypeId::of::<R>(), Box::new(resource))
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old)
+    }
+    pub fn remove_resource<R: Send + Sync + 'static>(&mut self) -> Result<R, Error> {
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .and_then(|r| r.downcast().ok())
+            .map(|r| *r)
+            .ok_or(Error::Missing)
+    }
+    pub fn resource<R: Send + Sync + 'static>(&self) -> Result<&R, Error> {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .and_then(|r| r.downcast_ref())
+            .ok_or(Error::Missing)
+    }
+    pub fn resource_mut<R: Send + Sync + 'static>(&mut self) -> Result<&mut R, Error> {
+        self.resources
+            .get_mut(&TypeId::of::<R>())
+            .and_then(|r| r.downcast_mut())
+            .ok_or(Error::Missing)
+    }
+
+    /// iterates every live entity that has all the components in `Q`.
+    /// e.g. `world.query::<(&Instance, &mut Velocity)>()` yields `(Handle, (&Instance, &mut Velocity))`.
+    /// `&mut` terms mark what's yielded as changed, like `get_mut`
+    ///
+    /// panics if `Q` borrows the same component mutably twice, e.g. `(&mut Foo, &Foo)`
+    pub fn query<Q: Query>(&mut self) -> QueryIter<'_, Q> {
+        QueryIter::new(
+            &self.entities,
+            ColumnSource::Whole {
+                columns: &mut self.components,
+                tick: self.tick,
+            },
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Error {
+    Dead,
+    WrongType(TypeId),
+    InvalidPtr,
+    Missing,
+    InvalidStructure,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Dead => write!(f, "x_x :: entity is dead"),
+            Self::WrongType(ty) => write!(f, "x_x :: entity isn't a {ty:?}"),
+            Self::InvalidPtr => write!(f, "x_x :: entity has no stored value"),
+            Self::Missing => write!(f, "x_x :: entity doesn't have that component"),
+            Self::InvalidStructure => write!(f, "x_x :: invalid entity structure"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+#[derive(Default)]
+pub struct TypeIdHasher {
+    hash: u64,
+}
+
+impl Hasher for TypeIdHasher {
+    fn write_u64(&mut self, n: u64) {
+        debug_assert_eq!(self.hash, 0);
+        self.hash = n;
+    }
+    fn write_u128(&mut self, n: u128) {
+        debug_assert_eq!(self.hash, 0);
+        self.hash = (n as u64) ^ ((n >> 64) as u64);
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        debug_assert_eq!(self.hash, 0);
+        let mut hash = 0u64;
+        for &b in bytes {
+            hash = hash.wrapping_mul(131).wrapping_add(b as u64);
+        }
+        self.hash = hash;
+    }
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// data that can be attached to any entity alongside its value; see `World::insert`.
+/// has to be `Send + Sync` so systems can share it across threads
+pub trait Component
+where
+    Self: Sized + Send + Sync + 'static,
+{
+    /// how `World::dump_tree` shows this component. nothing by default;
+    /// return `Some(format!("{this:?}"))` for a `Debug` preview
+    #[allow(unused)]
+    fn preview_fn(this: &Self) -> Option<String> {
+        None
+    }
+}
+
+/// a kind of entity. it and what it stores have to be `Send + Sync` so systems can share them
+/// across threads
+pub trait EntityType
+where
+    Self: Sized + Send + Sync + 'static,
+{
+    type DowncastType: Send + Sync + 'static = Self;
+    #[allow(unused)]
+    fn kill_fn(this: Entity) {}
+    /// called with the stored value when its entity is killed
+    fn drop_fn(this: Self::DowncastType) {
+        drop(this);
+    }
+    /// how `World::dump_tree` shows the stored value. nothing by default;
+    /// return `Some(format!("{this:?}"))` for a `Debug` preview
+    #[allow(unused)]
+    fn preview_fn(this: &Self::DowncastType) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,251 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    struct Foo(i32);
+    impl EntityType for Foo {}
+    struct Bar;
+    impl EntityType for Bar {}
+
+    type Log = Arc<Mutex<Vec<(&'static str, Handle, Handle)>>>;
+    fn log() -> Log {
+        Arc::new(Mutex::new(Vec::new()))
+    }
+    /// records every event for `T` into `log`
+    fn watch<T: EntityType>(w: &mut World, log: &Log) {
+        let l = log.clone();
+        w.on_spawn::<T>(move |_, h| l.lock().unwrap().push(("spawn", h, h)));
+        let l = log.clone();
+        w.on_kill::<T>(move |_, h| l.lock().unwrap().push(("kill", h, h)));
+        let l = log.clone();
+        w.on_attach::<T>(move |_, p, c| l.lock().unwrap().push(("attach", p, c)));
+        let l = log.clone();
+        w.on_detach::<T>(move |_, p, c| l.lock().unwrap().push(("detach", p, c)));
+    }
+
+    #[test]
+    fn spawn_and_kill_fire() {
+        let mut w = World::new();
+        let log = log();
+        watch::<Foo>(&mut w, &log);
+        let h = w.spawn(Foo(1));
+        w.kill(h).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec![("spawn", h, h), ("kill", h, h)]);
+    }
+
+    #[test]
+    fn only_the_observed_type_fires() {
+        let mut w = World::new();
+        let log = log();
+        watch::<Foo>(&mut w, &log);
+        let b = w.spawn(Bar);
+        w.kill(b).unwrap();
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn attach_detach_and_reparent_fire() {
+        let mut w = World::new();
+        let log = log();
+        watch::<Foo>(&mut w, &log);
+        let a = w.spawn(Bar);
+        let b = w.spawn(Bar);
+        let c = w.spawn(Foo(0));
+        log.lock().unwrap().clear();
+
+        w.attach_child::<Foo>(a, c).unwrap();
+        // attaching where it already is changes nothing
+        w.attach_child::<Foo>(a, c).unwrap();
+        w.attach_child::<Foo>(b, c).unwrap();
+        w.remove_child(b, c, w.entity(c).unwrap().ty).unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                ("attach", a, c),
+                ("detach", a, c),
+                ("attach", b, c),
+                ("detach", b, c)
+            ]
+        );
+    }
+
+    #[test]
+    fn kill_fires_for_children_but_not_detach() {
+        let mut w = World::new();
+        let log = log();
+        watch::<Foo>(&mut w, &log);
+        let parent = w.spawn(Foo(0));
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+        log.lock().unwrap().clear();
+
+        w.kill(parent).unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![("kill", parent, parent), ("kill", child, child)]
+        );
+    }
+
+    #[test]
+    fn killed_child_does_not_fire_detach() {
+        let mut w = World::new();
+        let log = log();
+        watch::<Foo>(&mut w, &log);
+        let parent = w.spawn(Bar);
+        let child = w.spawn(Foo(1));
+        w.attach_child::<Foo>(parent, child).unwrap();
+        log.lock().unwrap().clear();
+
+        w.kill(child).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec![("kill", child, child)]);
+    }
+
+    #[test]
+    fn kill_observer_sees_the_entity_intact() {
+        let mut w = World::new();
+        let seen = Arc::new(Mutex::new(None));
+        let s = seen.clone();
+        w.on_kill::<Foo>(move |world, h| {
+            let entity = world.entity(h).unwrap();
+            *s.lock().unwrap() = Some((entity.downcast::<Foo>().unwrap().0, entity.children.len()));
+        });
+        let parent = w.spawn(Foo(7));
+        let child = w.spawn(Bar);
+        w.attach_child::<Bar>(parent, child).unwrap();
+        w.kill(parent).unwrap();
+        assert_eq!(*seen.lock().unwrap(), Some((7, 1)));
+    }
+
+    #[test]
+    fn any_type_observers_see_every_type() {
+        let mut w = World::new();
+        let log = log();
+        watch::<Foo>(&mut w, &log);
+        let l = log.clone();
+        w.on_attach_any(move |_, p, c| l.lock().unwrap().push(("attach any", p, c)));
+        let l = log.clone();
+        w.on_detach_any(move |_, p, c| l.lock().unwrap().push(("detach any", p, c)));
+        let a = w.spawn(Bar);
+        let b = w.spawn(Bar);
+        let foo = w.spawn(Foo(0));
+        log.lock().unwrap().clear();
+
+        w.attach_child::<Foo>(a, foo).unwrap();
+        w.attach_child::<Bar>(a, b).unwrap();
+        w.remove_child(a, b, w.entity(b).unwrap().ty).unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                ("attach", a, foo),
+                ("attach any", a, foo),
+                ("attach any", a, b),
+                ("detach any", a, b)
+            ]
+        );
+    }
+
+    #[test]
+    fn several_observers_run_in_registration_order() {
+        let mut w = World::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for i in 0..3 {
+            let o = order.clone();
+            w.on_spawn::<Foo>(move |_, _| o.lock().unwrap().push(i));
+        }
+        w.spawn(Foo(0));
+        w.spawn(Foo(0));
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 0, 1, 2]);
+    }
+}
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use super::{EntityType, Handle, TypeIdHasher, World};
+
+/// something that happened to an entity
+#[derive(Clone, Copy)]
+pub(crate) enum Event {
+    Spawn,
+    Kill,
+    Attach,
+    Detach,
+}
+
+/// called with the world and two handles: the entity twice for spawn/kill,
+/// parent then child for attach/detach
+type Observer = Box<dyn FnMut(&World, Handle, Handle) + Send + Sync>;
+
+/// per `Event`, entitytype typeid → its observers in registration order,
+/// then the ones that watch every type
+#[derive(Default)]
+pub(crate) struct Observers(
+    [HashMap<TypeId, Vec<Observer>, BuildHasherDefault<TypeIdHasher>>; 4],
+    [Vec<Observer>; 4],
+);
+
+impl World {
+    /// calls `f` with every new `T` right after it's spawned
+    pub fn on_spawn<T: EntityType>(
+        &mut self,
+        mut f: impl FnMut(&World, Handle) + Send + Sync + 'static,
+    ) {
+        self.observe::<T>(Event::Spawn, Box::new(move |w, h, _| f(w, h)));
+    }
+    /// calls `f` with every `T` about to be killed, while its value, components and children
+    /// are all still there. parents are reported before their children
+    pub fn on_kill<T: EntityType>(
+        &mut self,
+        mut f: impl FnMut(&World, Handle) + Send + Sync + 'static,
+    ) {
+        self.observe::<T>(Event::Kill, Box::new(move |w, h, _| f(w, h)));
+    }
+    /// calls `f` with `(parent, child)` whenever a `T` is attached to a parent
+    pub fn on_attach<T: EntityType>(
+        &mut self,
+        f: impl FnMut(&World, Handle, Handle) + Send + Sync + 'static,
+    ) {
+        self.observe::<T>(Event::Attach, Box::new(f));
+    }
+    /// calls `f` with `(parent, child)` whenever a `T` is detached from its parent, including
+    /// when it's moved to another one. entities detached because they or their parent died
+    /// only report `on_kill`
+    pub fn on_detach<T: EntityType>(
+        &mut self,
+        f: impl FnMut(&World, Handle, Handle) + Send + Sync + 'static,
+    ) {
+        self.observe::<T>(Event::Detach, Box::new(f));
+    }
+    /// `on_attach`, for every entity type. runs after the ones for the child's own type
+    pub fn on_attach_any(&mut self, f: impl FnMut(&World, Handle, Handle) + Send + Sync + 'static) {
+        self.observers.1[Event::Attach as usize].push(Box::new(f));
+    }
+    /// `on_detach`, for every entity type. runs after the ones for the child's own type
+    pub fn on_detach_any(&mut self, f: impl FnMut(&World, Handle, Handle) + Send + Sync + 'static) {
+        self.observers.1[Event::Detach as usize].push(Box::new(f));
+    }
+
+    fn observe<T: EntityType>(&mut self, event: Event, f: Observer) {
+        self.observers.0[event as usize]
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(f);
+    }
+
+    /// runs the observers of `event` for entities of type `ty`
+    pub(crate) fn notify(&mut self, event: Event, ty: TypeId, a: Handle, b: Handle) {
+        // taken out for the duration so they can look at the world.
+        // they only get `&World`, so none can be registered in the meantime
+        let mut observers = self.observers.0[event as usize].remove(&ty);
+        let mut any = std::mem::take(&mut self.observers.1[event as usize]);
+        for f in observers.iter_mut().flatten().chain(&mut any) {
+            f(self, a, b);
+        }
+        if let Some(observers) = observers {
+            self.observers.0[event as usize].insert(ty, observers);
+        }
+        self.observers.1[event as usize] = any;
+    }
+}
@@ -0,0 +1,309 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::fixtures::{self, Health, Light, LightValue};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+    struct Emitter {
+        rate: f32,
+    }
+    impl EntityType for Emitter {}
+
+    #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+    struct Mesh(String);
+    impl EntityType for Mesh {}
+
+    fn registry() -> SceneRegistry {
+        let mut registry = fixtures::registry();
+        registry.register::<Emitter>("emitter");
+        registry.register::<Mesh>("mesh");
+        registry
+    }
+
+    /// emitter ─┬─ mesh "body" ── light "glow"
+    ///          └─ mesh
+    fn emitter() -> Prefab {
+        Prefab::new(Emitter { rate: 2. })
+            .with(Health(5))
+            .child(
+                Prefab::new(Mesh("body".into()))
+                    .named("body")
+                    .child(Prefab::new_marker::<Light>(LightValue { strength: 1. }).named("glow")),
+            )
+            .child(Prefab::new(Mesh("trail".into())))
+    }
+
+    /// checks `root` looks like `emitter()`
+    fn check(w: &World, root: Handle, named: &HashMap<String, Handle>) {
+        let e = w.entity(root).unwrap();
+        assert_eq!(e.downcast::<Emitter>().unwrap(), &Emitter { rate: 2. });
+        assert_eq!(w.get::<Health>(root).unwrap(), &Health(5));
+        let meshes: Vec<_> = w.children_of::<Mesh>(root).unwrap().collect();
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].1.0, "body");
+        assert_eq!(meshes[1].1.0, "trail");
+
+        assert_eq!(named.len(), 2);
+        assert_eq!(named["body"], meshes[0].0);
+        let glow = w.entity(named["glow"]).unwrap();
+        assert_eq!(glow.parent, named["body"]);
+        assert_eq!(glow.downcast::<Light>().unwrap().strength, 1.);
+    }
+
+    #[test]
+    fn instantiate_builds_the_tree() {
+        let mut w = World::new();
+        let (root, named) = w.instantiate(&emitter()).unwrap();
+        check(&w, root, &named);
+        assert_eq!(w.entity(root).unwrap().parent, root);
+    }
+
+    #[test]
+    fn instances_are_independent() {
+        let mut w = World::new();
+        let prefab = emitter();
+        let (a, a_named) = w.instantiate(&prefab).unwrap();
+        let (b, b_named) = w.instantiate(&prefab).unwrap();
+        assert_ne!(a_named["body"], b_named["body"]);
+
+        w.kill(a).unwrap();
+        assert!(!w.is_alive(a_named["glow"]));
+        check(&w, b, &b_named);
+    }
+
+    #[test]
+    fn duplicate_names_rejected() {
+        let mut w = World::new();
+        let prefab = Prefab::new(Mesh("a".into()))
+            .named("same")
+            .child(Prefab::new(Mesh("b".into())).named("same"));
+        assert!(w.instantiate(&prefab).is_err());
+        assert_eq!(w.iter::<Mesh>().count(), 0);
+    }
+
+    #[test]
+    fn loads_from_yaml() {
+        let yaml = "
+type: emitter
+value: { rate: 2.0 }
+components:
+  health: 5
+children:
+  - name: body
+    type: mesh
+    value: body
+    children:
+      - name: glow
+        type: light
+        value: { strength: 1.0 }
+  - type: mesh
+    value: trail
+";
+        let prefab = Prefab::from_yaml(&registry(), yaml).unwrap();
+        let mut w = World::new();
+        let (root, named) = w.instantiate(&prefab).unwrap();
+        check(&w, root, &named);
+    }
+
+    #[test]
+    fn bad_yaml_fails_to_load() {
+        let registry = registry();
+        // unknown type
+        assert!(Prefab::from_yaml(&registry, "type: nope\nvalue: 1").is_err());
+        // unknown component
+        let yaml = "type: mesh\nvalue: a\ncomponents:\n  nope: 1";
+        assert!(Prefab::from_yaml(&registry, yaml).is_err());
+        // value of the wrong shape, deep in the tree
+        let yaml = "type: mesh\nvalue: a\nchildren:\n  - type: emitter\n    value: fast";
+        assert!(Prefab::from_yaml(&registry, yaml).is_err());
+        // names clash
+        let yaml =
+            "name: x\ntype: mesh\nvalue: a\nchildren:\n  - name: x\n    type: mesh\n    value: b";
+        assert!(Prefab::from_yaml(&registry, yaml).is_err());
+    }
+}
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context as _, anyhow, bail};
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use super::{Component, EntityType, Error, Handle, SceneRegistry, World};
+
+type SpawnFn = Box<dyn Fn(&mut World) -> anyhow::Result<Handle> + Send + Sync>;
+type InsertFn = Box<dyn Fn(&mut World, Handle) -> anyhow::Result<()> + Send + Sync>;
+
+/// a tree of entity values, plus their components, that can be spawned into a `World` as many
+/// times as you like with `World::instantiate`.
+///
+/// built up like `Prefab::new(Emitter { .. }).with(Transform::new(..)).child(Prefab::new(..))`,
+/// or loaded with `Prefab::from_yaml`
+pub struct Prefab {
+    name: Option<String>,
+    spawn: SpawnFn,
+    attach: fn(&mut World, Handle, Handle) -> Result<bool, Error>,
+    components: Vec<InsertFn>,
+    children: Vec<Prefab>,
+}
+
+impl Prefab {
+    /// a prefab whose root is a copy of `value`
+    pub fn new<T: EntityType<DowncastType = T> + Clone>(value: T) -> Self {
+        Self::new_marker::<T>(value)
+    }
+    pub fn new_marker<T: EntityType>(value: T::DowncastType) -> Self
+    where
+        T::DowncastType: Clone,
+    {
+        Self::with_fns(
+            Box::new(move |world| Ok(world.spawn_marker::<T>(value.clone()))),
+            |world, parent, child| world.attach_child::<T>(parent, child),
+        )
+    }
+    fn with_fns(
+        spawn: SpawnFn,
+        attach: fn(&mut World, Handle, Handle) -> Result<bool, Error>,
+    ) -> Self {
+        Self {
+            name: None,
+            spawn,
+            attach,
+            components: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// `World::instantiate` hands back this entity's handle under `name`.
+    /// names have to be unique within the whole prefab
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+    /// gives the root a copy of `component`
+    pub fn with<C: Component + Clone>(mut self, component: C) -> Self {
+        self.components.push(Box::new(move |world, handle| {
+            world.insert(handle, component.clone())?;
+            Ok(())
+        }));
+        self
+    }
+    /// adds `child` (and everything under it) under the root, after any children it already has
+    pub fn child(mut self, child: Prefab) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// reads a prefab from yaml, in the shape
+    /// ```yaml
+    /// type: emitter        # a name from `registry`
+    /// value: { rate: 2.0 } # the stored value
+    /// components:          # optional
+    ///   health: 5
+    /// children:            # optional, the same shape again
+    ///   - name: body       # optional
+    ///     type: mesh
+    ///     value: body
+    /// ```
+    /// the whole thing is checked by spawning it once into an empty world
+    pub fn from_yaml(registry: &SceneRegistry, yaml: &str) -> anyhow::Result<Self> {
+        let file: PrefabFile = serde_yaml::from_str(yaml).context("x_x :: invalid prefab yaml")?;
+        let prefab = Self::from_file(registry, file)?;
+        World::new().instantiate(&prefab)?;
+        Ok(prefab)
+    }
+    fn from_file(registry: &SceneRegistry, file: PrefabFile) -> anyhow::Result<Self> {
+        let entry = registry
+            .entity_named(&file.ty)
+            .ok_or_else(|| anyhow!("x_x :: unknown entity type `{}` in prefab", file.ty))?;
+        let (spawn, ty, value) = (entry.spawn, file.ty, file.value);
+        let mut prefab = Self::with_fns(
+            Box::new(move |world| {
+                spawn(world, value.clone()).with_context(|| format!("x_x :: invalid `{ty}`"))
+            }),
+            entry.attach,
+        );
+        prefab.name = file.name;
+        for (name, value) in file.components {
+            let insert = registry
+                .component_named(&name)
+                .ok_or_else(|| anyhow!("x_x :: unknown component `{name}` in prefab"))?
+                .insert;
+            prefab.components.push(Box::new(move |world, handle| {
+                insert(world, handle, value.clone())
+                    .with_context(|| format!("x_x :: invalid `{name}`"))
+            }));
+        }
+        for child in file.children {
+            prefab.children.push(Self::from_file(registry, child)?);
+        }
+        Ok(prefab)
+    }
+}
+
+/// what a prefab file looks like
+#[derive(Deserialize)]
+struct PrefabFile {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    components: BTreeMap<String, Value>,
+    #[serde(default)]
+    children: Vec<PrefabFile>,
+}
+
+impl World {
+    /// spawns a copy of `prefab`, children attached and components inserted.
+    /// returns the root, and the handle of every entity the prefab gave a name.
+    ///
+    /// if anything goes wrong, everything it spawned is killed again
+    pub fn instantiate(
+        &mut self,
+        prefab: &Prefab,
+    ) -> anyhow::Result<(Handle, HashMap<String, Handle>)> {
+        let mut named = HashMap::new();
+        let mut spawned = Vec::new();
+        let result = self.instantiate_node(prefab, None, &mut named, &mut spawned);
+        match result {
+            Ok(root) => Ok((root, named)),
+            Err(e) => {
+                for handle in spawned {
+                    // children of other spawned entities may already be gone
+                    let _ = self.kill(handle);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn instantiate_node(
+        &mut self,
+        prefab: &Prefab,
+        parent: Option<Handle>,
+        named: &mut HashMap<String, Handle>,
+        spawned: &mut Vec<Handle>,
+    ) -> anyhow::Result<Handle> {
+        let handle = (prefab.spawn)(self)?;
+        spawned.push(handle);
+        if let Some(name) = &prefab.name
+            && named.insert(name.clone(), handle).is_some()
+        {
+            bail!("x_x :: name `{name}` is used twice in prefab");
+        }
+        for insert in &prefab.components {
+            insert(self, handle)?;
+        }
+        if let Some(parent) = parent {
+            (prefab.attach)(self, parent, handle)?;
+        }
+        for child in &prefab.children {
+            self.instantiate_node(child, Some(handle), named, spawned)?;
+        }
+        Ok(handle)
+    }
+}
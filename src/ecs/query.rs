@@ -0,0 +1,297 @@
+use std::any::{TypeId, type_name};
+use std::marker::PhantomData;
+
+use super::storage::{ColumnPtr, ColumnSource, Columns};
+use super::{Component, Entity, Handle};
+
+/// the component types something reads and writes.
+/// used to reject queries that would alias a component mutably
+#[derive(Clone, Debug, Default)]
+pub struct Access {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn read<T: 'static>(&mut self) -> &mut Self {
+        let ty = (TypeId::of::<T>(), type_name::<T>());
+        if !self.reads.contains(&ty) {
+            self.reads.push(ty);
+        }
+        self
+    }
+    pub fn write<T: 'static>(&mut self) -> &mut Self {
+        self.writes.push((TypeId::of::<T>(), type_name::<T>()));
+        self
+    }
+
+    /// returns the name of a type that is written twice, or both read and written
+    pub fn self_conflict(&self) -> Option<&'static str> {
+        for (i, (ty, name)) in self.writes.iter().enumerate() {
+            if self.writes[i + 1..].iter().any(|(t, _)| t == ty)
+                || self.reads.iter().any(|(t, _)| t == ty)
+            {
+                return Some(name);
+            }
+        }
+        None
+    }
+
+    /// returns the name of a type one side writes and the other reads or writes
+    pub fn conflicts_with(&self, other: &Access) -> Option<&'static str> {
+        let clash = |writes: &[(TypeId, &'static str)], other: &Access| {
+            writes
+                .iter()
+                .find(|(ty, _)| {
+                    other
+                        .reads
+                        .iter()
+                        .chain(&other.writes)
+                        .any(|(t, _)| t == ty)
+                })
+                .map(|&(_, name)| name)
+        };
+        clash(&self.writes, other).or_else(|| clash(&other.writes, self))
+    }
+
+    /// whether `ty` is read or written
+    pub fn can_read(&self, ty: TypeId) -> bool {
+        self.can_write(ty) || self.reads.iter().any(|&(t, _)| t == ty)
+    }
+    pub fn can_write(&self, ty: TypeId) -> bool {
+        self.writes.iter().any(|&(t, _)| t == ty)
+    }
+    /// returns `("reads" | "writes", name)` for a type this touches in a way `declared`
+    /// doesn't allow
+    pub fn undeclared_in(&self, declared: &Access) -> Option<(&'static str, &'static str)> {
+        let read = self.reads.iter().find(|&&(t, _)| !declared.can_read(t));
+        let written = self.writes.iter().find(|&&(t, _)| !declared.can_write(t));
+        read.map(|&(_, name)| ("reads", name))
+            .or(written.map(|&(_, name)| ("writes", name)))
+    }
+    pub(crate) fn written(&self) -> impl Iterator<Item = TypeId> + '_ {
+        self.writes.iter().map(|&(t, _)| t)
+    }
+}
+
+/// a set of components fetched together by `World::query`.
+///
+/// implemented for `&C`, `&mut C`, `Option<&C>`, `Option<&mut C>` and tuples of up to 8 of those
+///
+/// # Safety
+/// `access` must report every component `fetch` hands out, so `World::query` can refuse aliasing
+pub unsafe trait Query {
+    type Item<'w>;
+    /// pointers to the columns being fetched from
+    type State: Copy;
+    fn access(access: &mut Access);
+    /// returns `None` if a required column doesn't exist, since then nothing can match
+    fn state(components: &mut ColumnSource) -> Option<Self::State>;
+    /// the owners of the shortest required column, which iteration is driven from.
+    /// `None` if every term is optional
+    fn driver(state: &Self::State) -> Option<(*const Handle, usize)>;
+    /// fetches the components of the live entity at `index`.
+    /// returns `None` when it lacks a required component
+    ///
+    /// # Safety
+    /// `state` must still be valid, and nothing else may be borrowing the components in `access`
+    unsafe fn fetch<'w>(state: &Self::State, index: usize) -> Option<Self::Item<'w>>;
+    /// stamps whatever `fetch` borrowed mutably as changed. only called once `fetch` has
+    /// matched, so entities the query skips aren't marked
+    ///
+    /// # Safety
+    /// same as `fetch`
+    unsafe fn mark_changed(_state: &Self::State, _index: usize) {}
+}
+
+unsafe impl<C: Component> Query for &C {
+    type Item<'w> = &'w C;
+    type State = ColumnPtr<C>;
+    fn access(access: &mut Access) {
+        access.read::<C>();
+    }
+    fn state(components: &mut ColumnSource) -> Option<Self::State> {
+        components.read::<C>(TypeId::of::<C>())
+    }
+    fn driver(state: &Self::State) -> Option<(*const Handle, usize)> {
+        Some((state.owners, state.len))
+    }
+    unsafe fn fetch<'w>(state: &Self::State, index: usize) -> Option<Self::Item<'w>> {
+        unsafe { state.get(index).map(|p| &*p) }
+    }
+}
+
+unsafe impl<C: Component> Query for &mut C {
+    type Item<'w> = &'w mut C;
+    type State = ColumnPtr<C>;
+    fn access(access: &mut Access) {
+        access.write::<C>();
+    }
+    fn state(components: &mut ColumnSource) -> Option<Self::State> {
+        components.write::<C>(TypeId::of::<C>())
+    }
+    fn driver(state: &Self::State) -> Option<(*const Handle, usize)> {
+        Some((state.owners, state.len))
+    }
+    unsafe fn fetch<'w>(state: &Self::State, index: usize) -> Option<Self::Item<'w>> {
+        unsafe { state.get(index).map(|p| &mut *p) }
+    }
+    unsafe fn mark_changed(state: &Self::State, index: usize) {
+        unsafe { state.mark_changed(index) }
+    }
+}
+
+unsafe impl<C: Component> Query for Option<&C> {
+    type Item<'w> = Option<&'w C>;
+    type State = Option<ColumnPtr<C>>;
+    fn access(access: &mut Access) {
+        access.read::<C>();
+    }
+    fn state(components: &mut ColumnSource) -> Option<Self::State> {
+        Some(<&C>::state(components))
+    }
+    fn driver(_: &Self::State) -> Option<(*const Handle, usize)> {
+        None
+    }
+    unsafe fn fetch<'w>(state: &Self::State, index: usize) -> Option<Self::Item<'w>> {
+        Some(
+            state
+                .as_ref()
+                .and_then(|s| unsafe { <&C>::fetch(s, index) }),
+        )
+    }
+}
+
+unsafe impl<C: Component> Query for Option<&mut C> {
+    type Item<'w> = Option<&'w mut C>;
+    type State = Option<ColumnPtr<C>>;
+    fn access(access: &mut Access) {
+        access.write::<C>();
+    }
+    fn state(components: &mut ColumnSource) -> Option<Self::State> {
+        Some(<&mut C>::state(components))
+    }
+    fn driver(_: &Self::State) -> Option<(*const Handle, usize)> {
+        None
+    }
+    unsafe fn fetch<'w>(state: &Self::State, index: usize) -> Option<Self::Item<'w>> {
+        Some(
+            state
+                .as_ref()
+                .and_then(|s| unsafe { <&mut C>::fetch(s, index) }),
+        )
+    }
+    unsafe fn mark_changed(state: &Self::State, index: usize) {
+        if let Some(state) = state {
+            unsafe { state.mark_changed(index) }
+        }
+    }
+}
+
+macro_rules! impl_query_tuple {
+    ($(($q:ident, $i:tt)),+) => {
+        unsafe impl<$($q: Query),+> Query for ($($q,)+) {
+            type Item<'w> = ($($q::Item<'w>,)+);
+            type State = ($($q::State,)+);
+            fn access(access: &mut Access) {
+                $($q::access(access);)+
+            }
+            fn state(components: &mut ColumnSource) -> Option<Self::State> {
+                Some(($($q::state(components)?,)+))
+            }
+            fn driver(state: &Self::State) -> Option<(*const Handle, usize)> {
+                [$($q::driver(&state.$i)),+]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|&(_, len)| len)
+            }
+            unsafe fn fetch<'w>(state: &Self::State, index: usize) -> Option<Self::Item<'w>> {
+                Some(($(unsafe { $q::fetch(&state.$i, index) }?,)+))
+            }
+            unsafe fn mark_changed(state: &Self::State, index: usize) {
+                $(unsafe { $q::mark_changed(&state.$i, index) };)+
+            }
+        }
+    };
+}
+impl_query_tuple!((A, 0));
+impl_query_tuple!((A, 0), (B, 1));
+impl_query_tuple!((A, 0), (B, 1), (C, 2));
+impl_query_tuple!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_query_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+impl_query_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5));
+impl_query_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5), (G, 6));
+impl_query_tuple!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7)
+);
+
+/// yields `(Handle, Q::Item)` for every live entity that has all of `Q`'s components.
+///
+/// walks the owners of `Q`'s shortest column rather than every entity in the world
+pub struct QueryIter<'w, Q: Query> {
+    entities: &'w [Entity],
+    state: Option<Q::State>,
+    driver: Option<(*const Handle, usize)>,
+    pos: usize,
+    _marker: PhantomData<&'w mut Columns>,
+}
+
+impl<'w, Q: Query> QueryIter<'w, Q> {
+    pub(super) fn new(entities: &'w [Entity], mut components: ColumnSource<'w>) -> Self {
+        let mut access = Access::new();
+        Q::access(&mut access);
+        if let Some(name) = access.self_conflict() {
+            panic!("x_x :: query borrows `{name}` mutably more than once");
+        }
+        let state = Q::state(&mut components);
+        Self {
+            entities,
+            driver: state.as_ref().and_then(Q::driver),
+            state,
+            pos: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'w, Q: Query> Iterator for QueryIter<'w, Q> {
+    type Item = (Handle, Q::Item<'w>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let state = self.state.as_ref()?;
+        loop {
+            let handle = match self.driver {
+                Some((owners, len)) => {
+                    if self.pos >= len {
+                        return None;
+                    }
+                    // SAFETY: the columns can't change while `'w` borrows them
+                    unsafe { *owners.add(self.pos) }
+                }
+                None => {
+                    let entity = self.entities.get(self.pos)?;
+                    if !entity.alive {
+                        self.pos += 1;
+                        continue;
+                    }
+                    entity.handle
+                }
+            };
+            self.pos += 1;
+            // SAFETY: each entity is visited once and `new` rejected aliasing component access
+            if let Some(item) = unsafe { Q::fetch(state, handle.index()) } {
+                unsafe { Q::mark_changed(state, handle.index()) };
+                return Some((handle, item));
+            }
+        }
+    }
+}
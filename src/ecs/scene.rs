@@ -0,0 +1,374 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::fixtures::{self, Health, Light, LightValue};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Cube {
+        size: f32,
+    }
+    impl EntityType for Cube {}
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Skull(String);
+    impl EntityType for Skull {}
+
+    struct Unsaved;
+    impl EntityType for Unsaved {}
+
+    fn registry() -> SceneRegistry {
+        let mut registry = fixtures::registry();
+        registry.register::<Cube>("cube");
+        registry.register::<Skull>("skull");
+        registry
+    }
+
+    /// cube ─┬─ skull "a" ── light
+    ///       └─ skull "b"
+    /// cube
+    fn sample() -> (World, [Handle; 5]) {
+        let mut w = World::new();
+        let cube = w.spawn(Cube { size: 1. });
+        let a = w.spawn(Skull("a".into()));
+        let b = w.spawn(Skull("b".into()));
+        let light = w.spawn_marker::<Light>(LightValue { strength: 3. });
+        let lone = w.spawn(Cube { size: 2. });
+        w.attach_child::<Skull>(cube, a).unwrap();
+        w.attach_child::<Skull>(cube, b).unwrap();
+        w.attach_child::<Light>(a, light).unwrap();
+        w.insert(a, Health(10)).unwrap();
+        (w, [cube, a, b, light, lone])
+    }
+
+    #[test]
+    fn round_trip() {
+        let registry = registry();
+        let (w, _) = sample();
+        let yaml = w.save_yaml(&registry).unwrap();
+
+        let mut loaded = World::new();
+        let ids = loaded.load_yaml(&registry, &yaml).unwrap();
+        assert_eq!(ids.len(), 5);
+
+        // saved ids are handed out in depth-first order from the roots
+        let [cube2, a2, light2, b2, lone2] = [0, 1, 2, 3, 4].map(|id| ids[&id]);
+        let e = |h| loaded.entity(h).unwrap();
+        assert_eq!(e(cube2).downcast::<Cube>().unwrap(), &Cube { size: 1. });
+        assert_eq!(e(a2).downcast::<Skull>().unwrap().0, "a");
+        assert_eq!(e(b2).downcast::<Skull>().unwrap().0, "b");
+        assert_eq!(e(light2).downcast::<Light>().unwrap().strength, 3.);
+        assert_eq!(e(lone2).downcast::<Cube>().unwrap().size, 2.);
+
+        assert_eq!(e(cube2).children, vec![a2, b2]);
+        assert_eq!(e(a2).parent, cube2);
+        assert_eq!(e(light2).parent, a2);
+        assert_eq!(e(lone2).parent, lone2);
+        assert_eq!(loaded.get::<Health>(a2).unwrap(), &Health(10));
+        assert!(loaded.get::<Health>(b2).is_err());
+
+        // saving again gives the same file
+        assert_eq!(loaded.save_yaml(&registry).unwrap(), yaml);
+    }
+
+    #[test]
+    fn load_remaps_into_populated_world() {
+        let registry = registry();
+        let (w, _) = sample();
+        let yaml = w.save_yaml(&registry).unwrap();
+
+        let (mut target, _) = sample();
+        let ids = target.load_yaml(&registry, &yaml).unwrap();
+        assert_eq!(target.iter::<Cube>().count(), 4);
+        let cube = ids[&0];
+        let kids: Vec<_> = target
+            .children_of::<Skull>(cube)
+            .unwrap()
+            .map(|(h, _)| h)
+            .collect();
+        assert_eq!(kids, vec![ids[&1], ids[&3]]);
+    }
+
+    #[test]
+    fn hand_written_scene() {
+        let yaml = "
+entities:
+  - id: 10
+    type: skull
+    value: jaw
+    parent: 20
+  - id: 20
+    type: cube
+    value: { size: 4.0 }
+    components:
+      health: 3
+";
+        let mut w = World::new();
+        let ids = w.load_yaml(&registry(), yaml).unwrap();
+        let (skull, cube) = (ids[&10], ids[&20]);
+        assert_eq!(w.entity(skull).unwrap().parent, cube);
+        assert_eq!(w.get::<Health>(cube).unwrap(), &Health(3));
+    }
+
+    #[test]
+    fn registering_a_name_again_replaces_it() {
+        let mut registry = registry();
+        registry.register::<Skull>("cube");
+        let mut w = World::new();
+        let ids = w
+            .load_yaml(&registry, "entities: [{ id: 0, type: cube, value: jaw }]")
+            .unwrap();
+        assert_eq!(
+            w.entity(ids[&0]).unwrap().downcast::<Skull>().unwrap().0,
+            "jaw"
+        );
+
+        // the old `cube` is gone
+        w.spawn(Cube { size: 1. });
+        let err = w.save_yaml(&registry).unwrap_err();
+        assert!(err.to_string().contains("Cube"), "{err}");
+    }
+
+    #[test]
+    fn unregistered_type_fails_to_save() {
+        let (mut w, _) = sample();
+        w.spawn(Unsaved);
+        let err = w.save_yaml(&registry()).unwrap_err();
+        assert!(err.to_string().contains("Unsaved"), "{err}");
+    }
+
+    #[test]
+    fn bad_scenes_are_rolled_back() {
+        let registry = registry();
+        let bad = [
+            // unknown type
+            "entities:\n  - { id: 0, type: cube, value: { size: 1.0 } }\n  - { id: 1, type: nope, value: 1 }\n",
+            // parent that isn't in the file
+            "entities:\n  - { id: 0, type: cube, value: { size: 1.0 }, parent: 5 }\n",
+            // duplicate id
+            "entities:\n  - { id: 0, type: cube, value: { size: 1.0 } }\n  - { id: 0, type: cube, value: { size: 1.0 } }\n",
+            // value of the wrong shape
+            "entities:\n  - { id: 0, type: cube, value: { size: 1.0 } }\n  - { id: 1, type: cube, value: big, parent: 0 }\n",
+            // unknown component
+            "entities:\n  - { id: 0, type: cube, value: { size: 1.0 }, components: { mana: 1 } }\n",
+            // parent loop
+            "entities:\n  - { id: 0, type: cube, value: { size: 1.0 }, parent: 1 }\n  - { id: 1, type: cube, value: { size: 1.0 }, parent: 0 }\n",
+        ];
+        for yaml in bad {
+            let mut w = World::new();
+            let before = w.spawn(Cube { size: 9. });
+            assert!(w.load_yaml(&registry, yaml).is_err(), "{yaml}");
+            let left: Vec<_> = w.iter::<Cube>().map(|(h, _)| h).collect();
+            assert_eq!(left, vec![before], "{yaml}");
+        }
+    }
+}
+
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasherDefault;
+
+use anyhow::{Context as _, anyhow, bail};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_yaml::Value;
+
+use super::{Component, EntityRef, EntityType, Error, Handle, TypeIdHasher, World};
+
+/// what a scene file looks like
+#[derive(Serialize, Deserialize)]
+struct SceneFile {
+    entities: Vec<SceneEntity>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneEntity {
+    /// only means anything inside the file; `load_yaml` maps it to a fresh handle
+    id: u64,
+    #[serde(rename = "type")]
+    ty: String,
+    /// id of the parent. roots have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<u64>,
+    value: Value,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    components: BTreeMap<String, Value>,
+}
+
+pub(super) struct EntityEntry {
+    name: String,
+    save: fn(&EntityRef) -> anyhow::Result<Value>,
+    pub(super) spawn: fn(&mut World, Value) -> anyhow::Result<Handle>,
+    pub(super) attach: fn(&mut World, Handle, Handle) -> Result<bool, Error>,
+}
+
+pub(super) struct ComponentEntry {
+    name: String,
+    /// `None` if the entity doesn't have one
+    save: fn(&World, Handle) -> Option<anyhow::Result<Value>>,
+    pub(super) insert: fn(&mut World, Handle, Value) -> anyhow::Result<()>,
+}
+
+/// the `EntityType`s and `Component`s that can go in a scene file, and their names there.
+///
+/// anything not registered here can't be saved, and a file naming it can't be loaded
+#[derive(Default)]
+pub struct SceneRegistry {
+    entities: HashMap<TypeId, EntityEntry, BuildHasherDefault<TypeIdHasher>>,
+    components: Vec<ComponentEntry>,
+}
+
+impl SceneRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// lets `T` be saved and loaded, written as `type: name`. like `register_component`, it
+    /// replaces whatever was registered under `name` before
+    pub fn register<T: EntityType>(&mut self, name: &str)
+    where
+        T::DowncastType: Serialize + DeserializeOwned,
+    {
+        self.entities.retain(|_, e| e.name != name);
+        self.entities.insert(
+            TypeId::of::<T>(),
+            EntityEntry {
+                name: name.to_string(),
+                save: |entity| Ok(serde_yaml::to_value(entity.downcast::<T>()?)?),
+                spawn: |world, value| {
+                    let value = serde_yaml::from_value::<T::DowncastType>(value)?;
+                    Ok(world.spawn_marker::<T>(value))
+                },
+                attach: |world, parent, child| world.attach_child::<T>(parent, child),
+            },
+        );
+    }
+
+    /// lets `C` be saved and loaded, written as `name:` under an entity's `components`
+    pub fn register_component<C: Component + Serialize + DeserializeOwned>(&mut self, name: &str) {
+        self.components.retain(|c| c.name != name);
+        self.components.push(ComponentEntry {
+            name: name.to_string(),
+            save: |world, handle| {
+                let component = world.get::<C>(handle).ok()?;
+                Some(serde_yaml::to_value(component).map_err(Into::into))
+            },
+            insert: |world, handle, value| {
+                world.insert(handle, serde_yaml::from_value::<C>(value)?)?;
+                Ok(())
+            },
+        });
+    }
+
+    pub(super) fn entity_named(&self, name: &str) -> Option<&EntityEntry> {
+        self.entities.values().find(|e| e.name == name)
+    }
+    pub(super) fn component_named(&self, name: &str) -> Option<&ComponentEntry> {
+        self.components.iter().find(|c| c.name == name)
+    }
+}
+
+impl World {
+    /// writes every live entity, its place in the hierarchy, its value and its registered
+    /// components to a yaml scene. fails if any entity's type isn't in `registry`
+    pub fn save_yaml(&self, registry: &SceneRegistry) -> anyhow::Result<String> {
+        let mut entities = Vec::new();
+        // depth first from each root, so children keep their order when loaded
+        let roots = self
+            .entities
+            .iter()
+            .filter(|e| e.alive && e.parent == e.handle)
+            .map(|e| (e.handle, None));
+        let mut stack: Vec<(Handle, Option<u64>)> = roots.rev().collect();
+        while let Some((handle, parent)) = stack.pop() {
+            let entity = self.entity(handle)?;
+            let entry = registry.entities.get(&entity.ty).ok_or_else(|| {
+                anyhow!(
+                    "x_x :: entity type `{}` isn't registered for scenes",
+                    self.values[&entity.ty].type_name()
+                )
+            })?;
+            let id = entities.len() as u64;
+            let value = (entry.save)(&entity)
+                .with_context(|| format!("x_x :: couldn't save a `{}`", entry.name))?;
+            let mut components = BTreeMap::new();
+            for component in &registry.components {
+                if let Some(saved) = (component.save)(self, handle) {
+                    let saved = saved.with_context(|| {
+                        format!("x_x :: couldn't save a `{}` component", component.name)
+                    })?;
+                    components.insert(component.name.clone(), saved);
+                }
+            }
+            entities.push(SceneEntity {
+                id,
+                ty: entry.name.clone(),
+                parent,
+                value,
+                components,
+            });
+            stack.extend(entity.children.iter().rev().map(|&c| (c, Some(id))));
+        }
+        Ok(serde_yaml::to_string(&SceneFile { entities })?)
+    }
+
+    /// spawns everything in a yaml scene into this world, on top of what's already here.
+    /// returns the handle each id in the file was given.
+    ///
+    /// if anything in the file is wrong, everything it spawned is killed again
+    pub fn load_yaml(
+        &mut self,
+        registry: &SceneRegistry,
+        yaml: &str,
+    ) -> anyhow::Result<HashMap<u64, Handle>> {
+        let scene: SceneFile = serde_yaml::from_str(yaml).context("x_x :: invalid scene yaml")?;
+        let mut ids = HashMap::new();
+        let result = self.load_scene(registry, scene, &mut ids);
+        if result.is_err() {
+            for &handle in ids.values() {
+                // children of other spawned entities may already be gone
+                let _ = self.kill(handle);
+            }
+        }
+        result.map(|_| ids)
+    }
+
+    fn load_scene(
+        &mut self,
+        registry: &SceneRegistry,
+        scene: SceneFile,
+        ids: &mut HashMap<u64, Handle>,
+    ) -> anyhow::Result<()> {
+        // spawn everything first so parents can come after their children in the file
+        let mut links = Vec::new();
+        for entity in scene.entities {
+            let entry = registry
+                .entity_named(&entity.ty)
+                .ok_or_else(|| anyhow!("x_x :: unknown entity type `{}` in scene", entity.ty))?;
+            if ids.contains_key(&entity.id) {
+                bail!("x_x :: id {} is used twice in scene", entity.id);
+            }
+            let handle = (entry.spawn)(self, entity.value)
+                .with_context(|| format!("x_x :: invalid `{}` with id {}", entity.ty, entity.id))?;
+            ids.insert(entity.id, handle);
+
+            for (name, value) in entity.components {
+                let component = registry
+                    .component_named(&name)
+                    .ok_or_else(|| anyhow!("x_x :: unknown component `{name}` in scene"))?;
+                (component.insert)(self, handle, value)
+                    .with_context(|| format!("x_x :: invalid `{name}` on id {}", entity.id))?;
+            }
+            if let Some(parent) = entity.parent {
+                links.push((parent, handle, entry.attach, entity.id));
+            }
+        }
+        for (parent, child, attach, id) in links {
+            let parent = *ids.get(&parent).ok_or_else(|| {
+                anyhow!("x_x :: id {id} has parent {parent}, which isn't in the scene")
+            })?;
+            attach(self, parent, child)
+                .with_context(|| format!("x_x :: couldn't attach id {id}"))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,908 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{Component, EntityType};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    struct Pos(f32);
+    impl Component for Pos {}
+    struct Vel(f32);
+    impl Component for Vel {}
+    struct Unit;
+    impl EntityType for Unit {}
+
+    fn log() -> Arc<Mutex<Vec<&'static str>>> {
+        Arc::new(Mutex::new(Vec::new()))
+    }
+    /// a system that just records that it ran
+    fn logger(name: &'static str, log: &Arc<Mutex<Vec<&'static str>>>) -> System {
+        let log = log.clone();
+        System::new(name, move |_, _| {
+            log.lock().unwrap().push(name);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let log = log();
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(logger("render", &log).in_stage(Stage::PreRender))
+            .unwrap();
+        schedule
+            .add_system(logger("post", &log).in_stage(Stage::PostUpdate))
+            .unwrap();
+        schedule.add_system(logger("update", &log)).unwrap();
+        schedule
+            .add_system(logger("pre", &log).in_stage(Stage::PreUpdate))
+            .unwrap();
+
+        let mut w = World::new();
+        for stage in Stage::ALL {
+            schedule.run(stage, &mut w).unwrap();
+        }
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["pre", "update", "post", "render"]
+        );
+    }
+
+    #[test]
+    fn registration_order_kept_without_constraints() {
+        let log = log();
+        let mut schedule = Schedule::new();
+        schedule.add_system(logger("a", &log)).unwrap();
+        schedule.add_system(logger("b", &log)).unwrap();
+        schedule.add_system(logger("c", &log)).unwrap();
+        schedule.run(Stage::Update, &mut World::new()).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn before_and_after_respected() {
+        let log = log();
+        let mut schedule = Schedule::new();
+        schedule.add_system(logger("a", &log).after("c")).unwrap();
+        schedule.add_system(logger("b", &log).before("c")).unwrap();
+        schedule.add_system(logger("c", &log)).unwrap();
+        schedule.run(Stage::Update, &mut World::new()).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn constraint_on_later_system_applies() {
+        let log = log();
+        let mut schedule = Schedule::new();
+        schedule.add_system(logger("a", &log).after("b")).unwrap();
+        schedule.run(Stage::Update, &mut World::new()).unwrap();
+        schedule.add_system(logger("b", &log)).unwrap();
+        schedule.run(Stage::Update, &mut World::new()).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a", "b", "a"]);
+    }
+
+    #[test]
+    fn duplicate_name_rejected() {
+        let log = log();
+        let mut schedule = Schedule::new();
+        schedule.add_system(logger("a", &log)).unwrap();
+        let err = schedule.add_system(logger("a", &log).in_stage(Stage::PostUpdate));
+        assert!(matches!(err, Err(ScheduleError::DuplicateName("a"))));
+    }
+
+    #[test]
+    fn cycle_rejected_and_rolled_back() {
+        let log = log();
+        let mut schedule = Schedule::new();
+        schedule.add_system(logger("a", &log).before("b")).unwrap();
+        let err = schedule.add_system(logger("b", &log).before("a"));
+        assert!(matches!(err, Err(ScheduleError::Cycle(Stage::Update))));
+
+        // the rejected system isn't left behind
+        schedule.add_system(logger("b", &log)).unwrap();
+        schedule.run(Stage::Update, &mut World::new()).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn conflicting_writes_rejected() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("a", |_, _| Ok(())).writes::<Pos>())
+            .unwrap();
+        let err = schedule.add_system(System::new("b", |_, _| Ok(())).writes::<Pos>());
+        assert!(matches!(
+            err,
+            Err(ScheduleError::Conflict {
+                system: "b",
+                with: "a",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn read_write_conflict_rejected() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("a", |_, _| Ok(())).reads::<Pos>())
+            .unwrap();
+        let err = schedule.add_system(System::new("b", |_, _| Ok(())).writes::<Pos>());
+        assert!(matches!(err, Err(ScheduleError::Conflict { .. })));
+    }
+
+    #[test]
+    fn shared_reads_allowed() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("a", |_, _| Ok(())).reads::<Pos>())
+            .unwrap();
+        schedule
+            .add_system(
+                System::new("b", |_, _| Ok(()))
+                    .reads::<Pos>()
+                    .writes::<Vel>(),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn ordered_conflict_allowed() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("a", |_, _| Ok(())).writes::<Pos>())
+            .unwrap();
+        schedule
+            .add_system(System::new("b", |_, _| Ok(())).writes::<Pos>().after("a"))
+            .unwrap();
+    }
+
+    #[test]
+    fn transitively_ordered_conflict_allowed() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(
+                System::new("a", |_, _| Ok(()))
+                    .writes::<Pos>()
+                    .before("mid"),
+            )
+            .unwrap();
+        schedule
+            .add_system(System::new("mid", |_, _| Ok(())))
+            .unwrap();
+        schedule
+            .add_system(System::new("b", |_, _| Ok(())).writes::<Pos>().after("mid"))
+            .unwrap();
+    }
+
+    #[test]
+    fn conflict_in_other_stage_allowed() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("a", |_, _| Ok(())).writes::<Pos>())
+            .unwrap();
+        schedule
+            .add_system(
+                System::new("b", |_, _| Ok(()))
+                    .writes::<Pos>()
+                    .in_stage(Stage::PostUpdate),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn systems_mutate_world() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(
+                System::new("integrate", |world, _| {
+                    for (_, (pos, vel)) in world.query::<(&mut Pos, &Vel)>() {
+                        pos.0 += vel.0;
+                    }
+                    Ok(())
+                })
+                .writes::<Pos>()
+                .reads::<Vel>(),
+            )
+            .unwrap();
+
+        let mut w = World::new();
+        let h = w.spawn(Unit);
+        w.insert(h, Pos(1.)).unwrap();
+        w.insert(h, Vel(2.)).unwrap();
+        schedule.run(Stage::Update, &mut w).unwrap();
+        schedule.run(Stage::Update, &mut w).unwrap();
+        assert_eq!(w.get::<Pos>(h).unwrap().0, 5.);
+    }
+
+    #[test]
+    fn commands_applied_at_end_of_stage() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("spawner", |world, commands| {
+                assert_eq!(world.iter::<Unit>().count(), 0);
+                commands.spawn(Unit);
+                commands.spawn(Unit);
+                Ok(())
+            }))
+            .unwrap();
+        schedule
+            .add_system(
+                System::new("same_stage", |world, _| {
+                    assert_eq!(world.iter::<Unit>().count(), 0);
+                    Ok(())
+                })
+                .after("spawner"),
+            )
+            .unwrap();
+        schedule
+            .add_system(
+                System::new("killer", |world, commands| {
+                    assert_eq!(world.iter::<Unit>().count(), 2);
+                    for (h, _) in world.iter::<Unit>() {
+                        commands.kill(h);
+                    }
+                    Ok(())
+                })
+                .in_stage(Stage::PostUpdate),
+            )
+            .unwrap();
+
+        let mut w = World::new();
+        schedule.run(Stage::Update, &mut w).unwrap();
+        assert_eq!(w.iter::<Unit>().count(), 2);
+        schedule.run(Stage::PostUpdate, &mut w).unwrap();
+        assert_eq!(w.iter::<Unit>().count(), 0);
+    }
+
+    #[test]
+    fn system_error_stops_stage() {
+        let log = log();
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::new("fails", |_, _| Err(anyhow::anyhow!("nope"))))
+            .unwrap();
+        schedule.add_system(logger("after", &log)).unwrap();
+        assert!(schedule.run(Stage::Update, &mut World::new()).is_err());
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unordered_parallel_systems_overlap() {
+        // each waits (up to a few seconds) for the other to start
+        let started = Arc::new(AtomicUsize::new(0));
+        let overlapped = Arc::new(AtomicUsize::new(0));
+        let mut schedule = Schedule::new();
+        schedule.set_threads(2);
+        for name in ["a", "b"] {
+            let (started, overlapped) = (started.clone(), overlapped.clone());
+            let system = System::parallel(name, move |_, _| {
+                started.fetch_add(1, Ordering::SeqCst);
+                let deadline = Instant::now() + Duration::from_secs(5);
+                while started.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+                    thread::yield_now();
+                }
+                if started.load(Ordering::SeqCst) == 2 {
+                    overlapped.fetch_add(1, Ordering::SeqCst);
+                }
+                Ok(())
+            });
+            schedule.add_system(system).unwrap();
+        }
+        schedule.run(Stage::Update, &mut World::new()).unwrap();
+        assert_eq!(overlapped.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn one_thread_runs_everything_here() {
+        let here = thread::current().id();
+        let ran = Arc::new(AtomicUsize::new(0));
+        let mut schedule = Schedule::new();
+        schedule.set_threads(1);
+        for name in ["a", "b", "c"] {
+            let ran = ran.clone();
+            let system = System::parallel(name, move |_, _| {
+                assert_eq!(thread::current().id(), here);
+                ran.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            });
+            schedule.add_system(system).unwrap();
+        }
+        schedule.run(Stage::Update, &mut World::new()).unwrap();
+        assert_eq!(ran.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn worker_threads_are_kept_between_runs() {
+        let here = thread::current().id();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut schedule = Schedule::new();
+        schedule.set_threads(2);
+        for name in ["a", "b"] {
+            let seen = seen.clone();
+            let system = System::parallel(name, move |_, _| {
+                seen.lock().unwrap().push(thread::current().id());
+                Ok(())
+            });
+            schedule.add_system(system).unwrap();
+        }
+        let mut w = World::new();
+        for _ in 0..3 {
+            schedule.run(Stage::Update, &mut w).unwrap();
+        }
+        let mut workers: Vec<_> = seen.lock().unwrap().clone();
+        workers.retain(|&id| id != here);
+        workers.dedup();
+        assert_eq!(workers.len(), 1);
+    }
+
+    #[test]
+    fn parallel_systems_see_earlier_writes() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(
+                System::parallel("integrate", |view, _| {
+                    for (_, (pos, vel)) in view.query::<(&mut Pos, &Vel)>() {
+                        pos.0 += vel.0;
+                    }
+                    Ok(())
+                })
+                .writes::<Pos>()
+                .reads::<Vel>(),
+            )
+            .unwrap();
+        schedule
+            .add_system(
+                System::parallel("double", |view, _| {
+                    for (_, pos) in view.query::<&mut Pos>() {
+                        pos.0 *= 2.;
+                    }
+                    Ok(())
+                })
+                .writes::<Pos>()
+                .after("integrate"),
+            )
+            .unwrap();
+        schedule
+            .add_system(
+                System::parallel("slow_down", |view, _| {
+                    for (_, vel) in view.query::<&mut Vel>() {
+                        vel.0 = 0.;
+                    }
+                    Ok(())
+                })
+                .writes::<Vel>()
+                .after("integrate"),
+            )
+            .unwrap();
+
+        let mut w = World::new();
+        let h = w.spawn(Unit);
+        w.insert(h, Pos(1.)).unwrap();
+        w.insert(h, Vel(2.)).unwrap();
+        schedule.run(Stage::Update, &mut w).unwrap();
+        assert_eq!(w.get::<Pos>(h).unwrap().0, 6.);
+        assert_eq!(w.get::<Vel>(h).unwrap().0, 0.);
+    }
+
+    #[test]
+    fn exclusive_systems_split_batches() {
+        let log = log();
+        let mut schedule = Schedule::new();
+        let l = log.clone();
+        schedule
+            .add_system(System::parallel("a", move |_, _| {
+                l.lock().unwrap().push("a");
+                Ok(())
+            }))
+            .unwrap();
+        schedule.add_system(logger("exclusive", &log)).unwrap();
+        let l = log.clone();
+        schedule
+            .add_system(System::parallel("b", move |_, _| {
+                l.lock().unwrap().push("b");
+                Ok(())
+            }))
+            .unwrap();
+        schedule.run(Stage::Update, &mut World::new()).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["a", "exclusive", "b"]);
+    }
+
+    #[test]
+    fn undeclared_access_panics_and_world_survives() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(
+                System::parallel("sneaky", |view, _| {
+                    view.query::<(&mut Pos, &Vel)>().count();
+                    Ok(())
+                })
+                .writes::<Pos>(),
+            )
+            .unwrap();
+        let mut w = World::new();
+        let h = w.spawn(Unit);
+        w.insert(h, Pos(1.)).unwrap();
+        w.insert(h, Vel(2.)).unwrap();
+
+        let run = panic::catch_unwind(AssertUnwindSafe(|| schedule.run(Stage::Update, &mut w)));
+        let message = *run.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("system `sneaky` reads"));
+        // what it borrowed was put back
+        assert_eq!(w.get::<Pos>(h).unwrap().0, 1.);
+    }
+
+    #[test]
+    fn parallel_commands_applied_in_system_order() {
+        let mut schedule = Schedule::new();
+        schedule.set_threads(4);
+        schedule
+            .add_system(System::parallel("spawner", |_, commands| {
+                commands.spawn(Unit);
+                Ok(())
+            }))
+            .unwrap();
+        schedule
+            .add_system(
+                System::parallel("killer", |view, commands| {
+                    for (h, _) in view.iter::<Unit>() {
+                        commands.kill(h);
+                    }
+                    Ok(())
+                })
+                .reads::<Unit>(),
+            )
+            .unwrap();
+
+        let mut w = World::new();
+        let old = w.spawn(Unit);
+        schedule.run(Stage::Update, &mut w).unwrap();
+        assert!(!w.is_alive(old));
+        assert_eq!(w.iter::<Unit>().count(), 1);
+    }
+
+    #[test]
+    fn parallel_errors_report_the_first_system() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(System::parallel("ok", |_, _| Ok(())))
+            .unwrap();
+        schedule
+            .add_system(System::parallel("fails", |_, _| {
+                Err(anyhow::anyhow!("nope"))
+            }))
+            .unwrap();
+        let err = schedule.run(Stage::Update, &mut World::new()).unwrap_err();
+        assert_eq!(err.to_string(), "x_x :: in system `fails`");
+    }
+}
+
+use std::any::Any;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+use anyhow::anyhow;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use super::view::Owned;
+use super::{Access, Commands, World, WorldView};
+
+/// when in a frame a system runs. stages run in the order listed here
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Stage {
+    /// before `AppHandler::update`
+    PreUpdate,
+    /// right after `AppHandler::update`
+    Update,
+    PostUpdate,
+    /// last thing before the frame is rendered
+    PreRender,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 4] = [
+        Stage::PreUpdate,
+        Stage::Update,
+        Stage::PostUpdate,
+        Stage::PreRender,
+    ];
+}
+
+type ExclusiveFn = Box<dyn FnMut(&mut World, &mut Commands) -> anyhow::Result<()> + Send>;
+type ParallelFn = Box<dyn FnMut(&mut WorldView, &mut Commands) -> anyhow::Result<()> + Send>;
+
+enum SystemFn {
+    /// gets the whole world and runs alone
+    Exclusive(ExclusiveFn),
+    /// gets what it declared and can run alongside other parallel systems
+    Parallel(ParallelFn),
+}
+
+/// a function over the `World`, plus when it runs and what it touches.
+/// structural changes go through the `Commands` it's handed, which are applied once its stage ends.
+///
+/// the declared reads and writes are what `Schedule::add_system` checks for conflicts.
+/// systems made with `System::parallel` can only touch what they declared, and run on other
+/// threads alongside the systems they don't conflict with
+pub struct System {
+    name: &'static str,
+    stage: Stage,
+    access: Access,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    run: SystemFn,
+    commands: Commands,
+}
+
+impl System {
+    /// a system in `Stage::Update` with no declared access or ordering.
+    /// it gets the whole `World`, so it never runs at the same time as another system;
+    /// its declared access is only used to check for conflicts
+    pub fn new(
+        name: &'static str,
+        run: impl FnMut(&mut World, &mut Commands) -> anyhow::Result<()> + Send + 'static,
+    ) -> Self {
+        Self::with_fn(name, SystemFn::Exclusive(Box::new(run)))
+    }
+    /// like `new`, but the system only sees what it declares with `reads` and `writes`,
+    /// through a `WorldView`, and may run on another thread at the same time as other
+    /// parallel systems in its stage. so what it touches has to be `Send + Sync`
+    pub fn parallel(
+        name: &'static str,
+        run: impl FnMut(&mut WorldView, &mut Commands) -> anyhow::Result<()> + Send + 'static,
+    ) -> Self {
+        Self::with_fn(name, SystemFn::Parallel(Box::new(run)))
+    }
+    fn with_fn(name: &'static str, run: SystemFn) -> Self {
+        Self {
+            name,
+            stage: Stage::Update,
+            access: Access::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            run,
+            commands: Commands::new(),
+        }
+    }
+    pub fn in_stage(mut self, stage: Stage) -> Self {
+        self.stage = stage;
+        self
+    }
+    /// declares that this system reads `T` (a component, value or anything else it shares)
+    pub fn reads<T: 'static>(mut self) -> Self {
+        self.access.read::<T>();
+        self
+    }
+    /// declares that this system writes `T`
+    pub fn writes<T: 'static>(mut self) -> Self {
+        self.access.write::<T>();
+        self
+    }
+    /// run before the system called `name`, if it's in the same stage
+    pub fn before(mut self, name: &'static str) -> Self {
+        self.before.push(name);
+        self
+    }
+    /// run after the system called `name`, if it's in the same stage
+    pub fn after(mut self, name: &'static str) -> Self {
+        self.after.push(name);
+        self
+    }
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// runs a parallel system on its share of `world`
+    fn run_view(&mut self, world: &World, owned: &mut Owned) -> anyhow::Result<()> {
+        let SystemFn::Parallel(run) = &mut self.run else {
+            unreachable!("exclusive systems aren't batched");
+        };
+        let mut view = WorldView::new(self.name, &self.access, world, owned);
+        run(&mut view, &mut self.commands).map_err(|e| self.context(e))
+    }
+    fn context(&self, e: anyhow::Error) -> anyhow::Error {
+        e.context(format!("x_x :: in system `{}`", self.name))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ScheduleError {
+    /// a system with this name is already registered
+    DuplicateName(&'static str),
+    /// the `before`/`after` constraints in this stage loop
+    Cycle(Stage),
+    /// `system` and `with` access `ty` in a way that conflicts, and nothing orders them
+    Conflict {
+        system: &'static str,
+        with: &'static str,
+        ty: &'static str,
+    },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateName(name) => write!(f, "x_x :: system `{name}` registered twice"),
+            Self::Cycle(stage) => write!(f, "x_x :: system ordering in {stage:?} has a cycle"),
+            Self::Conflict { system, with, ty } => write!(
+                f,
+                "x_x :: systems `{system}` and `{with}` both access `{ty}` and one writes it; \
+                 order them with `before`/`after`"
+            ),
+        }
+    }
+}
+impl std::error::Error for ScheduleError {}
+
+/// the systems of one stage, and the order they run in
+#[derive(Default)]
+struct StageSystems {
+    systems: Vec<System>,
+    order: Vec<usize>,
+    /// `order` split into runs of systems that can go at the same time.
+    /// exclusive systems are always alone in theirs
+    batches: Vec<Vec<usize>>,
+}
+
+impl StageSystems {
+    /// `edges[i]` are the systems that must run after system `i`
+    fn edges(&self) -> Vec<Vec<usize>> {
+        let index_of =
+            |name: &str| -> Option<usize> { self.systems.iter().position(|s| s.name == name) };
+        let mut edges = vec![Vec::new(); self.systems.len()];
+        for (i, system) in self.systems.iter().enumerate() {
+            for j in system.before.iter().filter_map(|n| index_of(n)) {
+                edges[i].push(j);
+            }
+            for j in system.after.iter().filter_map(|n| index_of(n)) {
+                edges[j].push(i);
+            }
+        }
+        edges
+    }
+
+    /// topological sort, keeping registration order between unconstrained systems.
+    /// `None` if there's a cycle
+    fn sort(edges: &[Vec<usize>]) -> Option<Vec<usize>> {
+        let mut incoming = vec![0; edges.len()];
+        for &j in edges.iter().flatten() {
+            incoming[j] += 1;
+        }
+        let mut order = Vec::with_capacity(edges.len());
+        let mut done = vec![false; edges.len()];
+        while order.len() < edges.len() {
+            let next = (0..edges.len()).find(|&i| !done[i] && incoming[i] == 0)?;
+            done[next] = true;
+            order.push(next);
+            for &j in &edges[next] {
+                incoming[j] -= 1;
+            }
+        }
+        Some(order)
+    }
+
+    /// whether `to` has to run after `from`, directly or through other systems
+    fn reaches(edges: &[Vec<usize>], from: usize, to: usize) -> bool {
+        let mut stack = vec![from];
+        let mut seen = vec![false; edges.len()];
+        while let Some(i) = stack.pop() {
+            if i == to {
+                return true;
+            }
+            if !std::mem::replace(&mut seen[i], true) {
+                stack.extend(&edges[i]);
+            }
+        }
+        false
+    }
+
+    /// greedily groups consecutive systems of `order` while they're parallel and none of the
+    /// group has to run before the next one
+    fn batch(&self, edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        let mut joinable = false;
+        for &i in &self.order {
+            let system = &self.systems[i];
+            let parallel = matches!(system.run, SystemFn::Parallel(_));
+            if let Some(batch) = batches.last_mut().filter(|_| joinable && parallel)
+                && batch.iter().all(|&j| {
+                    !Self::reaches(edges, j, i)
+                        && system
+                            .access
+                            .conflicts_with(&self.systems[j].access)
+                            .is_none()
+                })
+            {
+                batch.push(i);
+                continue;
+            }
+            batches.push(vec![i]);
+            joinable = parallel;
+        }
+        batches
+    }
+}
+
+/// every registered system, grouped by stage.
+/// `Context` runs each stage once per frame on its `world`
+#[derive(Default)]
+pub struct Schedule {
+    stages: [StageSystems; 4],
+    /// 0 for one per core
+    threads: usize,
+    /// the threads besides the caller's that parallel batches run on.
+    /// started by the first batch that needs them, and kept until `set_threads`
+    pool: Option<ThreadPool>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// registers `system`.
+    /// fails if its name is taken, if its ordering makes a cycle, or if it conflicts with an
+    /// unordered system in the same stage (one writes what the other reads or writes)
+    pub fn add_system(&mut self, system: System) -> Result<(), ScheduleError> {
+        let name = system.name;
+        if self.systems().any(|s| s.name == name) {
+            return Err(ScheduleError::DuplicateName(name));
+        }
+        let stage = system.stage;
+        let systems = &mut self.stages[stage as usize];
+        systems.systems.push(system);
+        let new = systems.systems.len() - 1;
+
+        let edges = systems.edges();
+        let Some(order) = StageSystems::sort(&edges) else {
+            systems.systems.pop();
+            return Err(ScheduleError::Cycle(stage));
+        };
+        for (i, other) in systems.systems[..new].iter().enumerate() {
+            let ordered =
+                StageSystems::reaches(&edges, i, new) || StageSystems::reaches(&edges, new, i);
+            if ordered {
+                continue;
+            }
+            if let Some(ty) = systems.systems[new].access.conflicts_with(&other.access) {
+                let with = other.name;
+                systems.systems.pop();
+                return Err(ScheduleError::Conflict {
+                    system: name,
+                    with,
+                    ty,
+                });
+            }
+        }
+        systems.order = order;
+        systems.batches = systems.batch(&edges);
+        Ok(())
+    }
+
+    /// how many threads parallel systems are spread over, including the one calling `run`.
+    /// 1 runs everything on the calling thread; 0 (the default) uses one per core.
+    /// the others are started once and reused by every `run` after
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+        self.pool = None;
+    }
+    fn threads(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            n => n,
+        }
+    }
+
+    /// runs every system in `stage`, then applies the commands they recorded, in system order.
+    ///
+    /// stops at the first error, though the rest of a parallel batch still finishes.
+    /// commands recorded before it are applied by the next `run`
+    pub fn run(&mut self, stage: Stage, world: &mut World) -> anyhow::Result<()> {
+        let threads = self.threads();
+        let systems = &mut self.stages[stage as usize];
+        let parallel = systems.batches.iter().any(|b| b.len() > 1);
+        if parallel && threads > 1 && self.pool.is_none() {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads - 1)
+                .thread_name(|i| format!("ostinato system {i}"))
+                .build()
+                .map_err(|e| anyhow!("x_x :: couldn't start the system threads: {e}"))?;
+            self.pool = Some(pool);
+        }
+        for batch in &systems.batches {
+            if let [i] = batch[..] {
+                let system = &mut systems.systems[i];
+                if let SystemFn::Exclusive(run) = &mut system.run {
+                    let result = run(world, &mut system.commands);
+                    result.map_err(|e| system.context(e))?;
+                    continue;
+                }
+            }
+            let mut slots: Vec<_> = systems.systems.iter_mut().map(Some).collect();
+            let batch = batch.iter().map(|&i| slots[i].take().unwrap()).collect();
+            run_batch(batch, world, self.pool.as_ref())?;
+        }
+        let mut first_error = Ok(());
+        for &i in &systems.order {
+            if let Err(e) = systems.systems[i].commands.apply(world) {
+                first_error = first_error.and(Err(e));
+            }
+        }
+        Ok(first_error?)
+    }
+
+    pub fn systems(&self) -> impl Iterator<Item = &System> {
+        self.stages.iter().flat_map(|s| s.systems.iter())
+    }
+}
+
+/// a parallel system, what it took out of the world, and how running it went
+struct Job<'s> {
+    system: &'s mut System,
+    owned: Owned,
+    result: thread::Result<anyhow::Result<()>>,
+}
+
+/// runs a batch of parallel systems, each on its share of `world`, spread over the calling thread
+/// and `pool`'s. everything is put back into `world` before an error or panic is passed on
+fn run_batch(
+    batch: Vec<&mut System>,
+    world: &mut World,
+    pool: Option<&ThreadPool>,
+) -> anyhow::Result<()> {
+    let mut jobs: Vec<Job> = batch
+        .into_iter()
+        .map(|system| {
+            let owned = world.take_owned(&system.access);
+            Job {
+                system,
+                owned,
+                result: Ok(Ok(())),
+            }
+        })
+        .collect();
+
+    let threads = (pool.map_or(0, ThreadPool::current_num_threads) + 1).min(jobs.len());
+    let mut groups: Vec<Vec<&mut Job>> = (0..threads).map(|_| Vec::new()).collect();
+    for (i, job) in jobs.iter_mut().enumerate() {
+        groups[i % threads].push(job);
+    }
+    let shared = &*world;
+    let run_group = move |group: Vec<&mut Job>| {
+        for job in group {
+            let run = || job.system.run_view(shared, &mut job.owned);
+            job.result = panic::catch_unwind(AssertUnwindSafe(run));
+        }
+    };
+    let mut groups = groups.into_iter();
+    let here = groups.next().unwrap();
+    match pool {
+        Some(pool) if groups.len() > 0 => pool.in_place_scope(|scope| {
+            for group in groups {
+                scope.spawn(move |_| run_group(group));
+            }
+            run_group(here);
+        }),
+        _ => run_group(here),
+    }
+
+    let mut first_error = Ok(());
+    let mut panicked: Option<Box<dyn Any + Send>> = None;
+    for Job { owned, result, .. } in jobs {
+        world.restore(owned);
+        match result {
+            Ok(result) => first_error = first_error.and(result),
+            Err(payload) => {
+                panicked.get_or_insert(payload);
+            }
+        }
+    }
+    if let Some(payload) = panicked {
+        panic::resume_unwind(payload);
+    }
+    first_error
+}
@@ -0,0 +1,289 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use super::{Handle, TypeIdHasher};
+
+/// typeid → the column holding every value of that type
+pub type Columns = HashMap<TypeId, Box<dyn AnyColumn>, BuildHasherDefault<TypeIdHasher>>;
+
+/// where a query gets its columns: all of a world's, or what a `WorldView` was given.
+/// `tick` is the world's change tick, stamped on values the query borrows mutably
+pub enum ColumnSource<'a> {
+    Whole {
+        columns: &'a mut Columns,
+        tick: u64,
+    },
+    /// columns the view owns (its declared writes) and ones it shares with other views
+    Split {
+        owned: &'a mut Columns,
+        shared: &'a Columns,
+        tick: u64,
+    },
+}
+
+impl ColumnSource<'_> {
+    /// pointers into the column for `ty`, which will only be read through
+    pub(crate) fn read<V: Send + Sync + 'static>(&mut self, ty: TypeId) -> Option<ColumnPtr<V>> {
+        match self {
+            Self::Whole { columns, .. } => Some(column::<V>(columns, ty)?.shared_ptr()),
+            Self::Split { owned, shared, .. } => match column::<V>(owned, ty) {
+                Some(column) => Some(column.shared_ptr()),
+                None => Some(column::<V>(shared, ty)?.shared_ptr()),
+            },
+        }
+    }
+    /// pointers into the column for `ty`, which may be written through.
+    /// a view can only write to columns it owns
+    pub(crate) fn write<V: Send + Sync + 'static>(&mut self, ty: TypeId) -> Option<ColumnPtr<V>> {
+        match self {
+            Self::Whole { columns, tick } => Some(column_mut::<V>(columns, ty)?.ptr(*tick)),
+            Self::Split { owned, tick, .. } => Some(column_mut::<V>(owned, ty)?.ptr(*tick)),
+        }
+    }
+}
+
+/// marks an entity index with no row in a column's `sparse` list
+const EMPTY: usize = usize::MAX;
+
+/// every value of one type, packed next to each other so iterating them doesn't chase pointers.
+/// `owners[row]` is the entity that owns `data[row]`, and `sparse[owner.index()]` is `row`
+pub(crate) struct Column<V> {
+    pub(crate) data: Vec<V>,
+    pub(crate) owners: Vec<Handle>,
+    /// the change tick `data[row]` was last inserted or mutably borrowed at
+    pub(crate) ticks: Vec<u64>,
+    /// entity index → row, or `EMPTY`
+    sparse: Vec<usize>,
+    /// called on values removed by `AnyColumn::remove`
+    drop_fn: fn(V),
+    /// shows a value in `World::dump_tree`
+    preview_fn: fn(&V) -> Option<String>,
+    /// the type this column is keyed by, for messages
+    type_name: &'static str,
+}
+
+impl<V: Send + Sync + 'static> Column<V> {
+    pub(crate) fn new(
+        type_name: &'static str,
+        drop_fn: fn(V),
+        preview_fn: fn(&V) -> Option<String>,
+    ) -> Self {
+        Self {
+            data: Vec::new(),
+            owners: Vec::new(),
+            ticks: Vec::new(),
+            sparse: Vec::new(),
+            drop_fn,
+            preview_fn,
+            type_name,
+        }
+    }
+
+    /// the row holding the value of the entity at `index`
+    pub(crate) fn row(&self, index: usize) -> Option<usize> {
+        self.sparse.get(index).copied().filter(|&row| row != EMPTY)
+    }
+    pub(crate) fn get(&self, index: usize) -> Option<&V> {
+        self.row(index).map(|row| &self.data[row])
+    }
+    /// also stamps the value as changed at `tick`
+    pub(crate) fn get_mut(&mut self, index: usize, tick: u64) -> Option<&mut V> {
+        let row = self.row(index)?;
+        self.ticks[row] = tick;
+        Some(&mut self.data[row])
+    }
+    /// every value inserted or mutably borrowed at `since` or later
+    pub(crate) fn changed_since(&self, since: u64) -> impl Iterator<Item = (Handle, &V)> {
+        (0..self.data.len())
+            .filter(move |&row| self.ticks[row] >= since)
+            .map(|row| (self.owners[row], &self.data[row]))
+    }
+
+    /// stores `value` for `owner` as changed at `tick`, handing back the value it replaced
+    pub(crate) fn insert(&mut self, owner: Handle, value: V, tick: u64) -> Option<V> {
+        if let Some(row) = self.row(owner.index()) {
+            self.ticks[row] = tick;
+            return Some(std::mem::replace(&mut self.data[row], value));
+        }
+        if self.sparse.len() <= owner.index() {
+            self.sparse.resize(owner.index() + 1, EMPTY);
+        }
+        self.sparse[owner.index()] = self.data.len();
+        self.data.push(value);
+        self.owners.push(owner);
+        self.ticks.push(tick);
+        None
+    }
+
+    /// removes the value of the entity at `index` and hands it back instead of dropping it.
+    /// the last row is moved into the gap
+    pub(crate) fn take(&mut self, index: usize) -> Option<V> {
+        let row = self.row(index)?;
+        self.sparse[index] = EMPTY;
+        let value = self.data.swap_remove(row);
+        self.owners.swap_remove(row);
+        self.ticks.swap_remove(row);
+        if let Some(moved) = self.owners.get(row) {
+            self.sparse[moved.index()] = row;
+        }
+        Some(value)
+    }
+
+    /// raw pointers for queries, which hand out references to several columns at once.
+    /// values marked through it are stamped with `tick`
+    pub(crate) fn ptr(&mut self, tick: u64) -> ColumnPtr<V> {
+        ColumnPtr {
+            data: self.data.as_mut_ptr(),
+            ticks: self.ticks.as_mut_ptr(),
+            tick,
+            owners: self.owners.as_ptr(),
+            len: self.data.len(),
+            sparse: self.sparse.as_ptr(),
+            sparse_len: self.sparse.len(),
+        }
+    }
+    /// like `ptr`, but from a shared borrow, so nothing may be written through it
+    pub(crate) fn shared_ptr(&self) -> ColumnPtr<V> {
+        ColumnPtr {
+            data: self.data.as_ptr().cast_mut(),
+            ticks: self.ticks.as_ptr().cast_mut(),
+            tick: 0,
+            owners: self.owners.as_ptr(),
+            len: self.data.len(),
+            sparse: self.sparse.as_ptr(),
+            sparse_len: self.sparse.len(),
+        }
+    }
+}
+
+/// a `Column` with its value type erased, so different types can share one map.
+/// `Send + Sync` so a `World` can be shared between the systems of a parallel batch
+pub trait AnyColumn: Any + Send + Sync {
+    /// removes the value of the entity at `index`, dropping it with the column's `drop_fn`.
+    /// returns false if it had none
+    fn remove(&mut self, index: usize) -> bool;
+    fn contains(&self, index: usize) -> bool;
+    fn owners(&self) -> &[Handle];
+    fn type_name(&self) -> &'static str;
+    /// the column's `preview_fn` on the value of the entity at `index`
+    fn preview(&self, index: usize) -> Option<String>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<V: Send + Sync + 'static> AnyColumn for Column<V> {
+    fn remove(&mut self, index: usize) -> bool {
+        match self.take(index) {
+            Some(value) => {
+                (self.drop_fn)(value);
+                true
+            }
+            None => false,
+        }
+    }
+    fn contains(&self, index: usize) -> bool {
+        self.row(index).is_some()
+    }
+    fn owners(&self) -> &[Handle] {
+        &self.owners
+    }
+    fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+    fn preview(&self, index: usize) -> Option<String> {
+        self.get(index).and_then(self.preview_fn)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// looks up the column for `ty`, which must hold `V`s
+pub(crate) fn column<V: Send + Sync + 'static>(
+    columns: &Columns,
+    ty: TypeId,
+) -> Option<&Column<V>> {
+    columns.get(&ty)?.as_any().downcast_ref()
+}
+/// looks up the column for `ty`, which must hold `V`s
+pub(crate) fn column_mut<V: Send + Sync + 'static>(
+    columns: &mut Columns,
+    ty: TypeId,
+) -> Option<&mut Column<V>> {
+    columns.get_mut(&ty)?.as_any_mut().downcast_mut()
+}
+/// looks up the column for `ty`, creating it if this is the first `V` stored under `ty`.
+/// `type_name` is the name of the type behind `ty`
+pub(crate) fn column_or_insert<'c, V: Send + Sync + 'static>(
+    columns: &'c mut Columns,
+    ty: TypeId,
+    type_name: &'static str,
+    drop_fn: fn(V),
+    preview_fn: fn(&V) -> Option<String>,
+) -> &'c mut Column<V> {
+    columns
+        .entry(ty)
+        .or_insert_with(|| Box::new(Column::new(type_name, drop_fn, preview_fn)))
+        .as_any_mut()
+        .downcast_mut()
+        .expect("x_x :: column holds a different type than its key says")
+}
+
+/// a snapshot of a column's buffers. only valid while the column isn't inserted into or removed from.
+/// if it came from `Column::shared_ptr`, it must only be read through
+pub struct ColumnPtr<V> {
+    data: *mut V,
+    ticks: *mut u64,
+    /// what `mark_changed` stamps
+    tick: u64,
+    pub(crate) owners: *const Handle,
+    pub(crate) len: usize,
+    sparse: *const usize,
+    sparse_len: usize,
+}
+
+impl<V> ColumnPtr<V> {
+    /// the row of the entity at `index`
+    ///
+    /// # Safety
+    /// the column this was taken from must not have changed since
+    unsafe fn row(&self, index: usize) -> Option<usize> {
+        if index >= self.sparse_len {
+            return None;
+        }
+        let row = unsafe { *self.sparse.add(index) };
+        if row == EMPTY {
+            return None;
+        }
+        debug_assert!(row < self.len);
+        Some(row)
+    }
+    /// pointer to the value of the entity at `index`
+    ///
+    /// # Safety
+    /// the column this was taken from must not have changed since
+    pub(crate) unsafe fn get(&self, index: usize) -> Option<*mut V> {
+        unsafe { self.row(index).map(|row| self.data.add(row)) }
+    }
+    /// stamps the value of the entity at `index` as changed at the tick this was made with
+    ///
+    /// # Safety
+    /// the column this was taken from must not have changed since, and this must have come
+    /// from `Column::ptr`
+    pub(crate) unsafe fn mark_changed(&self, index: usize) {
+        if let Some(row) = unsafe { self.row(index) } {
+            unsafe { *self.ticks.add(row) = self.tick };
+        }
+    }
+}
+
+impl<V> Clone for ColumnPtr<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<V> Copy for ColumnPtr<V> {}
@@ -0,0 +1,410 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{EntityType, Schedule};
+    use glam::{Quat, Vec3};
+
+    struct Cube;
+    impl EntityType for Cube {}
+    struct Skull;
+    impl EntityType for Skull {}
+
+    fn at(x: f32, y: f32, z: f32) -> Instance {
+        Instance {
+            position: Vec3::new(x, y, z),
+            scale: Vec3::ONE,
+            ..Default::default()
+        }
+    }
+    fn position(w: &World, h: Handle) -> Vec3 {
+        w.get::<GlobalTransform>(h).unwrap().position
+    }
+
+    #[test]
+    fn root_global_is_local() {
+        let mut w = World::new();
+        let h = w.spawn(Cube);
+        w.insert(h, Transform::new(at(1., 2., 3.))).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, h), Vec3::new(1., 2., 3.));
+    }
+
+    #[test]
+    fn child_follows_parent() {
+        let mut w = World::new();
+        let cube = w.spawn(Cube);
+        let skull = w.spawn(Skull);
+        w.insert(cube, Transform::new(at(1., 0., 0.))).unwrap();
+        w.insert(skull, Transform::new(at(0., 1., 0.))).unwrap();
+        w.attach_child::<Skull>(cube, skull).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, skull), Vec3::new(1., 1., 0.));
+
+        // moving the cube drags the skull along
+        w.get_mut::<Transform>(cube).unwrap().position.x = 5.;
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, cube), Vec3::new(5., 0., 0.));
+        assert_eq!(position(&w, skull), Vec3::new(5., 1., 0.));
+    }
+
+    #[test]
+    fn child_rotates_with_parent() {
+        let mut w = World::new();
+        let cube = w.spawn(Cube);
+        let skull = w.spawn(Skull);
+        let mut spin = at(0., 0., 0.);
+        spin.rotation = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        w.insert(cube, Transform::new(spin)).unwrap();
+        w.insert(skull, Transform::new(at(1., 0., 0.))).unwrap();
+        w.attach_child::<Skull>(cube, skull).unwrap();
+        propagate_transforms(&mut w);
+        assert!(position(&w, skull).abs_diff_eq(Vec3::new(0., 0., -1.), 1e-5));
+    }
+
+    #[test]
+    fn grandchildren_and_untransformed_links() {
+        let mut w = World::new();
+        let a = w.spawn(Cube);
+        let middle = w.spawn(Skull); // no Transform, passes its parent's through
+        let c = w.spawn(Skull);
+        w.insert(a, Transform::new(at(1., 0., 0.))).unwrap();
+        w.insert(c, Transform::new(at(0., 0., 1.))).unwrap();
+        w.attach_child::<Skull>(a, middle).unwrap();
+        w.attach_child::<Skull>(middle, c).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, c), Vec3::new(1., 0., 1.));
+        assert!(w.get::<GlobalTransform>(middle).is_err());
+    }
+
+    #[test]
+    fn reparenting_an_untransformed_link_moves_what_is_under_it() {
+        let mut w = World::new();
+        let a = w.spawn(Cube);
+        let b = w.spawn(Cube);
+        let middle = w.spawn(Skull);
+        let c = w.spawn(Skull);
+        w.insert(a, Transform::new(at(1., 0., 0.))).unwrap();
+        w.insert(b, Transform::new(at(10., 0., 0.))).unwrap();
+        w.insert(c, Transform::new(at(0., 0., 0.))).unwrap();
+        w.attach_child::<Skull>(a, middle).unwrap();
+        w.attach_child::<Skull>(middle, c).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, c).x, 1.);
+
+        w.attach_child::<Skull>(b, middle).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, c).x, 10.);
+    }
+
+    #[test]
+    fn cached_anchors_and_depths_follow_moves() {
+        let mut w = World::new();
+        let a = w.spawn(Cube);
+        let b = w.spawn(Cube);
+        let b1 = w.spawn(Cube);
+        let middle = w.spawn(Skull);
+        let c = w.spawn(Skull);
+        w.insert(a, Transform::new(at(1., 0., 0.))).unwrap();
+        w.insert(b, Transform::new(at(0., 0., 7.))).unwrap();
+        w.insert(b1, Transform::new(at(0., 1., 0.))).unwrap();
+        w.insert(c, Transform::new(at(0., 0., 0.))).unwrap();
+        w.attach_child::<Cube>(b, b1).unwrap();
+        w.attach_child::<Skull>(a, middle).unwrap();
+        w.attach_child::<Skull>(middle, c).unwrap();
+        propagate_transforms(&mut w);
+        let cached = |w: &World| {
+            let g = w.get::<GlobalTransform>(c).unwrap();
+            (g.1, g.2)
+        };
+        assert_eq!(cached(&w), (a, 2));
+
+        // the cached depth says 2, but it's 3 now; the move still wins
+        w.get_mut::<Transform>(c).unwrap().position.x = 2.;
+        w.attach_child::<Skull>(b1, middle).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, c), Vec3::new(2., 1., 7.));
+        assert_eq!(cached(&w), (b1, 3));
+
+        let ty = w.entity(middle).unwrap().ty;
+        w.remove_child(b1, middle, ty).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, c), Vec3::new(2., 0., 0.));
+        assert_eq!(cached(&w), (c, 1));
+    }
+
+    #[test]
+    fn removing_a_transform_in_the_middle() {
+        let mut w = World::new();
+        let a = w.spawn(Cube);
+        let middle = w.spawn(Skull);
+        let c = w.spawn(Skull);
+        w.insert(a, Transform::new(at(1., 0., 0.))).unwrap();
+        w.insert(middle, Transform::new(at(5., 0., 0.))).unwrap();
+        w.insert(c, Transform::new(at(0., 0., 0.))).unwrap();
+        w.attach_child::<Skull>(a, middle).unwrap();
+        w.attach_child::<Skull>(middle, c).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, c).x, 6.);
+
+        w.remove::<Transform>(middle).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, c).x, 1.);
+        assert!(w.get::<GlobalTransform>(middle).is_err());
+    }
+
+    #[test]
+    fn clean_entities_are_not_recomputed() {
+        let mut w = World::new();
+        let cube = w.spawn(Cube);
+        let skull = w.spawn(Skull);
+        w.insert(cube, Transform::new(at(1., 0., 0.))).unwrap();
+        w.insert(skull, Transform::new(at(0., 1., 0.))).unwrap();
+        w.attach_child::<Skull>(cube, skull).unwrap();
+        propagate_transforms(&mut w);
+
+        // scribble over the global. nothing is dirty, so nothing fixes it
+        w.get_mut::<GlobalTransform>(skull).unwrap().0.position = Vec3::ZERO;
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, skull), Vec3::ZERO);
+
+        // reading the local doesn't change it either
+        let _ = w.get::<Transform>(skull).unwrap().position;
+        for _ in w.query::<&Transform>() {}
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, skull), Vec3::ZERO);
+
+        // but touching the parent recomputes the whole subtree
+        w.get_mut::<Transform>(cube).unwrap().position.x = 2.;
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, skull), Vec3::new(2., 1., 0.));
+
+        // as does writing through a query, in the same frame
+        for (_, t) in w.query::<&mut Transform>() {
+            t.position.z = 3.;
+        }
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, skull), Vec3::new(2., 1., 6.));
+    }
+
+    #[test]
+    fn reparenting_recomputes() {
+        let mut w = World::new();
+        let a = w.spawn(Cube);
+        let b = w.spawn(Cube);
+        let skull = w.spawn(Skull);
+        w.insert(a, Transform::new(at(1., 0., 0.))).unwrap();
+        w.insert(b, Transform::new(at(0., 0., 7.))).unwrap();
+        w.insert(skull, Transform::new(at(0., 1., 0.))).unwrap();
+        w.attach_child::<Skull>(a, skull).unwrap();
+        propagate_transforms(&mut w);
+
+        w.attach_child::<Skull>(b, skull).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, skull), Vec3::new(0., 1., 7.));
+
+        let ty = w.entity(skull).unwrap().ty;
+        w.remove_child(b, skull, ty).unwrap();
+        propagate_transforms(&mut w);
+        assert_eq!(position(&w, skull), Vec3::new(0., 1., 0.));
+    }
+
+    #[test]
+    fn runs_as_a_system() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(propagate_system()).unwrap();
+        let mut w = World::new();
+        let h = w.spawn(Cube);
+        w.insert(h, Transform::new(at(0., 3., 0.))).unwrap();
+        schedule.run(Stage::PostUpdate, &mut w).unwrap();
+        assert_eq!(position(&w, h), Vec3::new(0., 3., 0.));
+    }
+}
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use super::{Component, Handle, Stage, System, World};
+use crate::renderer::Instance;
+
+/// name of the system `propagate_system` makes, for ordering against it
+pub const PROPAGATE_TRANSFORMS: &str = "propagate_transforms";
+
+/// an entity's transform relative to its parent.
+///
+/// derefs to the `Instance`. borrowing it mutably (`World::get_mut`, or a `&mut Transform`
+/// query) stamps its change tick, so `propagate_transforms` recomputes it and everything under it
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    local: Instance,
+}
+impl Component for Transform {}
+
+impl Transform {
+    pub fn new(local: Instance) -> Self {
+        Self { local }
+    }
+}
+impl Deref for Transform {
+    type Target = Instance;
+    fn deref(&self) -> &Instance {
+        &self.local
+    }
+}
+impl DerefMut for Transform {
+    fn deref_mut(&mut self) -> &mut Instance {
+        &mut self.local
+    }
+}
+
+/// an entity's transform in world space, written by `propagate_transforms`.
+/// entities with a `Transform` get one the first time it runs, and lose it the first run after
+/// the `Transform` is removed.
+///
+/// also caches the nearest transformed entity above it and how many parents up its root is, so
+/// unchanged parts of the tree don't have to be walked to find them. attaching or detaching
+/// anything above it invalidates them
+#[derive(Clone, Copy, Debug)]
+pub struct GlobalTransform(pub Instance, Handle, usize);
+impl Component for GlobalTransform {}
+
+/// what `propagate_transforms` keeps between runs
+struct Propagated {
+    /// the change tick it last ran at. `Transform`s changed at it or later haven't been
+    /// propagated yet
+    tick: u64,
+    /// everything attached or detached since, from its observers
+    moved: Arc<Mutex<Vec<Handle>>>,
+}
+
+impl Deref for GlobalTransform {
+    type Target = Instance;
+    fn deref(&self) -> &Instance {
+        &self.0
+    }
+}
+
+/// recomputes `GlobalTransform`s for every entity whose `Transform` changed since it last ran,
+/// or that was moved to another parent, or lost its `Transform`, along with everything under
+/// them. subtrees where none of that happened aren't visited.
+///
+/// the first run registers attach/detach observers on `world` to find out what moved. it starts
+/// a new change tick with `World::bump_tick`, so changes made after it in the same frame are
+/// picked up next time
+///
+/// entities without a `Transform` pass their parent's global transform on to their children
+pub fn propagate_transforms(world: &mut World) {
+    if world.resource::<Propagated>().is_err() {
+        let moved = Arc::new(Mutex::new(Vec::new()));
+        let m = moved.clone();
+        world.on_attach_any(move |_, _, child| m.lock().unwrap().push(child));
+        let m = moved.clone();
+        world.on_detach_any(move |_, _, child| m.lock().unwrap().push(child));
+        world.insert_resource(Propagated { tick: 0, moved });
+    }
+    let propagated = world.resource::<Propagated>().unwrap();
+    let since = propagated.tick;
+    let moved = std::mem::take(&mut *propagated.moved.lock().unwrap());
+
+    // where to start walking from: (depth, entity, its anchor if it's cached)
+    let mut roots = Vec::new();
+    let orphans: Vec<_> = world
+        .query::<(Option<&Transform>, &GlobalTransform)>()
+        .filter(|(_, (t, _))| t.is_none())
+        .map(|(h, _)| h)
+        .collect();
+    // what was under them is anchored somewhere else now
+    for h in orphans {
+        let _ = world.remove::<GlobalTransform>(h);
+        roots.push((depth(world, h), h, None));
+    }
+    for h in moved {
+        if world.validate(h).is_ok() {
+            roots.push((depth(world, h), h, None));
+        }
+    }
+    let changed: Vec<_> = world
+        .query_changed_component_since::<Transform>(since)
+        .map(|(h, _)| h)
+        .collect();
+    for h in changed {
+        roots.push(match world.get::<GlobalTransform>(h) {
+            Ok(&GlobalTransform(_, anchor, depth)) => (depth, h, Some(anchor)),
+            Err(_) => (depth(world, h), h, None),
+        });
+    }
+    // parents first, so a stale parent's pass covers its stale children
+    roots.sort_by_key(|&(depth, ..)| depth);
+
+    let mut done = vec![false; world.entities.len()];
+    for (depth, root, anchor) in roots {
+        if done[root.index()] {
+            continue;
+        }
+        let anchor = anchor.unwrap_or_else(|| anchor_of(world, root));
+        let above = match anchor == root {
+            true => None,
+            false => world
+                .get::<GlobalTransform>(anchor)
+                .ok()
+                .map(|g| (anchor, g.0)),
+        };
+        let mut stack = vec![(root, depth, above)];
+        while let Some((h, depth, above)) = stack.pop() {
+            done[h.index()] = true;
+            let above = match world.get::<Transform>(h) {
+                Ok(local) => {
+                    let (anchor, global) = match above {
+                        Some((anchor, above)) => (anchor, local.local.apply(&above)),
+                        None => (h, local.local),
+                    };
+                    let _ = world.insert(h, GlobalTransform(global, anchor, depth));
+                    Some((h, global))
+                }
+                Err(_) => above,
+            };
+            stack.extend(
+                world.entities[h]
+                    .children
+                    .iter()
+                    .map(|&c| (c, depth + 1, above)),
+            );
+        }
+    }
+    let tick = world.bump_tick();
+    world.resource_mut::<Propagated>().unwrap().tick = tick;
+}
+
+/// the system that runs `propagate_transforms` in `Stage::PostUpdate`.
+/// `Context` registers it, so you only need this for your own `Schedule`s
+pub fn propagate_system() -> System {
+    System::new(PROPAGATE_TRANSFORMS, |world, _| {
+        propagate_transforms(world);
+        Ok(())
+    })
+    .in_stage(Stage::PostUpdate)
+    .reads::<Transform>()
+    .writes::<GlobalTransform>()
+}
+
+/// how many parents up `handle`'s root is
+fn depth(world: &World, mut handle: Handle) -> usize {
+    let mut depth = 0;
+    while world.entities[handle].parent != handle {
+        handle = world.entities[handle].parent;
+        depth += 1;
+    }
+    depth
+}
+
+/// the nearest entity above `handle` with a `Transform`, or `handle` itself if there isn't one
+fn anchor_of(world: &World, handle: Handle) -> Handle {
+    let (mut child, mut parent) = (handle, world.entities[handle].parent);
+    while parent != child {
+        if world.has::<Transform>(parent).unwrap_or(false) {
+            return parent;
+        }
+        child = parent;
+        parent = world.entities[parent].parent;
+    }
+    handle
+}
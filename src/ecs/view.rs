@@ -0,0 +1,288 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{Component, EntityType};
+
+    struct Foo(i32);
+    impl EntityType for Foo {}
+    struct Pos(f32);
+    impl Component for Pos {}
+    struct Vel(f32);
+    impl Component for Vel {}
+    struct Gravity(f32);
+
+    fn world() -> (World, Handle) {
+        let mut w = World::new();
+        let h = w.spawn(Foo(1));
+        w.insert(h, Pos(0.)).unwrap();
+        w.insert(h, Vel(2.)).unwrap();
+        w.insert_resource(Gravity(-1.));
+        (w, h)
+    }
+    /// runs `f` on a view of `w` the way a parallel batch would
+    fn with_view(w: &mut World, access: Access, f: impl FnOnce(&mut WorldView)) {
+        let mut owned = w.take_owned(&access);
+        f(&mut WorldView::new("test", &access, w, &mut owned));
+        w.restore(owned);
+    }
+
+    #[test]
+    fn declared_access_works_and_is_put_back() {
+        let (mut w, h) = world();
+        let mut access = Access::new();
+        access
+            .write::<Pos>()
+            .read::<Vel>()
+            .write::<Foo>()
+            .read::<Gravity>();
+        with_view(&mut w, access, |view| {
+            let g = view.resource::<Gravity>().unwrap().0;
+            for (_, (pos, vel)) in view.query::<(&mut Pos, &Vel)>() {
+                pos.0 += vel.0 + g;
+            }
+            view.value_mut::<Foo>(h).unwrap().0 = 5;
+        });
+        assert_eq!(w.get::<Pos>(h).unwrap().0, 1.);
+        assert_eq!(w.entity(h).unwrap().downcast::<Foo>().unwrap().0, 5);
+        assert_eq!(w.resource::<Gravity>().unwrap().0, -1.);
+        assert_eq!(w.query_changed::<Foo>().count(), 1);
+    }
+
+    #[test]
+    fn written_types_leave_the_world_while_borrowed() {
+        let (mut w, h) = world();
+        let mut access = Access::new();
+        access.write::<Pos>().write::<Gravity>();
+        let owned = w.take_owned(&access);
+        assert!(w.get::<Pos>(h).is_err());
+        assert!(w.resource::<Gravity>().is_err());
+        assert!(w.get::<Vel>(h).is_ok());
+        w.restore(owned);
+        assert!(w.get::<Pos>(h).is_ok());
+        assert!(w.resource::<Gravity>().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "reads `ostinato::ecs::view::tests::Vel` without declaring it")]
+    fn undeclared_read_panics() {
+        let (mut w, h) = world();
+        with_view(&mut w, Access::new(), |view| {
+            let _ = view.get::<Vel>(h);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "writes `ostinato::ecs::view::tests::Pos` without declaring it")]
+    fn read_only_query_cannot_write() {
+        let (mut w, _) = world();
+        let mut access = Access::new();
+        access.read::<Pos>();
+        with_view(&mut w, access, |view| {
+            view.query::<&mut Pos>().count();
+        });
+    }
+}
+
+use std::any::{TypeId, type_name};
+
+use super::storage::{ColumnSource, Columns, column, column_mut};
+use super::{
+    Access, Component, EntityRef, EntityType, Error, Handle, Query, QueryIter, Resources, World,
+};
+
+/// what a system in a parallel batch took out of the `World`: everything it declared it writes.
+/// nothing else in the batch can see it until it's put back
+#[derive(Default)]
+pub(crate) struct Owned {
+    values: Columns,
+    components: Columns,
+    resources: Resources,
+}
+
+impl World {
+    /// moves the values, components and resources `access` writes out of the world
+    pub(crate) fn take_owned(&mut self, access: &Access) -> Owned {
+        let mut owned = Owned::default();
+        for ty in access.written() {
+            if let Some(c) = self.values.remove(&ty) {
+                owned.values.insert(ty, c);
+            }
+            if let Some(c) = self.components.remove(&ty) {
+                owned.components.insert(ty, c);
+            }
+            if let Some(r) = self.resources.remove(&ty) {
+                owned.resources.insert(ty, r);
+            }
+        }
+        owned
+    }
+    pub(crate) fn restore(&mut self, owned: Owned) {
+        self.values.extend(owned.values);
+        self.components.extend(owned.components);
+        self.resources.extend(owned.resources);
+    }
+}
+
+/// what a parallel system sees of the `World`: everything it declared it reads, shared with the
+/// rest of its batch, and everything it declared it writes, which only it has.
+///
+/// touching anything else panics. entities can't be spawned, killed or re-parented through it;
+/// use the `Commands` the system is handed
+pub struct WorldView<'w> {
+    system: &'static str,
+    access: &'w Access,
+    world: &'w World,
+    owned: &'w mut Owned,
+}
+
+impl<'w> WorldView<'w> {
+    pub(crate) fn new(
+        system: &'static str,
+        access: &'w Access,
+        world: &'w World,
+        owned: &'w mut Owned,
+    ) -> Self {
+        Self {
+            system,
+            access,
+            world,
+            owned,
+        }
+    }
+
+    fn check_read<T: 'static>(&self) {
+        if !self.access.can_read(TypeId::of::<T>()) {
+            self.undeclared("reads", type_name::<T>());
+        }
+    }
+    fn check_write<T: 'static>(&self) {
+        if !self.access.can_write(TypeId::of::<T>()) {
+            self.undeclared("writes", type_name::<T>());
+        }
+    }
+    fn undeclared(&self, how: &str, ty: &str) -> ! {
+        panic!(
+            "x_x :: system `{}` {how} `{ty}` without declaring it",
+            self.system
+        );
+    }
+    /// the columns of `ty`: the view's own if it writes it, the world's otherwise
+    fn values(&self, ty: TypeId) -> &Columns {
+        match self.access.can_write(ty) {
+            true => &self.owned.values,
+            false => &self.world.values,
+        }
+    }
+    fn components(&self, ty: TypeId) -> &Columns {
+        match self.access.can_write(ty) {
+            true => &self.owned.components,
+            false => &self.world.components,
+        }
+    }
+
+    pub fn is_alive(&self, handle: Handle) -> bool {
+        self.world.is_alive(handle)
+    }
+
+    /// the `T` value of `handle`
+    pub fn value<T: EntityType>(&self, handle: Handle) -> Result<&T::DowncastType, Error> {
+        self.check_read::<T>();
+        let ty = TypeId::of::<T>();
+        EntityRef {
+            entity: self.world.entity(handle)?.entity,
+            values: self.values(ty),
+        }
+        .downcast::<T>()
+    }
+    /// marks the value as changed, whether or not it's written to
+    pub fn value_mut<T: EntityType>(
+        &mut self,
+        handle: Handle,
+    ) -> Result<&mut T::DowncastType, Error> {
+        self.check_write::<T>();
+        let entity = self.world.entity(handle)?;
+        if entity.ty != TypeId::of::<T>() {
+            return Err(Error::WrongType(entity.ty));
+        }
+        column_mut::<T::DowncastType>(&mut self.owned.values, TypeId::of::<T>())
+            .and_then(|c| c.get_mut(handle.index(), self.world.tick))
+            .ok_or(Error::InvalidPtr)
+    }
+    /// every live `T` entity, in storage order
+    pub fn iter<T: EntityType>(&self) -> impl Iterator<Item = (Handle, &T::DowncastType)> {
+        self.check_read::<T>();
+        column::<T::DowncastType>(self.values(TypeId::of::<T>()), TypeId::of::<T>())
+            .into_iter()
+            .flat_map(|c| c.owners.iter().copied().zip(c.data.iter()))
+    }
+    /// every live `T` entity, in storage order. marks all of them as changed
+    pub fn iter_mut<T: EntityType>(
+        &mut self,
+    ) -> impl Iterator<Item = (Handle, &mut T::DowncastType)> {
+        self.check_write::<T>();
+        let tick = self.world.tick;
+        column_mut::<T::DowncastType>(&mut self.owned.values, TypeId::of::<T>())
+            .into_iter()
+            .flat_map(move |c| {
+                c.ticks.fill(tick);
+                c.owners.iter().copied().zip(c.data.iter_mut())
+            })
+    }
+
+    pub fn get<C: Component>(&self, handle: Handle) -> Result<&C, Error> {
+        self.check_read::<C>();
+        self.world.validate(handle)?;
+        column::<C>(self.components(TypeId::of::<C>()), TypeId::of::<C>())
+            .and_then(|c| c.get(handle.index()))
+            .ok_or(Error::Missing)
+    }
+    pub fn get_mut<C: Component>(&mut self, handle: Handle) -> Result<&mut C, Error> {
+        self.check_write::<C>();
+        self.world.validate(handle)?;
+        column_mut::<C>(&mut self.owned.components, TypeId::of::<C>())
+            .and_then(|c| c.get_mut(handle.index(), self.world.tick))
+            .ok_or(Error::Missing)
+    }
+
+    pub fn resource<R: Send + Sync + 'static>(&self) -> Result<&R, Error> {
+        self.check_read::<R>();
+        let resources = match self.access.can_write(TypeId::of::<R>()) {
+            true => &self.owned.resources,
+            false => &self.world.resources,
+        };
+        resources
+            .get(&TypeId::of::<R>())
+            .and_then(|r| r.downcast_ref())
+            .ok_or(Error::Missing)
+    }
+    pub fn resource_mut<R: Send + Sync + 'static>(&mut self) -> Result<&mut R, Error> {
+        self.check_write::<R>();
+        self.owned
+            .resources
+            .get_mut(&TypeId::of::<R>())
+            .and_then(|r| r.downcast_mut())
+            .ok_or(Error::Missing)
+    }
+
+    /// like `World::query`. panics if `Q` touches a component in a way the system didn't declare
+    pub fn query<Q: Query>(&mut self) -> QueryIter<'_, Q> {
+        let mut access = Access::new();
+        Q::access(&mut access);
+        if let Some((how, ty)) = access.undeclared_in(self.access) {
+            self.undeclared(how, ty);
+        }
+        QueryIter::new(
+            &self.world.entities,
+            ColumnSource::Split {
+                owned: &mut self.owned.components,
+                shared: &self.world.components,
+                tick: self.world.tick,
+            },
+        )
+    }
+
+    /// the world's change tick; see `World::change_tick`
+    pub fn change_tick(&self) -> u64 {
+        self.world.tick
+    }
+}
@@ -139,10 +139,14 @@ impl MouseData {
     }
 }
 
+/// does nothing headless
 pub fn lock_and_hide_cursor(lock: bool, ctx: &mut Context) {
-    ctx.renderer.window.set_cursor_visible(!lock);
+    if ctx.renderer.is_headless() {
+        return;
+    }
+    ctx.renderer.window().set_cursor_visible(!lock);
     ctx.renderer
-        .window
+        .window()
         .set_cursor_grab(if lock {
             winit::window::CursorGrabMode::Locked
         } else {
@@ -14,8 +14,9 @@ pub use app::*;
 // pub use bytemuck;
 use std::{
     iter,
+    path::PathBuf,
     sync::Arc,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use wgpu::{RenderPass, RenderPipeline};
 
@@ -32,15 +33,12 @@ use winit::{
 };
 
 use crate::{
+    ecs::{Schedule, Stage, World},
     input::{keyboard::KeyboardData, mouse::MouseData},
     prelude::post_pipeline,
-    renderer::Renderer,
+    renderer::{PassId, Renderer},
 };
 
-// TODO: didnt know where to put this.
-// it would be nice to have some kind of render queue so that internally we can reorder draw calls
-// to, say, reduce how often we set bind groups
-//
 /// context; all the information required to run the app
 pub struct Context {
     /// handles alll the rendering shit
@@ -56,6 +54,17 @@ pub struct Context {
     /// keyboard input information
     pub keyboard: KeyboardData,
 
+    /// every entity in the app, plus shared resources like `Time`
+    pub world: World,
+    /// systems run on `world` every frame.
+    /// starts with `ecs::propagate_system`, which keeps `GlobalTransform`s up to date
+    pub schedule: Schedule,
+
+    /// pressing this saves a screenshot to `screenshot-<unix millis>.png`. off by default
+    pub screenshot_key: Option<KeyCode>,
+    /// where the next frame gets saved, see `screenshot`
+    screenshot_path: Option<PathBuf>,
+
     nothing_shader: Option<RenderPipeline>,
 
     // last_frame: Instant,
@@ -64,19 +73,42 @@ pub struct Context {
     pub rapier: rapier::RapierContext<(), ()>,
 }
 
+/// frame timing. `Context` keeps it in `world` as a resource, for systems
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Time {
+    /// seconds since the last frame
+    pub delta: f32,
+    /// seconds since the app started
+    pub elapsed: f32,
+}
+
 const FPS: Duration = Duration::from_millis(1000 / 60);
 
 impl Context {
     pub async fn new(window: Arc<Window>) -> anyhow::Result<Self> {
+        Self::with_renderer(Renderer::new(window).await?)
+    }
+    /// a context with no window, rendering `width`×`height` frames offscreen.
+    /// there's no event loop either, so drive it with `frame`
+    pub async fn new_headless(width: u32, height: u32) -> anyhow::Result<Self> {
+        Self::with_renderer(Renderer::new_headless(width, height).await?)
+    }
+    fn with_renderer(renderer: Renderer) -> anyhow::Result<Self> {
+        let mut schedule = Schedule::new();
+        schedule.add_system(ecs::propagate_system())?;
         Ok(Self {
             nothing_shader: None,
-            renderer: Renderer::new(window).await?,
+            renderer,
             // in order:
             // time buffer
             resources_path: None,
 
             mouse: MouseData::new(true),
             keyboard: KeyboardData::new(),
+            screenshot_key: None,
+            screenshot_path: None,
+            world: World::new(),
+            schedule,
             // last_frame: Instant::now(),
             #[cfg(feature = "rapier3d")]
             rapier: rapier::RapierContext::new((), ()),
@@ -87,6 +119,12 @@ impl Context {
         self.resources_path = Some(path);
     }
 
+    /// saves the next frame, post processing and all, as a png at `path`.
+    /// if the window can't be copied from, it's the frame from before post processing
+    pub fn screenshot(&mut self, path: impl Into<PathBuf>) {
+        self.screenshot_path = Some(path.into());
+    }
+
     async fn init(&mut self) -> anyhow::Result<()> {
         self.renderer.start = Instant::now();
         self.renderer.delta_instant = Instant::now();
@@ -99,11 +137,16 @@ impl Context {
         self.renderer.delta = self.renderer.delta_instant.elapsed().as_secs_f64();
         // println!("{}", self.renderer.delta);
         self.renderer.delta_instant = Instant::now();
+        let elapsed = self.renderer.start.elapsed().as_secs_f32();
         self.renderer.queue.write_buffer(
             &self.renderer.post_uniform.0,
             0,
-            bytemuck::bytes_of(&self.renderer.start.elapsed().as_secs_f32()),
+            bytemuck::bytes_of(&elapsed),
         );
+        self.world.insert_resource(Time {
+            delta: self.renderer.delta as f32,
+            elapsed,
+        });
         //
         // let time_buffer = self
         //     .renderer
@@ -115,39 +158,82 @@ impl Context {
         //     bytemuck::cast_slice(&[self.start.elapsed().as_secs_f32()]),
         // );
 
+        self.world.advance_tick();
+        self.schedule.run(Stage::PreUpdate, &mut self.world)?;
         handler.update(self)?;
+        self.schedule.run(Stage::Update, &mut self.world)?;
+        self.schedule.run(Stage::PostUpdate, &mut self.world)?;
+        // `render` can only fail with a surface error, so pre-render systems run here.
+        // nothing happens between the end of `update` and `render` anyway
+        self.schedule.run(Stage::PreRender, &mut self.world)?;
 
+        if let Some(key) = self.screenshot_key
+            && self.keyboard.just_pressed(key)
+        {
+            let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+            self.screenshot(format!("screenshot-{millis}.png"));
+        }
         self.mouse.update();
         self.keyboard.update();
 
         Ok(())
     }
 
+    /// runs a whole frame, `update` then `render`, like the event loop does on every redraw.
+    /// for headless contexts
+    pub fn frame<T: AppHandler>(&mut self, handler: &mut T) -> anyhow::Result<()> {
+        self.update(handler)?;
+        self.render(handler)?;
+        Ok(())
+    }
+
     /// renders frames!
-    fn render<T: AppHandler>(&mut self, handler: &mut T) -> anyhow::Result<(), wgpu::SurfaceError> {
+    fn render<T: AppHandler>(&mut self, handler: &mut T) -> anyhow::Result<()> {
         // must be done before rendering and shit
-        self.renderer.window.request_redraw();
+        self.renderer.request_redraw();
         // instead of throwing an error we just pass this frame and wait for it to be true
         if !self.renderer.is_surface_configured {
             return Ok(());
         }
-        let mut encoder = self.renderer.command_encoder();
-        let mut pass = self.renderer.render_pass(&mut encoder)?;
-
-        // let the user render
-        handler.render(self, &mut pass)?;
-
-        // pass borrows encoder which conflicts with encoder. should just use a scope but im silly 😝
-        drop(pass);
-        self.renderer.queue.submit(iter::once(encoder.finish()));
-        let mut encoder = self.renderer.command_encoder();
-        let (tex, mut pass) = self.renderer.post_pass(&mut encoder)?;
-        pass.set_bind_group(0, Some(&self.renderer.post_uniform.2), &[]);
-        pass.set_bind_group(1, Some(&self.renderer.scene_bind_group.1), &[]);
-        handler.post_process(self, &mut pass)?;
-        drop(pass);
-        self.renderer.queue.submit(iter::once(encoder.finish()));
-        tex.present();
+        self.renderer.prepare_graph();
+        let mut frame = None;
+        for id in self.renderer.graph.order().to_vec() {
+            let mut encoder = self.renderer.command_encoder();
+            let mut pass = self
+                .renderer
+                .begin_graph_pass(id, &mut encoder, &mut frame)?;
+            // let the user render
+            match id {
+                PassId::SCENE => handler.render(self, &mut pass)?,
+                PassId::POST => handler.post_process(self, &mut pass)?,
+                id => handler.graph_pass(self, id, &mut pass)?,
+            }
+            // pass borrows encoder which conflicts with encoder. should just use a scope but im silly 😝
+            drop(pass);
+            self.renderer.queue.submit(iter::once(encoder.finish()));
+        }
+        let capture = self.screenshot_path.take().map(|path| {
+            let texture = frame
+                .as_ref()
+                .and_then(|(frame, _)| self.renderer.frame_texture(frame))
+                .unwrap_or_else(|| self.renderer.ldr_scene_texture());
+            (path, self.renderer.capture_texture(texture))
+        });
+        if let Some((frame, _)) = frame {
+            frame.present();
+        }
+        if let Some((path, capture)) = capture {
+            // a failed screenshot shouldn't take the frame down with it
+            let saved = capture
+                .and_then(|c| c.read(&self.renderer.device))
+                .and_then(|image| Ok(image.save(&path)?));
+            if let Err(e) = saved {
+                eprintln!(
+                    "x_x :: couldn't save screenshot to {}: {e:#}",
+                    path.display()
+                );
+            }
+        }
         Ok(())
     }
 
@@ -210,8 +296,8 @@ pub trait AppHandler: Sized {
     #[allow(async_fn_in_trait)]
     async fn new(context: &mut Context) -> Result<Self>;
 
-    /// called once every frame. you are given one `RenderPass`
-    /// if you want another, contribute to the library and make `Context` a trait so you can make custom event loops
+    /// called once every frame to draw the `PassId::SCENE` pass of `context.renderer.graph`.
+    /// add passes to the graph if you want more, and draw them in `graph_pass`
     // TODO not make this a surfacerror
     fn render(
         &mut self,
@@ -228,6 +314,16 @@ pub trait AppHandler: Sized {
         context: &mut Context,
         pass: &mut RenderPass<'_>,
     ) -> Result<(), wgpu::SurfaceError>;
+    /// draws the passes you added to `context.renderer.graph`, once each every frame in the
+    /// graph's order. does nothing by default
+    fn graph_pass(
+        &mut self,
+        _context: &mut Context,
+        _pass: PassId,
+        _render_pass: &mut RenderPass<'_>,
+    ) -> Result<(), wgpu::SurfaceError> {
+        Ok(())
+    }
 
     fn window_attributes() -> WindowAttributes {
         WindowAttributes::default().with_inner_size(Size::Physical(PhysicalSize {
@@ -239,10 +335,14 @@ pub trait AppHandler: Sized {
 
 pub mod prelude {
     pub use crate::{
-        AppHandler, Context, camera,
+        AppHandler, Context, Time, camera,
         mesh::{self, Mesh, vertex},
-        renderer::{Instance, Renderable, Renderer, post_pipeline},
-        resources::{load_model, load_pipeline},
+        renderer::{
+            AttachmentDesc, AttachmentId, Binding, Bloom, BloomSettings, Instance, Level,
+            LeveledPipeline, Levels, PassDesc, PassId, PostEffect, PostStack, Queueable,
+            RenderGraph, RenderQueue, Renderable, Renderer, Tonemap, Tonemapper, post_pipeline,
+        },
+        resources::{load_leveled_pipeline, load_model, load_pipeline},
     };
     // TODO: dont do this.
     pub use anyhow::Result;
@@ -5,7 +5,7 @@ use std::{
 
 use ostinato::{
     AppHandler, Context,
-    camera::light::LightUniform,
+    camera::light::{Environment, LightUniform},
     mesh::{
         Model, ObjModel, StorageMesh, new_cube,
         vertex::{ModelVertex, SimpleVertex, StepInstance, VertexBuffer},
@@ -13,13 +13,12 @@ use ostinato::{
     prelude::*,
     resources::{
         Texture,
-        blinn_phong::{Material, light_binding},
+        blinn_phong::Material,
         load_pipeline, load_texture,
     },
 };
 use wgpu::{
-    BindGroup, BindGroupDescriptor, BindGroupEntry, Buffer, BufferUsages, PrimitiveState,
-    RenderPipeline,
+    BindGroupDescriptor, BindGroupEntry, Buffer, BufferUsages, PrimitiveState, RenderPipeline,
     util::{BufferInitDescriptor, DeviceExt},
 };
 use winit::{
@@ -35,15 +34,13 @@ fn main() {
 /// example handler. not for outside use.
 /// full namespace paths so it doesnt clutter this file
 /// so much for above LOLW LOLW LOLW LOWL LOLW LOLW LOLW
+/// the camera and the lights are `context.world` resources, so systems can get at them too
 #[allow(dead_code)]
 pub struct ExampleHandler {
-    camera: ostinato::camera::Camera,
     camera_controller: ostinato::camera::CameraController,
     cube: Mesh<ModelVertex>,
     wireframe: StorageMesh,
     clickbait: Clickbait,
-    lights: Vec<LightUniform>,
-    light_binding: (Buffer, BindGroup),
 
     pipelines: [RenderPipeline; 5],
 
@@ -140,17 +137,11 @@ impl AppHandler for ExampleHandler {
         //     height: 600,
         // });
 
-        let lights = vec![LightUniform::new([-3., 5., 6.5], [1., 1., 1.], 30.)];
-        let light_buffer = renderer
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Lgiht buf"),
-                contents: bytemuck::cast_slice(&lights),
-                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
-            });
-        let light_bg = light_binding(&renderer.device, &light_buffer);
+        let environment = Environment::new(
+            &[LightUniform::new([-3., 5., 6.5], [1., 1., 1.], 30.)],
+            &renderer.device,
+        );
 
-        // renderer.shader_resources.insert("lights", light_buffer);
         let material = Material {
             diffuse: [0., 1., 0.],
             ambient: [0., 0.1, 0.],
@@ -187,16 +178,16 @@ impl AppHandler for ExampleHandler {
         //wireframe.transform.scale = glam::Vec3::new(1.2, 1.2, 1.2);
         //wireframe.transform.rotation = glam::Quat::from_rotation_y(PI);
 
+        context.world.insert_resource(camera);
+        context.world.insert_resource(environment);
+
         Ok(Self {
             // cube: None,
             // cube2: None,
-            camera,
             cube,
             wireframe,
             clickbait,
-            lights,
             camera_controller: ostinato::camera::CameraController::new(0.15, 1.),
-            light_binding: (light_buffer, light_bg),
             pipelines,
             skull: ObjModel::from_model(
                 skull,
@@ -230,10 +221,17 @@ impl AppHandler for ExampleHandler {
             0.,
             1.,
         );
-        pass.set_bind_group(0, Some(&self.camera.bind_group), &[]);
+        // nothing to draw with if something took them out of the world
+        let (Ok(camera), Ok(environment)) = (
+            context.world.resource::<ostinato::camera::Camera>(),
+            context.world.resource::<Environment>(),
+        ) else {
+            return Ok(());
+        };
+        pass.set_bind_group(0, Some(&camera.bind_group), &[]);
 
         pass.set_pipeline(&self.pipelines[4]);
-        pass.set_bind_group(1, Some(&self.light_binding.1), &[]);
+        pass.set_bind_group(1, Some(environment.bind_group()), &[]);
         // pass.set_bind_group(2, Some(&self.jaw_bg), &[]);
         // self.skull.meshes[0].draw(pass, &mut context.renderer);
         // for i in 0..3 {
@@ -243,7 +241,7 @@ impl AppHandler for ExampleHandler {
 
         self.skull.draw(pass, &mut context.renderer);
         // pass.set_pipeline(&self.pipelines[0]);
-        // pass.set_bind_group(1, Some(&self.light_binding.1), &[]);
+        // pass.set_bind_group(1, Some(environment.bind_group()), &[]);
         // self.cube.draw(pass, &mut context.renderer);
         //
         // pass.set_pipeline(&self.pipelines[1]);
@@ -263,25 +261,25 @@ impl AppHandler for ExampleHandler {
             win.set_decorations(boo);
             win.set_cursor_hittest(boo).unwrap();
         }
-        // dbg!(self.lights[0].position);
+        // dbg!(context.world.resource::<Environment>()?.lights()[0].position);
+        // let camera = context.world.resource_mut::<ostinato::camera::Camera>()?;
         // self.camera_controller
-        //     .update_keyboard(&mut self.camera, &context.keyboard);
+        //     .update_keyboard(camera, &context.keyboard);
         // self.camera_controller
-        //     .update_camera(&mut self.camera, &context.mouse, &context.keyboard);
+        //     .update_camera(camera, &context.mouse, &context.keyboard);
         // maybe bundle these two lines into a Camera method that takes `&mut self, renderer: &mut Renderer`
-        // self.camera.uniform.update_view_proj(self.camera.config());
+        // camera.uniform.update_view_proj(camera.config());
+        let camera = context.world.resource::<ostinato::camera::Camera>()?;
         context.renderer.queue.write_buffer(
-            &self.camera.buffer,
+            &camera.buffer,
             0,
-            bytemuck::cast_slice(&[self.camera.uniform]),
+            bytemuck::cast_slice(&[camera.uniform]),
         );
         let elapsed = context.renderer.start.elapsed().as_secs_f32();
-        // self.lights[0].position = [5. * SQRT_2 * elapsed.cos(), 5., 5. * SQRT_2 * elapsed.sin()];
-        // context.renderer.queue.write_buffer(
-        //     &self.light_binding.0,
-        //     0,
-        //     bytemuck::cast_slice(&self.lights),
-        // );
+        // let environment = context.world.resource_mut::<Environment>()?;
+        // environment.light_mut(0).unwrap().position =
+        //     [5. * SQRT_2 * elapsed.cos(), 5., 5. * SQRT_2 * elapsed.sin()];
+        // environment.update(&context.renderer.device, &context.renderer.queue);
         let s = win.inner_size();
         let horizontal = (-(s.width as f64) + context.mouse.mouse_position.x + 250.).atan2(1000.0);
         let vertical = (-(s.height as f64) + context.mouse.mouse_position.y + 250.).atan2(1000.0);
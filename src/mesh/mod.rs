@@ -13,7 +13,7 @@ use wgpu::{Device, Queue, util::DeviceExt};
 use crate::{
     Renderer,
     prelude::Renderable,
-    renderer::{Instance, InstanceRaw},
+    renderer::{Instance, InstanceRaw, Queueable, RenderQueue},
     resources::blinn_phong::Material,
 };
 
@@ -422,6 +422,15 @@ impl<V: VertexBuffer> Renderable for InstancedMesh<V> {
         pass.draw_indexed(0..self.mesh.num_elements, 0, instances);
     }
 }
+impl<V: VertexBuffer> Queueable for InstancedMesh<V> {
+    fn queue(&self, queue: &mut RenderQueue) {
+        self.queue_instances(queue, 0..self.instances.instances.len() as u32);
+    }
+    fn queue_instances(&self, queue: &mut RenderQueue, instances: std::ops::Range<u32>) {
+        queue.set_vertex_buffer(1, &self.instances.buffer);
+        self.mesh.queue_instances(queue, instances);
+    }
+}
 impl<V: VertexBuffer> Renderable for Mesh<V> {
     fn draw_instances(
         &self,
@@ -439,3 +448,27 @@ impl<V: VertexBuffer> Renderable for Mesh<V> {
         pass.draw_indexed(0..self.num_elements, 0, instances);
     }
 }
+impl<V: VertexBuffer> Queueable for Mesh<V> {
+    fn queue_instances(&self, queue: &mut RenderQueue, instances: std::ops::Range<u32>) {
+        self.queue_transformed(self.transform, queue, instances);
+    }
+}
+impl<V: VertexBuffer> Mesh<V> {
+    /// queues the mesh as if `transform` were its own
+    pub(crate) fn queue_transformed(
+        &self,
+        transform: Instance,
+        queue: &mut RenderQueue,
+        instances: std::ops::Range<u32>,
+    ) {
+        queue.set_index_buffer(&self.index_buffer, wgpu::IndexFormat::Uint32);
+        queue.set_vertex_buffer(0, &self.vertex_buffer);
+        queue.set_immediates(0, bytemuck::cast_slice(&[transform.to_raw()]));
+        queue.set_immediates(
+            std::mem::size_of::<InstanceRaw>() as u32,
+            bytemuck::bytes_of(&self.material.to_raw()),
+        );
+        queue.set_origin(transform.position + transform.pivot);
+        queue.draw_indexed(0..self.num_elements, 0, instances);
+    }
+}
@@ -1,4 +1,4 @@
-use wgpu::{BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, Device};
+use wgpu::{BindGroup, BindGroupLayoutEntry, Device};
 
 use crate::{
     Context,
@@ -7,7 +7,7 @@ use crate::{
         vertex::{ModelVertex, VertexBuffer},
     },
     prelude::{Instance, Renderable},
-    renderer::InstanceRaw,
+    renderer::{Binding, InstanceRaw, Level, Queueable, RenderQueue},
     resources::{Texture, load_texture},
 };
 
@@ -45,6 +45,18 @@ impl<V: VertexBuffer> Renderable for Model<V> {
     }
 }
 
+impl<V: VertexBuffer> Queueable for Model<V> {
+    fn queue_instances(&self, queue: &mut RenderQueue, instances: std::ops::Range<u32>) {
+        for mesh in &self.meshes {
+            mesh.queue_transformed(
+                self.transform.apply(&mesh.transform),
+                queue,
+                instances.clone(),
+            );
+        }
+    }
+}
+
 pub struct ObjModel {
     pub meshes: Vec<ObjMesh>,
     pub transform: Instance,
@@ -58,15 +70,13 @@ impl ObjModel {
         if model.meshes.len() != textures.len() {
             panic!("a")
         }
-        let bgl = ObjMesh::bind_group_layout(&context.renderer.device);
-
         let mut old_meshes = model.meshes.into_iter();
         let mut meshes = Vec::with_capacity(textures.len());
         for i in 0..textures.len() {
             let texture = load_texture(textures[i][0], context).await?;
             let roughness = load_texture(textures[i][1], context).await?;
 
-            let bg = ObjMesh::bind_group(&bgl, [&texture, &roughness], &context.renderer.device);
+            let bg = ObjMesh::bind_group([&texture, &roughness], &context.renderer.device);
             meshes.push(ObjMesh {
                 mesh: old_meshes.next().unwrap(),
                 texture,
@@ -113,74 +123,59 @@ impl Renderable for ObjModel {
     }
 }
 
+impl Queueable for ObjModel {
+    fn queue_instances(&self, queue: &mut RenderQueue, instances: std::ops::Range<u32>) {
+        for mesh in &self.meshes {
+            queue.set_bind_group(Level::Object, &mesh.bind_group);
+            mesh.mesh.queue_transformed(
+                self.transform.apply(&mesh.mesh.transform),
+                queue,
+                instances.clone(),
+            );
+        }
+    }
+}
+
 pub struct ObjMesh {
     pub mesh: Mesh<ModelVertex>,
     pub texture: Texture,
     pub roughness: Texture,
     pub bind_group: BindGroup,
 }
-impl ObjMesh {
-    fn bind_group_layout(device: &Device) -> BindGroupLayout {
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("texture"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 3,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-        })
+/// the texture and the roughness map, each with its sampler
+impl Binding for ObjMesh {
+    const LEVEL: Level = Level::Object;
+    const LABEL: &'static str = "texture";
+    fn entries() -> Vec<BindGroupLayoutEntry> {
+        let texture = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let sampler = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        vec![texture(0), sampler(1), texture(2), sampler(3)]
     }
-    fn bind_group(bgl: &BindGroupLayout, textures: [&Texture; 2], device: &Device) -> BindGroup {
-        device.create_bind_group(&BindGroupDescriptor {
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&textures[0].view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&textures[0].sampler),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&textures[1].view),
-                },
-                BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Sampler(&textures[1].sampler),
-                },
+}
+impl ObjMesh {
+    fn bind_group(textures: [&Texture; 2], device: &Device) -> BindGroup {
+        Self::create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(&textures[0].view),
+                wgpu::BindingResource::Sampler(&textures[0].sampler),
+                wgpu::BindingResource::TextureView(&textures[1].view),
+                wgpu::BindingResource::Sampler(&textures[1].sampler),
             ],
-            label: None,
-            layout: bgl,
-        })
+        )
     }
 }
@@ -1,13 +1,11 @@
 use std::fmt::Debug;
 
-use wgpu::{
-    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BufferUsages, ShaderStages, util::DeviceExt,
-};
+use wgpu::{BindGroup, BindGroupLayoutEntry, BufferUsages, ShaderStages, util::DeviceExt};
 
 use crate::{
     mesh::vertex::{SimpleVertex, StepVertex, VertexBuffer},
     prelude::{Instance, Mesh, Renderable, Renderer},
+    renderer::{Binding, Level, Queueable, RenderQueue},
 };
 
 /// LOLOLOLOLOL i spent ages trying to make this work (poor docs for immediates) and apparently i
@@ -82,52 +80,15 @@ impl StorageMesh {
         }
     }
 
-    pub fn bind_group_layout(device: &wgpu::Device) -> BindGroupLayout {
-        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: Some("storagmesh storage bind group"),
-            entries: &[
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStages::all(),
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStages::all(),
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        })
-    }
     pub fn bind_group(
         vbuf: &wgpu::Buffer,
         ibuf: &wgpu::Buffer,
         device: &wgpu::Device,
     ) -> BindGroup {
-        device.create_bind_group(&BindGroupDescriptor {
-            label: Some("storagemesh verts and indices bind group"),
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: vbuf.as_entire_binding(),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: ibuf.as_entire_binding(),
-                },
-            ],
-            layout: &Self::bind_group_layout(device),
-        })
+        Self::create_bind_group(
+            device,
+            &[vbuf.as_entire_binding(), ibuf.as_entire_binding()],
+        )
     }
 
     pub fn update_buffers(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
@@ -243,6 +204,25 @@ impl StorageMesh {
     }
 }
 
+/// the vertices and the indices, both read-only storage buffers
+impl Binding for StorageMesh {
+    const LEVEL: Level = Level::Object;
+    const LABEL: &'static str = "storagemesh verts and indices bind group";
+    fn entries() -> Vec<BindGroupLayoutEntry> {
+        let storage = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::all(),
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        vec![storage(0), storage(1)]
+    }
+}
+
 impl Renderable for StorageMesh {
     fn draw_instances(
         &self,
@@ -256,3 +236,12 @@ impl Renderable for StorageMesh {
         pass.draw(0..self.num_elements, instances);
     }
 }
+impl Queueable for StorageMesh {
+    fn queue_instances(&self, queue: &mut RenderQueue, instances: std::ops::Range<u32>) {
+        queue.set_immediates(0, bytemuck::cast_slice(&[self.transform.to_raw()]));
+        queue.set_bind_group(Level::Object, &self.bind_group);
+        queue.set_immediates(112, bytemuck::cast_slice(&[self.color]));
+        queue.set_origin(self.transform.position + self.transform.pivot);
+        queue.draw(0..self.num_elements, instances);
+    }
+}
@@ -0,0 +1,404 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(width: u32, height: u32) -> Context {
+        let mut context = pollster::block_on(Context::new_headless(width, height)).unwrap();
+        context.set_resource_directory(format!("{}/res", env!("CARGO_MANIFEST_DIR")));
+        context
+    }
+
+    #[test]
+    fn core_omi_has_the_defaults() {
+        let mut context = context(8, 8);
+        let loaded = pollster::block_on(Bloom::load(
+            "core_shaders/post_processing/bloom",
+            &mut context,
+        ))
+        .unwrap();
+        assert_eq!(loaded.settings, BloomSettings::default());
+        assert!(pollster::block_on(Bloom::load("nowhere", &mut context)).is_err());
+    }
+
+    #[test]
+    fn chain_halves_down_to_mips() {
+        let mut context = context(64, 32);
+        let bloom = Bloom::new(&mut context).with_settings(BloomSettings {
+            mips: 3,
+            ..Default::default()
+        });
+        let renderer = &mut context.renderer;
+        renderer.bloom = Some(bloom);
+        let mut encoder = renderer.command_encoder();
+        assert!(renderer.run_bloom(&mut encoder).is_some());
+        let sizes = |renderer: &Renderer| -> Vec<_> {
+            let targets = renderer.bloom.as_ref().unwrap().targets.as_ref();
+            targets.map_or(vec![], |t| {
+                t.chain
+                    .iter()
+                    .map(|(t, _)| (t.texture.width(), t.texture.height()))
+                    .collect()
+            })
+        };
+        assert_eq!(sizes(renderer), [(32, 16), (16, 8), (8, 4)]);
+
+        renderer.bloom.as_mut().unwrap().settings.mips = 2;
+        renderer.run_bloom(&mut encoder);
+        assert_eq!(sizes(renderer), [(32, 16), (16, 8)]);
+
+        renderer.resize(16, 16);
+        assert!(sizes(renderer).is_empty());
+        renderer.run_bloom(&mut encoder);
+        assert_eq!(sizes(renderer), [(8, 8), (4, 4)]);
+
+        renderer.bloom.as_mut().unwrap().settings.mips = 40;
+        renderer.run_bloom(&mut encoder);
+        assert_eq!(sizes(renderer), [(8, 8), (4, 4), (2, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn omi_mips_are_checked() {
+        let dir = std::env::temp_dir().join("ostinato_bloom_mips");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut context = context(8, 8);
+        context.set_resource_directory(dir.to_str().unwrap().to_string());
+        for mips in ["0", "17", "4294967297", "lots"] {
+            std::fs::write(dir.join("bloom.omi"), format!("mips: {mips}")).unwrap();
+            let Err(e) = pollster::block_on(Bloom::load("bloom", &mut context)) else {
+                panic!("x_x :: `mips: {mips}` loaded");
+            };
+            assert!(e.to_string().contains("`mips`"), "{e}");
+        }
+    }
+}
+
+use anyhow::anyhow;
+use serde_yaml::Value;
+use wgpu::{BindGroup, BindGroupLayout, CommandEncoder, RenderPipeline, Sampler, TextureFormat};
+
+use super::Renderer;
+use crate::{
+    Context,
+    resources::{Texture, load_shader, load_string},
+};
+
+/// how `Bloom` looks. the defaults are the same as `core_shaders/post_processing/bloom.omi`'s
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomSettings {
+    /// brightness bloom starts at. 0 blooms everything, which is the physically based look
+    pub threshold: f32,
+    /// how far under `threshold` it eases in
+    pub knee: f32,
+    /// how much of the bloom is mixed into the scene
+    pub intensity: f32,
+    /// in uv, how far each step back up spreads
+    pub radius: f32,
+    /// how many times the scene's halved. it stops once the smallest mip would be a pixel
+    pub mips: u32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.,
+            knee: 0.5,
+            intensity: 0.04,
+            radius: 0.005,
+            mips: 5,
+        }
+    }
+}
+
+/// a physically based bloom, drawn over the scene before it's tonemapped: a bright pass, a chain
+/// of smaller and smaller mips, then back up adding each onto the last, mixed into the scene.
+///
+/// it's meant for hdr (`Renderer::set_hdr`), where bright things can go past 1, but works on any
+/// scene. turn it on with `Renderer::bloom`
+pub struct Bloom {
+    pub settings: BloomSettings,
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    prefilter: RenderPipeline,
+    downsample: RenderPipeline,
+    upsample: RenderPipeline,
+    composite: RenderPipeline,
+    /// made the first time they're needed
+    targets: Option<BloomTargets>,
+}
+
+struct BloomTargets {
+    /// `SCENE`, through the bloom's filtering sampler
+    scene: BindGroup,
+    /// the first is half the size of the scene, and so on
+    chain: Vec<(Texture, BindGroup)>,
+    /// the scene with bloom, readable like `SCENE`
+    result: (Texture, BindGroup),
+}
+
+impl Bloom {
+    /// what the mips and the result are, so bright bits don't clip even without hdr
+    pub const FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+    const SHADER: &str = "core_shaders/post_processing/bloom.wgsl";
+    /// the most `mips` an .omi can ask for. a 65536 pixel scene halves this many times
+    pub const MAX_MIPS: u32 = 16;
+
+    /// with the default settings
+    pub fn new(context: &mut Context) -> Self {
+        Self::with_shader(Self::SHADER, context).unwrap()
+    }
+
+    /// settings from `{file_name}.omi`. any of `threshold`, `knee`, `intensity`, `radius` and
+    /// `mips` left out are the same as `new`'s, and `shader` can swap in another bloom.wgsl
+    pub async fn load(file_name: &str, context: &mut Context) -> anyhow::Result<Self> {
+        let text = load_string(&format!("{file_name}.omi"), &context.resources_path).await?;
+        let root: Value = serde_yaml::from_str(&text)?;
+        let invalid = |key: &str| anyhow!("x_x :: invalid bloom yaml! field `{key}` is invalid");
+
+        let shader = match root.get("shader") {
+            Some(value) => value.as_str().ok_or_else(|| invalid("shader"))?,
+            None => Self::SHADER,
+        };
+        let mut settings = BloomSettings::default();
+        for (key, setting) in [
+            ("threshold", &mut settings.threshold),
+            ("knee", &mut settings.knee),
+            ("intensity", &mut settings.intensity),
+            ("radius", &mut settings.radius),
+        ] {
+            if let Some(value) = root.get(key) {
+                *setting = value.as_f64().ok_or_else(|| invalid(key))? as f32;
+            }
+        }
+        if let Some(value) = root.get("mips") {
+            settings.mips = value
+                .as_u64()
+                .filter(|mips| (1..=Self::MAX_MIPS as u64).contains(mips))
+                .ok_or_else(|| invalid("mips"))? as u32;
+        }
+        Ok(Self::with_shader(shader, context)?.with_settings(settings))
+    }
+
+    pub fn with_settings(mut self, settings: BloomSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    fn with_shader(shader_path: &str, context: &mut Context) -> anyhow::Result<Self> {
+        let module = load_shader(shader_path, context)?;
+        let renderer = &context.renderer;
+        let device = &renderer.device;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bloom texture bgl"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("bloom sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let pipeline_layout = |groups: &[&BindGroupLayout]| {
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("bloom pipeline layout"),
+                bind_group_layouts: groups,
+                immediate_size: 16,
+            })
+        };
+        let one_source = pipeline_layout(&[&renderer.post_uniform.1, &layout]);
+        let two_sources = pipeline_layout(&[&renderer.post_uniform.1, &layout, &layout]);
+        let pipeline = |entry_point: &str, layout: &wgpu::PipelineLayout, blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point: Some(entry_point),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: Self::FORMAT,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview_mask: None,
+                cache: None,
+            })
+        };
+        let add = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        Ok(Self {
+            settings: BloomSettings::default(),
+            prefilter: pipeline("fs_prefilter", &one_source, None),
+            downsample: pipeline("fs_downsample", &one_source, None),
+            upsample: pipeline(
+                "fs_upsample",
+                &one_source,
+                Some(wgpu::BlendState {
+                    color: add,
+                    alpha: add,
+                }),
+            ),
+            composite: pipeline("fs_composite", &two_sources, None),
+            layout,
+            sampler,
+            targets: None,
+        })
+    }
+
+    /// drops the textures, so they're remade the new size
+    pub(crate) fn resize(&mut self) {
+        self.targets = None;
+    }
+}
+
+impl Renderer {
+    /// draws `bloom` over `SCENE`, and returns the bind group of the result if bloom's on
+    pub(crate) fn run_bloom(&mut self, encoder: &mut CommandEncoder) -> Option<BindGroup> {
+        let bloom = self.bloom.as_ref()?;
+        // the smallest mip is at least a pixel across
+        let most = self.config.width.min(self.config.height).ilog2().max(1);
+        let mips = bloom.settings.mips.clamp(1, most) as usize;
+        if bloom.targets.as_ref().is_none_or(|t| t.chain.len() != mips) {
+            let targets = self.bloom_targets(bloom, mips);
+            self.bloom.as_mut().unwrap().targets = Some(targets);
+        }
+
+        let bloom = self.bloom.as_ref().unwrap();
+        let BloomTargets {
+            scene,
+            chain,
+            result,
+        } = bloom.targets.as_ref().unwrap();
+        let BloomSettings {
+            threshold,
+            knee,
+            intensity,
+            radius,
+            ..
+        } = bloom.settings;
+        let immediates = [threshold, knee, radius, intensity];
+        let immediates = bytemuck::cast_slice(&immediates);
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+
+        self.draw_fullscreen(
+            encoder,
+            "bloom prefilter",
+            &chain[0].0.view,
+            clear,
+            &bloom.prefilter,
+            &[scene],
+            immediates,
+        );
+        for i in 1..mips {
+            self.draw_fullscreen(
+                encoder,
+                "bloom downsample",
+                &chain[i].0.view,
+                clear,
+                &bloom.downsample,
+                &[&chain[i - 1].1],
+                immediates,
+            );
+        }
+        for i in (1..mips).rev() {
+            self.draw_fullscreen(
+                encoder,
+                "bloom upsample",
+                &chain[i - 1].0.view,
+                wgpu::LoadOp::Load,
+                &bloom.upsample,
+                &[&chain[i].1],
+                immediates,
+            );
+        }
+        self.draw_fullscreen(
+            encoder,
+            "bloom composite",
+            &result.0.view,
+            clear,
+            &bloom.composite,
+            &[scene, &chain[0].1],
+            immediates,
+        );
+        Some(result.1.clone())
+    }
+
+    fn bloom_targets(&self, bloom: &Bloom, mips: usize) -> BloomTargets {
+        let bind_group = |texture: &Texture| {
+            self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("bloom texture bg"),
+                layout: &bloom.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&bloom.sampler),
+                    },
+                ],
+            })
+        };
+        let texture = |width: u32, height: u32| {
+            let config = wgpu::SurfaceConfiguration {
+                format: Bloom::FORMAT,
+                width: width.max(1),
+                height: height.max(1),
+                ..self.config.clone()
+            };
+            Texture::create_render_texture(&self.device, &config)
+        };
+
+        let (width, height) = (self.config.width, self.config.height);
+        let chain = (1..=mips)
+            .map(|i| {
+                let texture = texture(width >> i, height >> i);
+                let bind_group = bind_group(&texture);
+                (texture, bind_group)
+            })
+            .collect();
+        let result = texture(width, height);
+        let result_bind_group =
+            Self::texture_bind_group(&self.device, &self.scene_bind_group.0, &result);
+        BloomTargets {
+            scene: bind_group(&self.scene_texture),
+            chain,
+            result: (result, result_bind_group),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Context as _, bail};
+use image::RgbaImage;
+
+use super::{Frame, Output, Renderer};
+
+/// a texture being copied into a buffer the cpu can read. `read` waits for the copy
+pub struct Capture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    /// bytes per row in `buffer`; rows are padded to `COPY_BYTES_PER_ROW_ALIGNMENT`
+    padded_row: u32,
+    format: wgpu::TextureFormat,
+}
+
+impl Renderer {
+    /// starts copying `texture` into a mappable buffer. the texture needs `COPY_SRC` usage and an
+    /// 8 bit rgba or bgra format
+    pub fn capture_texture(&self, texture: &wgpu::Texture) -> anyhow::Result<Capture> {
+        let format = texture.format();
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            bail!("x_x :: can't capture a {format:?} texture");
+        }
+        let (width, height) = (texture.width(), texture.height());
+        let padded_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("capture buffer"),
+            size: (padded_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("capture encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+        Ok(Capture {
+            buffer,
+            width,
+            height,
+            padded_row,
+            format,
+        })
+    }
+
+    /// the texture `frame` is drawn into, if it can be copied from.
+    /// window surfaces only can when the platform allows it
+    pub fn frame_texture<'a>(&'a self, frame: &'a Frame) -> Option<&'a wgpu::Texture> {
+        match (frame, &self.output) {
+            (Frame::Surface(surface), _)
+                if self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) =>
+            {
+                Some(&surface.texture)
+            }
+            (Frame::Offscreen, Output::Offscreen(texture)) => Some(&texture.texture),
+            _ => None,
+        }
+    }
+
+    /// reads back the last frame, post processing and all. blocks until the gpu's done with it.
+    ///
+    /// in a window, frames are gone once they're presented, so this is the scene (tonemapped, if
+    /// hdr's on) from before post processing. `Context::screenshot` gets the real thing
+    pub fn capture_frame(&self) -> anyhow::Result<RgbaImage> {
+        let texture = match &self.output {
+            Output::Offscreen(texture) => &texture.texture,
+            Output::Window { .. } => self.ldr_scene_texture(),
+        };
+        self.capture_texture(texture)?.read(&self.device)
+    }
+}
+
+impl Capture {
+    /// waits for the copy, then unpads the rows and swizzles bgra into rgba
+    pub fn read(self, device: &wgpu::Device) -> anyhow::Result<RgbaImage> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.buffer
+            .map_async(wgpu::MapMode::Read, .., move |result| {
+                let _ = tx.send(result);
+            });
+        device.poll(wgpu::PollType::wait_indefinitely())?;
+        rx.recv()
+            .context("x_x :: capture buffer was never mapped")?
+            .context("x_x :: couldn't map capture buffer")?;
+
+        let row = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row * self.height as usize);
+        {
+            let mapped = self.buffer.get_mapped_range(..);
+            for padded in mapped.chunks(self.padded_row as usize) {
+                pixels.extend_from_slice(&padded[..row]);
+            }
+        }
+        self.buffer.unmap();
+        if matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        RgbaImage::from_raw(self.width, self.height, pixels)
+            .context("x_x :: capture is the wrong size")
+    }
+}
@@ -0,0 +1,632 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::tests::headless;
+
+    fn color(graph: &mut RenderGraph, label: &str) -> AttachmentId {
+        graph.add_attachment(AttachmentDesc::color(label))
+    }
+
+    #[test]
+    fn default_graph_is_scene_then_post() {
+        let graph = RenderGraph::new();
+        assert_eq!(graph.order(), [PassId::SCENE, PassId::POST]);
+    }
+
+    #[test]
+    fn passes_run_after_what_they_read() {
+        let mut graph = RenderGraph::new();
+        let (a, b) = (color(&mut graph, "a"), color(&mut graph, "b"));
+        // declared backwards on purpose
+        let reads_b = graph
+            .add_pass(PassDesc::new("reads b").with_color(a, None).with_read(b))
+            .unwrap();
+        let writes_b = graph
+            .add_pass(PassDesc::new("writes b").with_color(b, Some(wgpu::Color::BLACK)))
+            .unwrap();
+        graph
+            .replace_pass(
+                PassId::POST,
+                PassDesc::new("post")
+                    .with_color(AttachmentId::OUTPUT, None)
+                    .with_read(a),
+            )
+            .unwrap();
+        assert_eq!(
+            graph.order(),
+            [PassId::SCENE, writes_b, reads_b, PassId::POST]
+        );
+    }
+
+    #[test]
+    fn writers_keep_their_order() {
+        let mut graph = RenderGraph::new();
+        let overlay = graph
+            .add_pass(PassDesc::new("overlay").with_color(AttachmentId::SCENE, None))
+            .unwrap();
+        let first = graph
+            .add_pass(PassDesc::new("first").with_color(AttachmentId::OUTPUT, None))
+            .unwrap();
+        // `first` is declared after `POST`, but has to wait for it anyway
+        assert_eq!(graph.order(), [PassId::SCENE, overlay, PassId::POST, first]);
+
+        let c = color(&mut graph, "c");
+        let last = graph
+            .add_pass(PassDesc::new("last").with_color(c, None).with_after(first))
+            .unwrap();
+        assert_eq!(graph.order().last(), Some(&last));
+    }
+
+    #[test]
+    fn bad_passes_are_rejected() {
+        let mut graph = RenderGraph::new();
+        let (a, b) = (color(&mut graph, "a"), color(&mut graph, "b"));
+        let ab = graph
+            .add_pass(PassDesc::new("a to b").with_color(b, None).with_read(a))
+            .unwrap();
+        let order = graph.order().to_vec();
+
+        let cycle = || PassDesc::new("b to a").with_color(a, None).with_read(b);
+        assert!(graph.add_pass(cycle()).is_err());
+        let feedback = PassDesc::new("a to a").with_color(a, None).with_read(a);
+        assert!(graph.add_pass(feedback).is_err());
+        let depth = PassDesc::new("depth")
+            .with_color(a, None)
+            .with_read(AttachmentId::DEPTH);
+        assert!(graph.add_pass(depth).is_err());
+        let half = graph.add_attachment(AttachmentDesc::color("half").with_scale(0.5));
+        let mismatched = PassDesc::new("mismatched")
+            .with_color(a, None)
+            .with_color(half, None);
+        assert!(graph.add_pass(mismatched).is_err());
+        assert!(graph.add_pass(PassDesc::new("nothing")).is_err());
+
+        // failed replacements leave the old pass
+        assert!(graph.replace_pass(PassId::SCENE, cycle()).is_err());
+        assert!(graph.replace_pass(ab, PassDesc::new("nothing")).is_err());
+        assert_eq!(graph.pass(PassId::SCENE).unwrap().label, "scene");
+        assert_eq!(graph.pass(ab).unwrap().label, "a to b");
+        assert_eq!(graph.order(), order);
+
+        // the built in passes can only be replaced
+        assert!(graph.remove_pass(PassId::SCENE).is_err());
+        assert!(graph.remove_pass(PassId::POST).is_err());
+        assert_eq!(graph.order(), order);
+        assert_eq!(graph.remove_pass(ab).unwrap().label, "a to b");
+        assert!(graph.remove_pass(ab).is_err());
+    }
+
+    #[test]
+    fn attachments_are_made_when_used_and_remade_on_resize() {
+        let mut renderer = headless(8, 6);
+        let half = renderer
+            .graph
+            .add_attachment(AttachmentDesc::color("half").with_scale(0.5));
+        let unused = renderer
+            .graph
+            .add_attachment(AttachmentDesc::color("unused"));
+        renderer
+            .graph
+            .add_pass(PassDesc::new("half").with_color(half, None))
+            .unwrap();
+        renderer.prepare_graph();
+        assert!(renderer.graph.target(unused).is_none());
+        let texture = renderer.graph.target(half).unwrap();
+        assert_eq!((texture.width(), texture.height()), (4, 3));
+
+        renderer.resize(20, 10);
+        assert!(renderer.graph.target(half).is_none());
+        renderer.prepare_graph();
+        let texture = renderer.graph.target(half).unwrap();
+        assert_eq!((texture.width(), texture.height()), (10, 5));
+    }
+}
+
+use anyhow::{anyhow, bail};
+use wgpu::{BindGroup, CommandEncoder, RenderPass, TextureFormat, TextureView};
+
+use super::{Frame, Renderer};
+use crate::resources::Texture;
+
+/// a texture in a `RenderGraph` that passes draw into or read from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AttachmentId(usize);
+
+impl AttachmentId {
+    /// the window, or the offscreen texture when headless
+    pub const OUTPUT: Self = Self(0);
+    /// what `AppHandler::render` draws into and post processing reads. with
+    /// `Renderer::set_sample_count`, it's drawn multisampled and resolved as each pass ends.
+    /// it's in `Renderer::scene_format`, which isn't the output's with `Renderer::set_hdr`
+    pub const SCENE: Self = Self(1);
+    /// the scene's depth buffer, multisampled like `SCENE`. app attachments never are, so
+    /// don't draw into both in one pass when multisampling
+    pub const DEPTH: Self = Self(2);
+}
+
+/// a pass in a `RenderGraph`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PassId(usize);
+
+impl PassId {
+    /// `AppHandler::render`; draws into `SCENE` and `DEPTH`
+    pub const SCENE: Self = Self(0);
+    /// `AppHandler::post_process`; reads `SCENE` and draws into `OUTPUT`. it gets the scene after
+    /// `Renderer::bloom`, tonemapping and `Renderer::post_stack`, whichever are on
+    pub const POST: Self = Self(1);
+}
+
+/// what kind of texture an attachment is
+#[derive(Clone, Debug)]
+pub struct AttachmentDesc {
+    pub label: String,
+    /// `None` is the output's format
+    pub format: Option<TextureFormat>,
+    /// of the output's size
+    pub scale: f32,
+}
+
+impl AttachmentDesc {
+    /// the output's format and size
+    pub fn color(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            format: None,
+            scale: 1.,
+        }
+    }
+    /// a depth buffer the output's size
+    pub fn depth(label: impl Into<String>) -> Self {
+        Self {
+            format: Some(Texture::DEPTH_FORMAT),
+            ..Self::color(label)
+        }
+    }
+    pub fn with_format(self, format: TextureFormat) -> Self {
+        Self {
+            format: Some(format),
+            ..self
+        }
+    }
+    pub fn with_scale(self, scale: f32) -> Self {
+        Self { scale, ..self }
+    }
+
+    fn is_depth(&self) -> bool {
+        self.format.is_some_and(|f| f.has_depth_aspect())
+    }
+}
+
+/// a color attachment of a pass. `clear` is what it's cleared to first; `None` keeps what's
+/// there
+#[derive(Clone, Copy, Debug)]
+pub struct ColorTarget {
+    pub attachment: AttachmentId,
+    pub clear: Option<wgpu::Color>,
+}
+
+/// the depth attachment of a pass. `clear` works like `ColorTarget`'s
+#[derive(Clone, Copy, Debug)]
+pub struct DepthTarget {
+    pub attachment: AttachmentId,
+    pub clear: Option<f32>,
+}
+
+/// what a pass draws into and reads from.
+///
+/// passes that read attachments get `Renderer::post_uniform` at bind group 0 and what they read
+/// at 1 onwards, in order, so `post_pipeline` shaders work in them
+#[derive(Clone, Debug)]
+pub struct PassDesc {
+    pub label: String,
+    pub color: Vec<ColorTarget>,
+    pub depth: Option<DepthTarget>,
+    pub reads: Vec<AttachmentId>,
+    /// passes that have to go first, on top of the ones the graph works out itself
+    pub after: Vec<PassId>,
+}
+
+impl PassDesc {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            color: Vec::new(),
+            depth: None,
+            reads: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+    pub fn with_color(mut self, attachment: AttachmentId, clear: Option<wgpu::Color>) -> Self {
+        self.color.push(ColorTarget { attachment, clear });
+        self
+    }
+    pub fn with_depth(self, attachment: AttachmentId, clear: Option<f32>) -> Self {
+        Self {
+            depth: Some(DepthTarget { attachment, clear }),
+            ..self
+        }
+    }
+    pub fn with_read(mut self, attachment: AttachmentId) -> Self {
+        self.reads.push(attachment);
+        self
+    }
+    pub fn with_after(mut self, pass: PassId) -> Self {
+        self.after.push(pass);
+        self
+    }
+
+    fn writes(&self) -> impl Iterator<Item = AttachmentId> + '_ {
+        self.color
+            .iter()
+            .map(|c| c.attachment)
+            .chain(self.depth.map(|d| d.attachment))
+    }
+}
+
+struct Attachment {
+    desc: AttachmentDesc,
+    /// made by `Renderer::prepare_graph`, for attachments that aren't built in.
+    /// depth attachments can't be read, so they don't get a bind group
+    target: Option<(Texture, Option<BindGroup>)>,
+}
+
+/// the passes drawn every frame and the textures they use.
+///
+/// passes run after the ones writing what they read, passes writing the same attachment run in
+/// the order they were added, and `PassDesc::after` adds any other order. attachments get made
+/// when a pass first uses them, and remade when the window resizes
+pub struct RenderGraph {
+    attachments: Vec<Attachment>,
+    /// `None` for removed passes, so ids stay put
+    passes: Vec<Option<PassDesc>>,
+    order: Vec<PassId>,
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderGraph {
+    /// `SCENE` then `POST`, the way it's always been
+    pub fn new() -> Self {
+        let clear = Some(wgpu::Color::TRANSPARENT);
+        let attachment = |desc| Attachment { desc, target: None };
+        Self {
+            attachments: vec![
+                attachment(AttachmentDesc::color("output")),
+                attachment(AttachmentDesc::color("scene")),
+                attachment(AttachmentDesc::depth("depth")),
+            ],
+            passes: vec![
+                Some(
+                    PassDesc::new("scene")
+                        .with_color(AttachmentId::SCENE, clear)
+                        .with_depth(AttachmentId::DEPTH, Some(1.)),
+                ),
+                Some(
+                    PassDesc::new("post")
+                        .with_color(AttachmentId::OUTPUT, clear)
+                        .with_read(AttachmentId::SCENE),
+                ),
+            ],
+            order: vec![PassId::SCENE, PassId::POST],
+        }
+    }
+
+    pub fn add_attachment(&mut self, desc: AttachmentDesc) -> AttachmentId {
+        self.attachments.push(Attachment { desc, target: None });
+        AttachmentId(self.attachments.len() - 1)
+    }
+    /// errors if the pass doesn't make sense, or can't be ordered with the others
+    pub fn add_pass(&mut self, desc: PassDesc) -> anyhow::Result<PassId> {
+        let id = PassId(self.passes.len());
+        self.passes.push(None);
+        if let Err(e) = self.replace_pass(id, desc) {
+            self.passes.pop();
+            return Err(e);
+        }
+        Ok(id)
+    }
+    /// like `add_pass`. on an error the old pass stays
+    pub fn replace_pass(&mut self, id: PassId, desc: PassDesc) -> anyhow::Result<()> {
+        self.check(&desc)?;
+        let slot = self
+            .passes
+            .get_mut(id.0)
+            .ok_or(anyhow!("x_x :: no pass {id:?}"))?;
+        let old = slot.replace(desc);
+        match self.sort() {
+            Ok(order) => {
+                self.order = order;
+                Ok(())
+            }
+            Err(e) => {
+                self.passes[id.0] = old;
+                Err(e)
+            }
+        }
+    }
+    /// errors for `SCENE` and `POST`, which `AppHandler::render` and post processing need;
+    /// replace them instead
+    pub fn remove_pass(&mut self, id: PassId) -> anyhow::Result<PassDesc> {
+        if id == PassId::SCENE || id == PassId::POST {
+            bail!("x_x :: {id:?} is built in, so it can't be removed");
+        }
+        let desc = self
+            .passes
+            .get_mut(id.0)
+            .and_then(Option::take)
+            .ok_or(anyhow!("x_x :: no pass {id:?}"))?;
+        // fewer passes can't make a cycle
+        self.order = self.sort().unwrap();
+        Ok(desc)
+    }
+    pub fn pass(&self, id: PassId) -> Option<&PassDesc> {
+        self.passes.get(id.0)?.as_ref()
+    }
+    /// the order passes are drawn in
+    pub fn order(&self) -> &[PassId] {
+        &self.order
+    }
+    /// the texture behind an attachment that isn't built in, once it's been made
+    pub fn target(&self, id: AttachmentId) -> Option<&wgpu::Texture> {
+        let (texture, _) = self.attachments.get(id.0)?.target.as_ref()?;
+        Some(&texture.texture)
+    }
+
+    /// drops every attachment, so they're remade the new size
+    pub(crate) fn resize(&mut self) {
+        for attachment in &mut self.attachments {
+            attachment.target = None;
+        }
+    }
+
+    fn attachment(&self, id: AttachmentId) -> anyhow::Result<&AttachmentDesc> {
+        Ok(&self
+            .attachments
+            .get(id.0)
+            .ok_or(anyhow!("x_x :: no attachment {id:?}"))?
+            .desc)
+    }
+
+    fn check(&self, desc: &PassDesc) -> anyhow::Result<()> {
+        let label = &desc.label;
+        if desc.color.is_empty() && desc.depth.is_none() {
+            bail!("x_x :: pass `{label}` doesn't draw into anything");
+        }
+        let mut scale = None;
+        for id in desc.writes() {
+            let attachment = self.attachment(id)?;
+            if scale.is_some_and(|s| s != attachment.scale) {
+                bail!("x_x :: pass `{label}` draws into attachments of different sizes");
+            }
+            scale = Some(attachment.scale);
+        }
+        for color in &desc.color {
+            if self.attachment(color.attachment)?.is_depth() {
+                bail!("x_x :: pass `{label}` has a depth attachment as a color attachment");
+            }
+        }
+        if let Some(depth) = desc.depth
+            && !self.attachment(depth.attachment)?.is_depth()
+        {
+            bail!("x_x :: pass `{label}` has a color attachment as its depth attachment");
+        }
+        for &id in &desc.reads {
+            let attachment = self.attachment(id)?;
+            if id == AttachmentId::OUTPUT || attachment.is_depth() {
+                bail!("x_x :: pass `{label}` can't read `{}`", attachment.label);
+            }
+            if desc.writes().any(|w| w == id) {
+                bail!(
+                    "x_x :: pass `{label}` reads `{}` while drawing into it",
+                    attachment.label
+                );
+            }
+        }
+        for &after in &desc.after {
+            if self.pass(after).is_none() {
+                bail!("x_x :: pass `{label}` comes after {after:?}, which doesn't exist");
+            }
+        }
+        Ok(())
+    }
+
+    /// orders the passes, keeping the order they were added in where it can
+    fn sort(&self) -> anyhow::Result<Vec<PassId>> {
+        let live: Vec<_> = (0..self.passes.len())
+            .filter(|&i| self.passes[i].is_some())
+            .collect();
+        let mut before = vec![Vec::new(); self.passes.len()];
+        for &i in &live {
+            let pass = self.passes[i].as_ref().unwrap();
+            // removed passes don't hold anything up
+            before[i].extend(
+                pass.after
+                    .iter()
+                    .map(|p| p.0)
+                    .filter(|&j| live.contains(&j)),
+            );
+            for &j in &live {
+                let other = self.passes[j].as_ref().unwrap();
+                let writes_what_i_reads = other.writes().any(|w| pass.reads.contains(&w));
+                let writes_first = j < i && other.writes().any(|w| pass.writes().any(|v| v == w));
+                if j != i && (writes_what_i_reads || writes_first) {
+                    before[i].push(j);
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(live.len());
+        let mut done = vec![false; self.passes.len()];
+        while order.len() < live.len() {
+            let next = live
+                .iter()
+                .find(|&&i| !done[i] && before[i].iter().all(|&j| done[j]))
+                .ok_or_else(|| {
+                    let stuck = live.iter().find(|&&i| !done[i]).unwrap();
+                    let label = &self.passes[*stuck].as_ref().unwrap().label;
+                    anyhow!("x_x :: render graph has a cycle through pass `{label}`")
+                })?;
+            done[*next] = true;
+            order.push(PassId(*next));
+        }
+        Ok(order)
+    }
+}
+
+impl Renderer {
+    /// makes the attachments the graph's passes use, and drops the ones they don't
+    pub(crate) fn prepare_graph(&mut self) {
+        let graph = &mut self.graph;
+        let mut used = vec![false; graph.attachments.len()];
+        for pass in graph.passes.iter().flatten() {
+            for id in pass.writes().chain(pass.reads.iter().copied()) {
+                used[id.0] = true;
+            }
+        }
+        // the first three are the renderer's own
+        for (attachment, used) in graph.attachments.iter_mut().zip(used).skip(3) {
+            if !used {
+                attachment.target = None;
+                continue;
+            }
+            if attachment.target.is_some() {
+                continue;
+            }
+            let desc = &attachment.desc;
+            let size = |s: u32| ((s as f32 * desc.scale).round() as u32).max(1);
+            let (width, height) = (size(self.config.width), size(self.config.height));
+            attachment.target = Some(if desc.is_depth() {
+                let texture =
+                    Texture::create_depth_texture(&self.device, (width, height), 1, &desc.label);
+                (texture, None)
+            } else {
+                let texture = Texture::create_render_texture(
+                    &self.device,
+                    &wgpu::SurfaceConfiguration {
+                        format: desc.format.unwrap_or(self.config.format),
+                        width,
+                        height,
+                        ..self.config.clone()
+                    },
+                );
+                let bind_group =
+                    Self::texture_bind_group(&self.device, &self.scene_bind_group.0, &texture);
+                (texture, Some(bind_group))
+            });
+        }
+    }
+
+    /// starts pass `id` of the graph, binding what it reads. `frame` is fetched the first time
+    /// a pass draws into `OUTPUT`
+    pub(crate) fn begin_graph_pass<'b>(
+        &mut self,
+        id: PassId,
+        encoder: &'b mut CommandEncoder,
+        frame: &mut Option<(Frame, TextureView)>,
+    ) -> anyhow::Result<RenderPass<'b>> {
+        let desc = self
+            .graph
+            .pass(id)
+            .ok_or(anyhow!(
+                "x_x :: drawing pass {id:?}, which isn't in the graph"
+            ))?
+            .clone();
+        let posted = match id {
+            PassId::POST => {
+                let scene = self
+                    .run_bloom(encoder)
+                    .unwrap_or_else(|| self.scene_bind_group.1.clone());
+                let scene = self.run_tonemap(encoder, &scene).unwrap_or(scene);
+                Some(self.run_post_stack(encoder, &scene).unwrap_or(scene))
+            }
+            _ => None,
+        };
+        if frame.is_none() && desc.writes().any(|w| w == AttachmentId::OUTPUT) {
+            *frame = Some(self.acquire_frame()?);
+        }
+        let view = |id: AttachmentId| match id {
+            AttachmentId::OUTPUT => Ok(frame.as_ref().unwrap().1.clone()),
+            AttachmentId::SCENE => Ok(self.scene_view().clone()),
+            AttachmentId::DEPTH => Ok(self.depth_texture.view.clone()),
+            id => self
+                .graph
+                .attachments
+                .get(id.0)
+                .and_then(|a| a.target.as_ref())
+                .map(|(texture, _)| texture.view.clone())
+                .ok_or(anyhow!(
+                    "x_x :: attachment {id:?} used before `prepare_graph`"
+                )),
+        };
+        let colors = desc
+            .color
+            .iter()
+            .map(|c| {
+                let resolve = match c.attachment {
+                    AttachmentId::SCENE => self.scene_resolve_target().cloned(),
+                    _ => None,
+                };
+                Ok((view(c.attachment)?, resolve))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let depth = desc.depth.map(|d| view(d.attachment)).transpose()?;
+
+        let color_attachments: Vec<_> = desc
+            .color
+            .iter()
+            .zip(&colors)
+            .map(|(target, (view, resolve))| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: resolve.as_ref(),
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: target.clear.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })
+            })
+            .collect();
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&desc.label),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: desc.depth.zip(depth.as_ref()).map(|(target, view)| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: target.clear.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }
+            }),
+            occlusion_query_set: None,
+            multiview_mask: None,
+            timestamp_writes: None,
+        });
+
+        if !desc.reads.is_empty() {
+            pass.set_bind_group(0, Some(&self.post_uniform.2), &[]);
+        }
+        for (i, &id) in desc.reads.iter().enumerate() {
+            let bind_group = match id {
+                AttachmentId::SCENE => posted.as_ref().unwrap_or(&self.scene_bind_group.1),
+                id => self
+                    .graph
+                    .attachments
+                    .get(id.0)
+                    .and_then(|a| a.target.as_ref())
+                    .and_then(|(_, bind_group)| bind_group.as_ref())
+                    .ok_or(anyhow!(
+                        "x_x :: attachment {id:?} used before `prepare_graph`"
+                    ))?,
+            };
+            pass.set_bind_group(i as u32 + 1, Some(bind_group), &[]);
+        }
+        Ok(pass)
+    }
+}
@@ -0,0 +1,196 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Context {
+        let mut context = pollster::block_on(Context::new_headless(8, 8)).unwrap();
+        context.set_resource_directory(format!("{}/res", env!("CARGO_MANIFEST_DIR")));
+        context
+    }
+
+    #[test]
+    fn hdr_swaps_the_scene_format() {
+        let mut context = context();
+        assert!(!context.renderer.is_hdr());
+        assert_eq!(
+            context.renderer.scene_format(),
+            context.renderer.config.format
+        );
+
+        let tonemap = Tonemap::new(Tonemapper::Reinhard, &mut context);
+        let renderer = &mut context.renderer;
+        renderer.set_hdr(Some(tonemap));
+        assert_eq!(renderer.scene_format(), Tonemap::SCENE_FORMAT);
+        assert_eq!(
+            renderer.scene_texture.texture.format(),
+            Tonemap::SCENE_FORMAT
+        );
+
+        renderer.set_sample_count(4).unwrap();
+        let msaa = &renderer.msaa_texture.as_ref().unwrap().texture;
+        assert_eq!(msaa.format(), Tonemap::SCENE_FORMAT);
+
+        let tonemap = renderer.set_hdr(None).unwrap();
+        assert_eq!(tonemap.tonemapper, Tonemapper::Reinhard);
+        assert_eq!(renderer.scene_format(), renderer.config.format);
+        assert_eq!(
+            renderer.msaa_texture.as_ref().unwrap().texture.format(),
+            renderer.config.format
+        );
+    }
+
+    #[test]
+    fn tonemap_target_is_made_with_the_scene() {
+        let mut context = context();
+        let mut encoder = context.renderer.command_encoder();
+        let scene = context.renderer.scene_bind_group.1.clone();
+        assert!(context.renderer.run_tonemap(&mut encoder, &scene).is_none());
+
+        let tonemap = Tonemap::new(Tonemapper::Aces, &mut context).with_exposure(2.);
+        let renderer = &mut context.renderer;
+        renderer.set_hdr(Some(tonemap));
+        // before anything's tonemapped, so screenshots of the first frame work
+        let target = renderer.ldr_scene_texture();
+        assert_eq!(target.format(), renderer.config.format);
+        assert!(renderer.capture_texture(target).is_ok());
+        assert!(renderer.run_tonemap(&mut encoder, &scene).is_some());
+
+        renderer.resize(4, 4);
+        let target = renderer.ldr_scene_texture();
+        assert_eq!(
+            (target.width(), target.format()),
+            (4, renderer.config.format)
+        );
+        assert!(renderer.capture_texture(target).is_ok());
+        assert_eq!(renderer.tonemap().unwrap().exposure, 2.);
+    }
+}
+
+use wgpu::{BindGroup, CommandEncoder, RenderPipeline, TextureFormat};
+
+use super::{Renderer, post_pipeline};
+use crate::{Context, resources::Texture};
+
+/// how `Tonemap` squashes the hdr scene down to something the screen can show
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// `c / (1 + c)`. never quite reaches white
+    Reinhard,
+    /// the film-like curve most games go for. punchy, with soft highlights
+    #[default]
+    Aces,
+    /// hable's uncharted 2 curve. flatter and less saturated than aces
+    Filmic,
+}
+
+/// turns the hdr scene into the output format. it runs after `Bloom` and before the `PostStack`,
+/// so `PostEffect`s and `POST` get the tonemapped scene.
+///
+/// images from `Texture::from_image` are srgb, so sampling them gives linear colours. the scene
+/// stays linear, and this encodes it back to srgb when the output format doesn't do it itself.
+/// that's only with hdr on: without it the scene's drawn straight into the output format, linear
+/// colours and all, like it always was
+pub struct Tonemap {
+    pub tonemapper: Tonemapper,
+    /// what the scene's multiplied by before it's tonemapped
+    pub exposure: f32,
+    pipeline: RenderPipeline,
+    /// made with the scene textures, at the output's size and format
+    target: Option<(Texture, BindGroup)>,
+}
+
+impl Tonemap {
+    /// what the scene's drawn into with hdr on
+    pub const SCENE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+    pub fn new(tonemapper: Tonemapper, context: &mut Context) -> Self {
+        Self {
+            tonemapper,
+            exposure: 1.,
+            pipeline: post_pipeline("core_shaders/post_processing/tonemap.wgsl", 12, context),
+            target: None,
+        }
+    }
+    pub fn with_exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// (re)makes the target at the output's size
+    pub(crate) fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layout: &wgpu::BindGroupLayout,
+    ) {
+        let texture = Texture::create_render_texture(device, config);
+        let bind_group = Renderer::texture_bind_group(device, layout, &texture);
+        self.target = Some((texture, bind_group));
+    }
+}
+
+impl Renderer {
+    /// the format pipelines drawing into `SCENE` need
+    pub fn scene_format(&self) -> TextureFormat {
+        match self.hdr {
+            Some(_) => Tonemap::SCENE_FORMAT,
+            None => self.config.format,
+        }
+    }
+    pub fn is_hdr(&self) -> bool {
+        self.hdr.is_some()
+    }
+    pub fn tonemap(&self) -> Option<&Tonemap> {
+        self.hdr.as_ref()
+    }
+    /// to change the tonemapper or exposure
+    pub fn tonemap_mut(&mut self) -> Option<&mut Tonemap> {
+        self.hdr.as_mut()
+    }
+    /// draws the scene in `Tonemap::SCENE_FORMAT` and tonemaps it with `tonemap`, or goes back
+    /// to drawing straight in the output format with `None`. returns the old tonemap.
+    ///
+    /// like `set_sample_count`, pipelines drawing into the scene have to be made after this
+    pub fn set_hdr(&mut self, tonemap: Option<Tonemap>) -> Option<Tonemap> {
+        let old = std::mem::replace(&mut self.hdr, tonemap);
+        self.create_scene_textures();
+        old
+    }
+
+    /// tonemaps `source` (the scene, or it with bloom) and returns the bind group of the result,
+    /// if hdr's on
+    pub(crate) fn run_tonemap(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        source: &BindGroup,
+    ) -> Option<BindGroup> {
+        let tonemap = self.hdr.as_ref()?;
+        let (texture, bind_group) = tonemap
+            .target
+            .as_ref()
+            .expect("x_x :: the tonemap target is made with the scene textures");
+        let immediates = [
+            tonemap.exposure.to_bits(),
+            tonemap.tonemapper as u32,
+            !self.config.format.is_srgb() as u32,
+        ];
+        self.draw_fullscreen(
+            encoder,
+            "tonemap",
+            &texture.view,
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            &tonemap.pipeline,
+            &[source],
+            bytemuck::cast_slice(&immediates),
+        );
+        Some(bind_group.clone())
+    }
+    /// the tonemapped scene if hdr's on, or the scene. for screenshots when the frame itself
+    /// can't be copied, so it's never the hdr scene itself
+    pub(crate) fn ldr_scene_texture(&self) -> &wgpu::Texture {
+        match self.hdr.as_ref().and_then(|t| t.target.as_ref()) {
+            Some((texture, _)) => &texture.texture,
+            None => &self.scene_texture.texture,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_pack_into_consecutive_groups() {
+        let levels = Levels::new(&[Level::Object, Level::Global]);
+        assert_eq!(levels.index(Level::Global), Some(0));
+        assert_eq!(levels.index(Level::Environment), None);
+        assert_eq!(levels.index(Level::Object), Some(1));
+        assert_eq!(
+            levels.iter().collect::<Vec<_>>(),
+            [Level::Global, Level::Object]
+        );
+
+        for level in Level::ALL {
+            assert_eq!(Levels::ALL.index(level), Some(level as u32));
+        }
+        assert_eq!(Levels::default(), Levels::ALL);
+    }
+
+    #[test]
+    fn levels_parse() {
+        assert_eq!("ENVIRONMENT".parse::<Level>().unwrap(), Level::Environment);
+        assert_eq!("SHADER_TYPE".parse::<Level>().unwrap(), Level::ShaderType);
+        assert!("environment".parse::<Level>().is_err());
+    }
+}
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use wgpu::{BindGroup, BindGroupLayout, BindGroupLayoutEntry, RenderPass, RenderPipeline};
+
+use crate::camera::light::Environment;
+
+/// what a bind group is for, from the most to the least shared. see PLAN.md
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Level {
+    /// the same for everything on the pass: camera, time, resolution
+    Global,
+    /// lights and the like. a mirror might have its own
+    Environment,
+    /// shared by everything drawn with one kind of shader, like material info
+    ShaderType,
+    /// one object's own stuff, like its textures
+    Object,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [
+        Level::Global,
+        Level::Environment,
+        Level::ShaderType,
+        Level::Object,
+    ];
+}
+
+impl FromStr for Level {
+    type Err = anyhow::Error;
+    /// the way .omi files spell them, e.g. `SHADER_TYPE`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "GLOBAL" => Level::Global,
+            "ENVIRONMENT" => Level::Environment,
+            "SHADER_TYPE" => Level::ShaderType,
+            "OBJECT" => Level::Object,
+            other => return Err(anyhow!("x_x :: invalid bind group level: {other}")),
+        })
+    }
+}
+
+/// which levels a pipeline uses. they take up bind group indices from 0 in level order,
+/// skipping the ones it doesn't use; a pipeline with only `Global` and `Object` has them at 0 and 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Levels(u8);
+
+impl Levels {
+    /// every level, each at its own index
+    pub const ALL: Levels = Levels(0b1111);
+    pub const NONE: Levels = Levels(0);
+
+    pub fn new(levels: &[Level]) -> Self {
+        levels.iter().fold(Self::NONE, |acc, &l| acc.with(l))
+    }
+    pub fn with(self, level: Level) -> Self {
+        Self(self.0 | 1 << level as u8)
+    }
+    pub fn contains(self, level: Level) -> bool {
+        self.0 & 1 << level as u8 != 0
+    }
+    /// the bind group index `level` goes in, if it's used
+    pub fn index(self, level: Level) -> Option<u32> {
+        self.contains(level)
+            .then(|| (self.0 & ((1 << level as u8) - 1)).count_ones())
+    }
+    /// in level order
+    pub fn iter(self) -> impl Iterator<Item = Level> {
+        Level::ALL.into_iter().filter(move |&l| self.contains(l))
+    }
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// a kind of bind group, living at one level. it says what its layout looks like, so layouts
+/// don't have to be built by hand everywhere they're needed
+pub trait Binding {
+    const LEVEL: Level;
+    const LABEL: &'static str;
+    /// entry `i` should be binding `i`
+    fn entries() -> Vec<BindGroupLayoutEntry>;
+
+    fn layout(device: &wgpu::Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(Self::LABEL),
+            entries: &Self::entries(),
+        })
+    }
+    /// a bind group of this kind, with `resources[i]` in binding `i`
+    fn create_bind_group(device: &wgpu::Device, resources: &[wgpu::BindingResource]) -> BindGroup {
+        let entries: Vec<_> = resources
+            .iter()
+            .enumerate()
+            .map(|(i, resource)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: resource.clone(),
+            })
+            .collect();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(Self::LABEL),
+            layout: &Self::layout(device),
+            entries: &entries,
+        })
+    }
+}
+
+/// a render pipeline and the levels it binds
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LeveledPipeline {
+    pub pipeline: RenderPipeline,
+    pub levels: Levels,
+    /// bytes of immediates its layout takes
+    pub immediate_size: u32,
+}
+
+/// keeps track of what's bound on a pass, so each level is only bound when it changes
+#[derive(Default)]
+pub struct Bindings {
+    levels: Levels,
+    /// by level
+    wanted: [Option<BindGroup>; 4],
+    /// by bind group index
+    bound: [Option<BindGroup>; 4],
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// sets `pipeline` on the pass. its levels decide where each level's bind group goes
+    pub fn set_pipeline(&mut self, pass: &mut RenderPass, pipeline: &LeveledPipeline) {
+        pass.set_pipeline(&pipeline.pipeline);
+        self.set_levels(pipeline.levels);
+    }
+    /// for when the pipeline was set some other way
+    pub fn set_levels(&mut self, levels: Levels) {
+        self.levels = levels;
+    }
+    /// `bind_group` is bound at `level` on the next `flush`, unless it's already there
+    pub fn bind(&mut self, level: Level, bind_group: &BindGroup) {
+        self.wanted[level as usize] = Some(bind_group.clone());
+    }
+    /// `bind`s its lights at `Level::Environment`
+    pub fn set_environment(&mut self, environment: &Environment) {
+        self.bind(Level::Environment, environment.bind_group());
+    }
+    /// binds every level the pipeline uses that changed. returns how many it bound
+    pub fn flush(&mut self, pass: &mut RenderPass) -> u32 {
+        let mut bound = 0;
+        for level in self.levels.iter() {
+            let index = self.levels.index(level).unwrap() as usize;
+            let wanted = &self.wanted[level as usize];
+            if wanted.is_some() && *wanted != self.bound[index] {
+                pass.set_bind_group(index as u32, wanted.as_ref(), &[]);
+                self.bound[index] = wanted.clone();
+                bound += 1;
+            }
+        }
+        bound
+    }
+}
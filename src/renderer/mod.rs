@@ -1,3 +1,119 @@
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn headless(width: u32, height: u32) -> Renderer {
+        pollster::block_on(Renderer::new_headless(width, height)).unwrap()
+    }
+    /// clears through `render_pass`, then draws nothing through `post_pass`
+    fn draw_frame(renderer: &mut Renderer) {
+        let mut encoder = renderer.command_encoder();
+        drop(renderer.render_pass(&mut encoder).unwrap());
+        let (frame, pass) = renderer.post_pass(&mut encoder).unwrap();
+        drop(pass);
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+        frame.present();
+        renderer
+            .device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .unwrap();
+    }
+
+    #[test]
+    fn headless_renders_frames() {
+        let mut renderer = headless(64, 32);
+        assert!(renderer.is_headless());
+        assert_eq!(renderer.res(), [64., 32.]);
+        draw_frame(&mut renderer);
+        draw_frame(&mut renderer);
+    }
+
+    #[test]
+    fn headless_resizes() {
+        let mut renderer = headless(64, 32);
+        renderer.resize(16, 48);
+        assert_eq!(renderer.res(), [16., 48.]);
+        let Output::Offscreen(texture) = &renderer.output else {
+            unreachable!()
+        };
+        assert_eq!(texture.texture.size().width, 16);
+        assert_eq!(texture.texture.size().height, 48);
+        draw_frame(&mut renderer);
+    }
+
+    #[test]
+    fn msaa_resolves_into_the_scene() {
+        let mut renderer = headless(16, 16);
+        assert!(renderer.set_sample_count(3).is_err());
+        assert_eq!(renderer.sample_count(), 1);
+        assert!(renderer.msaa_texture.is_none());
+
+        renderer.set_sample_count(4).unwrap();
+        renderer.resize(8, 24);
+        let msaa = &renderer.msaa_texture.as_ref().unwrap().texture;
+        assert_eq!(msaa.sample_count(), 4);
+        assert_eq!(msaa.size().height, 24);
+        assert_eq!(renderer.depth_texture.texture.sample_count(), 4);
+        assert_eq!(renderer.scene_texture.texture.sample_count(), 1);
+        draw_frame(&mut renderer);
+
+        renderer.set_sample_count(1).unwrap();
+        assert!(renderer.msaa_texture.is_none());
+        assert_eq!(renderer.depth_texture.texture.sample_count(), 1);
+        draw_frame(&mut renderer);
+    }
+
+    #[test]
+    fn capture_reads_back_the_output() {
+        // 70 pixels is 280 bytes a row, which gets padded to 512
+        let renderer = headless(70, 3);
+        let Output::Offscreen(texture) = &renderer.output else {
+            unreachable!()
+        };
+        let mut encoder = renderer
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        drop(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &texture.view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        }));
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+
+        let image = renderer.capture_frame().unwrap();
+        assert_eq!(image.dimensions(), (70, 3));
+        assert!(image.pixels().all(|p| p.0 == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn headless_needs_a_size() {
+        assert!(pollster::block_on(Renderer::new_headless(0, 32)).is_err());
+    }
+}
+
+mod bloom;
+mod capture;
+mod graph;
+mod hdr;
+mod levels;
+mod post;
+mod queue;
+pub use bloom::*;
+pub use capture::*;
+pub use graph::*;
+pub use hdr::*;
+pub use levels::*;
+pub use post::*;
+pub use queue::*;
+
+use anyhow::{Context as _, bail};
 use glam::Mat4;
 use std::time::Instant;
 use std::{ops::Range, sync::Arc};
@@ -12,15 +128,21 @@ use crate::resources::{Texture, load_shader};
 pub type EntryLayoutGenerator = fn(u32) -> wgpu::BindGroupLayoutEntry;
 /// the rendering context and everything that handles it
 pub struct Renderer {
-    /// basically the window. wgpu stuff
-    pub(crate) surface: wgpu::Surface<'static>,
+    /// where finished frames go
+    pub(crate) output: Output,
     pub device: Arc<wgpu::Device>,
     pub queue: wgpu::Queue,
+    /// size and format of the output, headless or not
     pub config: wgpu::SurfaceConfiguration,
     pub(crate) is_surface_configured: bool,
     pub(crate) depth_texture: Texture,
-    pub(crate) window: Arc<Window>,
     pub(crate) scene_texture: Texture,
+    /// what the scene's drawn into when multisampling, resolved into `scene_texture`
+    pub(crate) msaa_texture: Option<Texture>,
+    /// samples per pixel of `SCENE` and `DEPTH`
+    sample_count: u32,
+    /// `Some` draws the scene in hdr, tonemapped with this
+    hdr: Option<Tonemap>,
 
     pub(crate) delta_instant: Instant,
     /// time between frames, in seconds
@@ -29,6 +151,39 @@ pub struct Renderer {
     pub start: Instant,
     pub post_uniform: (wgpu::Buffer, wgpu::BindGroupLayout, wgpu::BindGroup),
     pub(crate) scene_bind_group: (wgpu::BindGroupLayout, wgpu::BindGroup),
+    /// the passes drawn every frame. add to it in `AppHandler::new`
+    pub graph: RenderGraph,
+    /// effects drawn over the scene before the `POST` pass
+    pub post_stack: PostStack,
+    /// drawn over the scene before tonemapping and `post_stack`. `Some` turns it on
+    pub bloom: Option<Bloom>,
+}
+
+/// what `post_pass` draws into
+pub(crate) enum Output {
+    /// basically the window. wgpu stuff
+    Window {
+        surface: wgpu::Surface<'static>,
+        window: Arc<Window>,
+    },
+    /// no window; frames stay in this texture
+    Offscreen(Texture),
+}
+
+/// the image `post_pass` draws into. `present` it once everything drawn into it is submitted
+pub enum Frame {
+    Surface(SurfaceTexture),
+    /// headless; the image is in the renderer's offscreen texture
+    Offscreen,
+}
+
+impl Frame {
+    /// shows the frame in the window. does nothing headless
+    pub fn present(self) {
+        if let Self::Surface(texture) = self {
+            texture.present();
+        }
+    }
 }
 
 #[repr(C)]
@@ -40,8 +195,21 @@ pub struct PostUniform {
 }
 
 impl Renderer {
+    /// panics if the renderer is headless
     pub fn window(&self) -> &Window {
-        &self.window
+        match &self.output {
+            Output::Window { window, .. } => window,
+            Output::Offscreen(_) => panic!("x_x :: headless renderer has no window"),
+        }
+    }
+    pub fn is_headless(&self) -> bool {
+        matches!(self.output, Output::Offscreen(_))
+    }
+    /// asks for another frame. does nothing headless
+    pub(crate) fn request_redraw(&self) {
+        if let Output::Window { window, .. } = &self.output {
+            window.request_redraw();
+        }
     }
     /// dont worry :)
     pub(crate) async fn new(window: Arc<Window>) -> anyhow::Result<Self> {
@@ -63,7 +231,72 @@ impl Renderer {
             })
             .await
             .unwrap();
-        let (device, queue) = adapter
+        let (device, queue) = Self::request_device(&adapter).await?;
+
+        // let surface_caps = surface.get_capabilities(&adapter);
+        //
+        // let surface_format = surface_caps
+        //     .formats
+        //     .iter()
+        //     .copied()
+        //     .find(|f| f.is_srgb())
+        //     .unwrap_or(surface_caps.formats[0]);
+        let mut config = Self::config(size.width, size.height);
+        // so screenshots can be copied straight out of the frame
+        if surface
+            .get_capabilities(&adapter)
+            .usages
+            .contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            config.usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+
+        // window.set_visible(true);
+        // window.focus_window();
+        Ok(Self::with_output(
+            device,
+            queue,
+            config,
+            Output::Window { surface, window },
+        ))
+    }
+
+    /// a renderer with no window, drawing `width`×`height` frames into an offscreen texture.
+    /// uses the software fallback adapter when there's no gpu.
+    ///
+    /// `render_pass` and `post_pass` work as usual; `Frame::present` does nothing
+    pub async fn new_headless(width: u32, height: u32) -> anyhow::Result<Self> {
+        if width == 0 || height == 0 {
+            bail!("x_x :: headless renderer can't be {width}x{height}");
+        }
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let mut options = wgpu::RequestAdapterOptions::default();
+        let adapter = match instance.request_adapter(&options).await {
+            Ok(adapter) => adapter,
+            Err(_) => {
+                options.force_fallback_adapter = true;
+                instance
+                    .request_adapter(&options)
+                    .await
+                    .context("x_x :: no gpu or software adapter for headless rendering")?
+            }
+        };
+        let (device, queue) = Self::request_device(&adapter).await?;
+
+        let config = Self::config(width, height);
+        let output = Output::Offscreen(Texture::create_render_texture(&device, &config));
+        let mut renderer = Self::with_output(device, queue, config, output);
+        renderer.is_surface_configured = true;
+        Ok(renderer)
+    }
+
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+    ) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+        let device = adapter
             .request_device(&wgpu::DeviceDescriptor {
                 label: None,
                 required_features: wgpu::Features::POLYGON_MODE_LINE | Features::IMMEDIATES,
@@ -77,32 +310,36 @@ impl Renderer {
                 trace: wgpu::Trace::Off, // Trace path.
                 experimental_features: wgpu::ExperimentalFeatures::disabled(),
             })
-            .await
-            .unwrap();
+            .await?;
+        Ok(device)
+    }
 
-        // let surface_caps = surface.get_capabilities(&adapter);
-        //
-        // let surface_format = surface_caps
-        //     .formats
-        //     .iter()
-        //     .copied()
-        //     .find(|f| f.is_srgb())
-        //     .unwrap_or(surface_caps.formats[0]);
-        let config = wgpu::SurfaceConfiguration {
+    fn config(width: u32, height: u32) -> wgpu::SurfaceConfiguration {
+        wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: wgpu::TextureFormat::Bgra8Unorm,
-            width: size.width,
-            height: size.height,
+            width,
+            height,
             present_mode: wgpu::PresentMode::AutoVsync,
             alpha_mode: wgpu::CompositeAlphaMode::PostMultiplied,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
-        };
+        }
+    }
 
-        // window.set_visible(true);
-        // window.focus_window();
-        let depth_texture =
-            Texture::create_depth_texture(&device, (config.width, config.height), "depth_texture");
+    /// everything that doesn't care whether there's a window
+    fn with_output(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration,
+        output: Output,
+    ) -> Self {
+        let depth_texture = Texture::create_depth_texture(
+            &device,
+            (config.width, config.height),
+            1,
+            "depth_texture",
+        );
 
         let scene_texture = Texture::create_render_texture(&device, &config);
         let scene_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -126,27 +363,14 @@ impl Renderer {
                 },
             ],
         });
-        let scene_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("post texture bg"),
-            layout: &scene_bgl,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&scene_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&scene_texture.sampler),
-                },
-            ],
-        });
+        let scene_bind_group = Self::texture_bind_group(&device, &scene_bgl, &scene_texture);
 
         let post_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("post uniform buffer"),
             contents: bytemuck::bytes_of(&PostUniform {
                 time: 0.,
                 _pad: 0.,
-                res: [size.width as f32, size.height as f32],
+                res: [config.width as f32, config.height as f32],
             }),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
@@ -187,21 +411,48 @@ impl Renderer {
         //     ),
         // );
         // TODO: let user set initial cameraconfig (maybe)
-        Ok(Self {
-            surface,
+        Self {
+            output,
             device: Arc::new(device),
             queue,
             config,
             is_surface_configured: false,
             depth_texture,
-            window,
             scene_texture,
+            msaa_texture: None,
+            sample_count: 1,
+            hdr: None,
             scene_bind_group: (scene_bgl, scene_bind_group),
 
             delta: 0.,
             delta_instant: Instant::now(),
             start: Instant::now(),
             post_uniform: (post_buf, post_bgl, post_bg),
+            graph: RenderGraph::new(),
+            post_stack: PostStack::new(),
+            bloom: None,
+        }
+    }
+
+    /// `texture` for passes that read it, in the layout `post_pipeline`s want
+    fn texture_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        texture: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("post texture bg"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
         })
     }
 
@@ -210,7 +461,10 @@ impl Renderer {
     // }
 
     pub fn res(&self) -> [f32; 2] {
-        self.window.inner_size().cast::<f32>().into()
+        match &self.output {
+            Output::Window { window, .. } => window.inner_size().cast::<f32>().into(),
+            Output::Offscreen(_) => [self.config.width as f32, self.config.height as f32],
+        }
     }
 
     pub(crate) fn resize(&mut self, width: u32, height: u32) {
@@ -218,33 +472,86 @@ impl Renderer {
             self.is_surface_configured = true;
             self.config.width = width;
             self.config.height = height;
-            self.surface.configure(&self.device, &self.config);
-            let t = Texture::create_depth_texture(
-                &self.device,
-                (self.config.width, self.config.height),
-                "depth_texture",
+            match &mut self.output {
+                Output::Window { surface, .. } => surface.configure(&self.device, &self.config),
+                Output::Offscreen(texture) => {
+                    *texture = Texture::create_render_texture(&self.device, &self.config)
+                }
+            }
+            self.create_scene_textures();
+            self.graph.resize();
+            self.post_stack.resize();
+            // `PostUniform::res`
+            self.queue.write_buffer(
+                &self.post_uniform.0,
+                8,
+                bytemuck::cast_slice(&[width as f32, height as f32]),
             );
-            self.depth_texture = t;
-            let t = Texture::create_render_texture(&self.device, &self.config);
-            self.scene_texture = t;
-            let scene_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("post texture bg"),
-                layout: &self.scene_bind_group.0,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&self.scene_texture.view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&self.scene_texture.sampler),
-                    },
-                ],
-            });
-            self.scene_bind_group.1 = scene_bind_group;
         }
     }
 
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+    /// multisamples the scene `count` times per pixel; 1 turns it off. 4 works everywhere.
+    ///
+    /// pipelines drawing into the scene need the same count. `load_pipeline` and
+    /// `blinn_phong::pipeline` use whatever it is when they're made, so set it first
+    /// (e.g. at the top of `AppHandler::new`)
+    pub fn set_sample_count(&mut self, count: u32) -> anyhow::Result<()> {
+        let features = self.device.features();
+        let supported = |format: wgpu::TextureFormat| {
+            format
+                .guaranteed_format_features(features)
+                .flags
+                .sample_count_supported(count)
+        };
+        if !supported(self.scene_format()) || !supported(Texture::DEPTH_FORMAT) {
+            bail!("x_x :: {count}x msaa isn't supported");
+        }
+        self.sample_count = count;
+        self.create_scene_textures();
+        Ok(())
+    }
+
+    /// (re)makes the scene's depth and color textures at the current size and sample count
+    fn create_scene_textures(&mut self) {
+        let (width, height) = (self.config.width, self.config.height);
+        self.depth_texture = Texture::create_depth_texture(
+            &self.device,
+            (width, height),
+            self.sample_count,
+            "depth_texture",
+        );
+        let config = wgpu::SurfaceConfiguration {
+            format: self.scene_format(),
+            ..self.config.clone()
+        };
+        self.scene_texture = Texture::create_render_texture(&self.device, &config);
+        self.msaa_texture = (self.sample_count > 1)
+            .then(|| Texture::create_msaa_texture(&self.device, &config, self.sample_count));
+        self.scene_bind_group.1 =
+            Self::texture_bind_group(&self.device, &self.scene_bind_group.0, &self.scene_texture);
+        if let Some(bloom) = &mut self.bloom {
+            bloom.resize();
+        }
+        // the tonemapped scene is what gets captured, so it's there from the first frame
+        if let Some(tonemap) = &mut self.hdr {
+            tonemap.resize(&self.device, &self.config, &self.scene_bind_group.0);
+        }
+    }
+    /// where passes drawing into the scene draw
+    pub(crate) fn scene_view(&self) -> &wgpu::TextureView {
+        match &self.msaa_texture {
+            Some(msaa) => &msaa.view,
+            None => &self.scene_texture.view,
+        }
+    }
+    /// where they resolve to, if multisampling
+    pub(crate) fn scene_resolve_target(&self) -> Option<&wgpu::TextureView> {
+        self.msaa_texture.as_ref().map(|_| &self.scene_texture.view)
+    }
+
     pub fn command_encoder(&mut self) -> CommandEncoder {
         self.device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -262,8 +569,8 @@ impl Renderer {
         let pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.scene_texture.view,
-                resolve_target: None,
+                view: self.scene_view(),
+                resolve_target: self.scene_resolve_target(),
                 depth_slice: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -293,11 +600,8 @@ impl Renderer {
     pub fn post_pass<'b>(
         &mut self,
         encoder: &'b mut CommandEncoder,
-    ) -> anyhow::Result<(SurfaceTexture, wgpu::RenderPass<'b>), SurfaceError> {
-        let surface_texture = self.surface.get_current_texture()?;
-        let view = surface_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    ) -> anyhow::Result<(Frame, wgpu::RenderPass<'b>), SurfaceError> {
+        let (frame, view) = self.acquire_frame()?;
         let pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -320,7 +624,20 @@ impl Renderer {
             timestamp_writes: None,
         });
 
-        Ok((surface_texture, pass))
+        Ok((frame, pass))
+    }
+    /// the next frame to draw into, and a view of it
+    pub(crate) fn acquire_frame(&self) -> anyhow::Result<(Frame, wgpu::TextureView), SurfaceError> {
+        Ok(match &self.output {
+            Output::Window { surface, .. } => {
+                let surface_texture = surface.get_current_texture()?;
+                let view = surface_texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                (Frame::Surface(surface_texture), view)
+            }
+            Output::Offscreen(texture) => (Frame::Offscreen, texture.view.clone()),
+        })
     }
     // pub fn set_camera(&mut self, camera: &Camera) {
     //     let buffer = self.get_shared_resource(0).as_inner_buffer();
@@ -509,6 +826,7 @@ pub fn post_pipeline(
         primitive: Default::default(),
 
         depth_stencil: None,
+        // post passes draw after the scene's resolved
         multisample: wgpu::MultisampleState::default(),
         fragment: Some(wgpu::FragmentState {
             module: &module,
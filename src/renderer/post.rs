@@ -0,0 +1,243 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(context: &mut Context, labels: &[&str]) -> PostStack {
+        let mut stack = PostStack::new();
+        for label in labels {
+            let mut effect =
+                PostEffect::new("core_shaders/post_processing/nothing.wgsl", 8, context);
+            effect.label = label.to_string();
+            stack.push(effect);
+        }
+        stack
+    }
+    fn context() -> Context {
+        let mut context = pollster::block_on(Context::new_headless(8, 8)).unwrap();
+        context.set_resource_directory(format!("{}/res", env!("CARGO_MANIFEST_DIR")));
+        context
+    }
+
+    #[test]
+    fn effects_keep_their_order() {
+        let mut stack = stack(&mut context(), &["a", "b", "c"]);
+        stack.get_mut(1).unwrap().enabled = false;
+        let enabled: Vec<_> = stack.enabled().map(|e| e.label.as_str()).collect();
+        assert_eq!(enabled, ["a", "c"]);
+
+        let c = stack.remove(2);
+        stack.insert(0, c);
+        let labels: Vec<_> = stack.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(labels, ["c", "a", "b"]);
+        assert_eq!(stack.position("b"), Some(2));
+    }
+
+    #[test]
+    fn immediates_are_sized_up_front() {
+        let mut stack = stack(&mut context(), &["a"]);
+        let effect = stack.find_mut("a").unwrap();
+        effect.set_immediates(&[1, 2, 3, 4]);
+        assert_eq!(effect.immediates(), [1, 2, 3, 4, 0, 0, 0, 0]);
+        let too_big = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            effect.set_immediates(&[0; 12]);
+        }));
+        assert!(too_big.is_err());
+    }
+
+    #[test]
+    fn targets_are_made_as_needed() {
+        let mut context = context();
+        let renderer = &mut context.renderer;
+        let mut encoder = renderer.command_encoder();
+        let scene = renderer.scene_bind_group.1.clone();
+        assert!(renderer.run_post_stack(&mut encoder, &scene).is_none());
+        assert!(renderer.post_stack.targets.iter().all(Option::is_none));
+
+        let stack = stack(&mut context, &["a", "b"]);
+        let renderer = &mut context.renderer;
+        renderer.post_stack = stack;
+        renderer.post_stack.get_mut(1).unwrap().enabled = false;
+        assert!(renderer.run_post_stack(&mut encoder, &scene).is_some());
+        assert!(renderer.post_stack.targets[0].is_some());
+        assert!(renderer.post_stack.targets[1].is_none());
+
+        renderer.resize(4, 4);
+        assert!(renderer.post_stack.targets[0].is_none());
+    }
+}
+
+use wgpu::{BindGroup, CommandEncoder, RenderPipeline, TextureView};
+
+use super::{Renderer, post_pipeline};
+use crate::{Context, resources::Texture};
+
+/// a fullscreen shader in a `PostStack`. it gets the same bind groups as `post_pipeline`s:
+/// `PostUniform` at 0, and the image so far at 1
+pub struct PostEffect {
+    pub label: String,
+    pipeline: RenderPipeline,
+    immediates: Vec<u8>,
+    /// disabled effects are skipped
+    pub enabled: bool,
+}
+
+impl PostEffect {
+    /// `shader_path` is loaded like `post_pipeline`'s, and labels the effect.
+    /// the `immediate_size` bytes of immediates start zeroed
+    pub fn new(shader_path: &str, immediate_size: u32, context: &mut Context) -> Self {
+        Self {
+            label: shader_path.to_string(),
+            pipeline: post_pipeline(shader_path, immediate_size, context),
+            immediates: vec![0; immediate_size as usize],
+            enabled: true,
+        }
+    }
+    pub fn with_immediates(mut self, data: &[u8]) -> Self {
+        self.set_immediates(data);
+        self
+    }
+    /// from offset 0. panics if `data` is bigger than the effect's immediates
+    pub fn set_immediates(&mut self, data: &[u8]) {
+        assert!(
+            data.len() <= self.immediates.len(),
+            "x_x :: {} bytes of immediates don't fit in `{}`'s {}",
+            data.len(),
+            self.label,
+            self.immediates.len()
+        );
+        self.immediates[..data.len()].copy_from_slice(data);
+    }
+    pub fn immediates(&self) -> &[u8] {
+        &self.immediates
+    }
+}
+
+/// post effects run one after the other on the scene, before the `POST` pass. each reads what
+/// the last one drew, ping-ponging between two textures, and `POST` reads the result in place
+/// of `SCENE`. with hdr on, they get the tonemapped scene
+#[derive(Default)]
+pub struct PostStack {
+    effects: Vec<PostEffect>,
+    /// made the first time they're needed
+    targets: [Option<(Texture, BindGroup)>; 2],
+}
+
+impl PostStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// at the end. returns its index
+    pub fn push(&mut self, effect: PostEffect) -> usize {
+        self.effects.push(effect);
+        self.effects.len() - 1
+    }
+    pub fn insert(&mut self, index: usize, effect: PostEffect) {
+        self.effects.insert(index, effect);
+    }
+    pub fn remove(&mut self, index: usize) -> PostEffect {
+        self.effects.remove(index)
+    }
+    pub fn get(&self, index: usize) -> Option<&PostEffect> {
+        self.effects.get(index)
+    }
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut PostEffect> {
+        self.effects.get_mut(index)
+    }
+    /// the first effect labelled `label`
+    pub fn position(&self, label: &str) -> Option<usize> {
+        self.effects.iter().position(|e| e.label == label)
+    }
+    pub fn find_mut(&mut self, label: &str) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|e| e.label == label)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &PostEffect> {
+        self.effects.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &PostEffect> {
+        self.effects.iter().filter(|e| e.enabled)
+    }
+    /// drops the textures, so they're remade the new size
+    pub(crate) fn resize(&mut self) {
+        self.targets = Default::default();
+    }
+}
+
+impl Renderer {
+    /// draws the enabled effects of `post_stack`, starting from `source`. returns the bind group
+    /// of the result, or `None` if there was nothing to draw
+    pub(crate) fn run_post_stack(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        source: &BindGroup,
+    ) -> Option<BindGroup> {
+        let count = self.post_stack.enabled().count();
+        for target in &mut self.post_stack.targets[..count.min(2)] {
+            if target.is_none() {
+                let texture = Texture::create_render_texture(&self.device, &self.config);
+                let bind_group =
+                    Self::texture_bind_group(&self.device, &self.scene_bind_group.0, &texture);
+                *target = Some((texture, bind_group));
+            }
+        }
+
+        let mut source = source;
+        for (i, effect) in self.post_stack.enabled().enumerate() {
+            let (texture, bind_group) = self.post_stack.targets[i % 2].as_ref().unwrap();
+            self.draw_fullscreen(
+                encoder,
+                &effect.label,
+                &texture.view,
+                wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                &effect.pipeline,
+                &[source],
+                &effect.immediates,
+            );
+            source = bind_group;
+        }
+        (count > 0).then(|| source.clone())
+    }
+
+    /// one `post_pipeline` triangle over all of `view`, with `PostUniform` at 0 and `sources`
+    /// from 1 up
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw_fullscreen(
+        &self,
+        encoder: &mut CommandEncoder,
+        label: &str,
+        view: &TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        pipeline: &RenderPipeline,
+        sources: &[&BindGroup],
+        immediates: &[u8],
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, Some(&self.post_uniform.2), &[]);
+        for (i, source) in sources.iter().enumerate() {
+            pass.set_bind_group(i as u32 + 1, Some(*source), &[]);
+        }
+        if !immediates.is_empty() {
+            pass.set_immediates(0, immediates);
+        }
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,491 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{Renderer, tests::headless};
+
+    /// two pipelines that draw nothing, and four bind groups with nothing in them
+    fn state(renderer: &Renderer) -> ([RenderPipeline; 2], [BindGroup; 4]) {
+        state_with_immediates(renderer, [0, 0])
+    }
+    /// `state`, with each pipeline taking that many bytes of immediates
+    fn state_with_immediates(
+        renderer: &Renderer,
+        immediate_sizes: [u32; 2],
+    ) -> ([RenderPipeline; 2], [BindGroup; 4]) {
+        let device = &renderer.device;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(
+                "@vertex fn vs() -> @builtin(position) vec4<f32> { return vec4(0.); }
+                 @fragment fn fs() -> @location(0) vec4<f32> { return vec4(1.); }"
+                    .into(),
+            ),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[],
+        });
+        let pipeline = |immediate_size| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&layout, &layout],
+                immediate_size,
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point: None,
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point: None,
+                    compilation_options: Default::default(),
+                    targets: &[Some(renderer.config.format.into())],
+                }),
+                multiview_mask: None,
+                cache: None,
+            })
+        };
+        let bind_group = || {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &layout,
+                entries: &[],
+            })
+        };
+        (
+            immediate_sizes.map(pipeline),
+            [bind_group(), bind_group(), bind_group(), bind_group()],
+        )
+    }
+
+    /// replays `queue` into a pass over the renderer's scene texture
+    fn replay(renderer: &mut Renderer, queue: &mut RenderQueue) -> QueueStats {
+        let mut encoder = renderer.command_encoder();
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &renderer.scene_texture.view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: Default::default(),
+            })],
+            ..Default::default()
+        });
+        let stats = queue.replay(&mut pass);
+        drop(pass);
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+        stats
+    }
+
+    #[test]
+    fn sorting_cuts_state_changes() {
+        let mut renderer = headless(8, 8);
+        let ([a, b], groups) = state(&renderer);
+        let mut queue = RenderQueue::new();
+        // a b a b, each with a different group 1
+        for i in 0..4 {
+            queue.set_pipeline(if i % 2 == 0 { &a } else { &b }, 0);
+            queue.set_bind_group(Level::Global, &groups[0]);
+            queue.set_bind_group(Level::Environment, &groups[i % 2 + 1]);
+            queue.draw(0..3, 0..1);
+        }
+        assert_eq!(queue.len(), 4);
+        let stats = replay(&mut renderer, &mut queue);
+        assert_eq!(
+            stats,
+            QueueStats {
+                draws: 4,
+                pipelines: 2,
+                bind_groups: 3,
+            }
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn levels_go_where_the_pipeline_wants_them() {
+        let mut renderer = headless(8, 8);
+        let ([a, _], groups) = state(&renderer);
+        // global at 0 and object at 1, like the wireframe shader
+        let pipeline = LeveledPipeline {
+            pipeline: a,
+            levels: Levels::new(&[Level::Global, Level::Object]),
+            immediate_size: 0,
+        };
+        let mut queue = RenderQueue::new();
+        queue.set_leveled_pipeline(&pipeline);
+        queue.set_bind_group(Level::Global, &groups[0]);
+        queue.set_bind_group(Level::Environment, &groups[1]);
+        for object in &groups[2..] {
+            queue.set_bind_group(Level::Object, object);
+            queue.draw(0..3, 0..1);
+        }
+        // the environment group is never bound
+        let stats = replay(&mut renderer, &mut queue);
+        assert_eq!(stats.bind_groups, 3);
+    }
+
+    #[test]
+    fn opaque_front_to_back_transparent_back_to_front() {
+        let renderer = headless(8, 8);
+        let ([a, _], _) = state(&renderer);
+        let mut queue = RenderQueue::new();
+        queue.set_eye(Vec3::ZERO);
+        queue.set_pipeline(&a, 0);
+        for (z, transparent) in [
+            (5., false),
+            (1., true),
+            (3., false),
+            (4., true),
+            (2., false),
+        ] {
+            queue.set_transparent(transparent);
+            queue.set_origin(Vec3::new(0., 0., -z));
+            queue.draw(0..3, 0..1);
+        }
+        let depths: Vec<_> = queue.sorted().iter().map(|c| c.depth).collect();
+        assert_eq!(depths, [2., 3., 5., 4., 1.]);
+    }
+
+    #[test]
+    fn state_carries_over_between_draws() {
+        let renderer = headless(8, 8);
+        let ([a, b], groups) = state_with_immediates(&renderer, [8, 8]);
+        let mut queue = RenderQueue::new();
+        queue.set_pipeline(&a, 8);
+        queue.set_bind_group(Level::ShaderType, &groups[2]);
+        queue.set_immediates(4, &[1, 2]);
+        queue.draw(0..3, 0..1);
+        queue.set_pipeline(&b, 8);
+        queue.set_immediates(0, &[9]);
+        queue.draw(0..6, 0..2);
+
+        let calls = queue.sorted();
+        let second = calls.iter().find(|c| c.pipeline == b).unwrap();
+        assert_eq!(second.bind_groups[2].as_ref(), Some(&groups[2]));
+        // immediates go with the pipeline, so they start over
+        assert_eq!(second.immediates, [9, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(second.range, DrawRange::Vertices(0..6));
+        assert_eq!(second.instances, 0..2);
+    }
+
+    #[test]
+    fn immediates_fit_each_pipeline() {
+        let mut renderer = headless(8, 8);
+        // like a blinn phong mesh then a wireframe one
+        let ([big, small], groups) = state_with_immediates(&renderer, [16, 8]);
+        let mut queue = RenderQueue::new();
+        queue.set_bind_group(Level::Global, &groups[0]);
+        queue.set_bind_group(Level::Environment, &groups[1]);
+        queue.set_pipeline(&big, 16);
+        queue.set_immediates(0, &[1; 16]);
+        queue.draw(0..3, 0..1);
+        queue.set_pipeline(&small, 8);
+        queue.set_immediates(4, &[2; 4]);
+        queue.draw(0..3, 0..1);
+        queue.set_pipeline(&big, 16);
+        queue.set_immediates(0, &[1; 16]);
+        queue.draw(0..3, 0..1);
+
+        // would overrun `small` if its draw got `big`'s immediates
+        let stats = replay(&mut renderer, &mut queue);
+        assert_eq!(stats.draws, 3);
+    }
+
+    #[test]
+    fn setting_the_same_pipeline_again_zeroes_immediates() {
+        let mut renderer = headless(8, 8);
+        let ([a, _], groups) = state_with_immediates(&renderer, [8, 0]);
+        let record = |queue: &mut RenderQueue| {
+            queue.set_bind_group(Level::Global, &groups[0]);
+            queue.set_bind_group(Level::Environment, &groups[1]);
+            queue.set_pipeline(&a, 8);
+            queue.set_immediates(0, &[7; 8]);
+            queue.draw(0..3, 0..1);
+            queue.set_pipeline(&a, 8);
+            queue.draw(0..6, 0..1);
+        };
+
+        // sorting puts both under one `set_pipeline`, so the second has to send its zeros
+        let mut queue = RenderQueue::new();
+        record(&mut queue);
+        let immediates: Vec<_> = queue.sorted().into_iter().map(|c| c.immediates).collect();
+        assert_eq!(immediates, [[7; 8], [0; 8]]);
+        record(&mut queue);
+        let stats = replay(&mut renderer, &mut queue);
+        assert_eq!(stats.draws, 2);
+        assert_eq!(stats.pipelines, 1);
+    }
+}
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use glam::Vec3;
+use wgpu::{BindGroup, Buffer, IndexFormat, RenderPass, RenderPipeline};
+
+use super::{Bindings, Level, LeveledPipeline, Levels};
+use crate::camera::light::Environment;
+
+/// what a draw call draws
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawRange {
+    Vertices(Range<u32>),
+    Indexed {
+        indices: Range<u32>,
+        base_vertex: i32,
+    },
+}
+
+/// one draw and all the state it needs
+#[derive(Clone, Debug)]
+pub struct DrawCall {
+    pub pipeline: RenderPipeline,
+    /// where the pipeline wants each level
+    pub levels: Levels,
+    /// by level; `None` leaves whatever was bound before
+    pub bind_groups: [Option<BindGroup>; 4],
+    /// by slot. always the whole buffer
+    pub vertex_buffers: Vec<Option<Buffer>>,
+    pub index_buffer: Option<(Buffer, IndexFormat)>,
+    /// from offset 0, padded with zeros to the pipeline's immediate size. only what was set
+    /// since the pipeline was
+    pub immediates: Vec<u8>,
+    pub range: DrawRange,
+    pub instances: Range<u32>,
+    /// distance from the queue's eye
+    pub depth: f32,
+    pub transparent: bool,
+}
+
+impl DrawCall {
+    /// opaque draws grouped by state, then front to back so the depth test can skip work.
+    /// transparent draws after them, back to front so they blend right
+    fn order(&self, other: &Self) -> Ordering {
+        match (self.transparent, other.transparent) {
+            (false, false) => self
+                .state_order(other)
+                .then(self.depth.total_cmp(&other.depth)),
+            (true, true) => other
+                .depth
+                .total_cmp(&self.depth)
+                .then_with(|| self.state_order(other)),
+            (a, b) => a.cmp(&b),
+        }
+    }
+    fn state_order(&self, other: &Self) -> Ordering {
+        self.pipeline
+            .cmp(&other.pipeline)
+            .then(self.levels.cmp(&other.levels))
+            .then_with(|| self.bind_groups.cmp(&other.bind_groups))
+            .then_with(|| self.vertex_buffers.cmp(&other.vertex_buffers))
+    }
+}
+
+/// what `RenderQueue::replay` actually set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub draws: u32,
+    pub pipelines: u32,
+    pub bind_groups: u32,
+}
+
+/// records draws like a `RenderPass` would, then replays them sorted so the pass changes pipeline
+/// and bind groups as little as possible.
+///
+/// state carries over between draws like it does on a pass, except immediates, which start
+/// over as zeros with each pipeline; draws before the first `set_pipeline` panic. `Queueable`s can record themselves
+#[derive(Default)]
+pub struct RenderQueue {
+    calls: Vec<DrawCall>,
+    pipeline: Option<LeveledPipeline>,
+    bind_groups: [Option<BindGroup>; 4],
+    vertex_buffers: Vec<Option<Buffer>>,
+    index_buffer: Option<(Buffer, IndexFormat)>,
+    immediates: Vec<u8>,
+    eye: Vec3,
+    origin: Vec3,
+    transparent: bool,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// a pipeline that doesn't say which levels it uses has all of them, each at its own index.
+    /// `immediate_size` is how many bytes of immediates its layout takes
+    pub fn set_pipeline(&mut self, pipeline: &RenderPipeline, immediate_size: u32) {
+        self.set_leveled_pipeline(&LeveledPipeline {
+            pipeline: pipeline.clone(),
+            levels: Levels::ALL,
+            immediate_size,
+        });
+    }
+    /// clears the immediates, since the new pipeline might take fewer of them
+    pub fn set_leveled_pipeline(&mut self, pipeline: &LeveledPipeline) {
+        self.pipeline = Some(pipeline.clone());
+        self.immediates.clear();
+    }
+    /// draws whose pipeline doesn't use `level` ignore it
+    pub fn set_bind_group(&mut self, level: Level, bind_group: &BindGroup) {
+        self.bind_groups[level as usize] = Some(bind_group.clone());
+    }
+    /// which lights the following draws see
+    pub fn set_environment(&mut self, environment: &Environment) {
+        self.set_bind_group(Level::Environment, environment.bind_group());
+    }
+    pub fn set_vertex_buffer(&mut self, slot: u32, buffer: &Buffer) {
+        let slot = slot as usize;
+        if self.vertex_buffers.len() <= slot {
+            self.vertex_buffers.resize(slot + 1, None);
+        }
+        self.vertex_buffers[slot] = Some(buffer.clone());
+    }
+    pub fn set_index_buffer(&mut self, buffer: &Buffer, format: IndexFormat) {
+        self.index_buffer = Some((buffer.clone(), format));
+    }
+    pub fn set_immediates(&mut self, offset: u32, data: &[u8]) {
+        let (start, end) = (offset as usize, offset as usize + data.len());
+        if self.immediates.len() < end {
+            self.immediates.resize(end, 0);
+        }
+        self.immediates[start..end].copy_from_slice(data);
+    }
+    /// where the camera is, for depth sorting
+    pub fn set_eye(&mut self, eye: Vec3) {
+        self.eye = eye;
+    }
+    /// where the next draws are in the world. their depth is its distance from the eye
+    pub fn set_origin(&mut self, origin: Vec3) {
+        self.origin = origin;
+    }
+    /// whether the next draws blend with what's behind them
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent;
+    }
+
+    pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.push(DrawRange::Vertices(vertices), instances);
+    }
+    pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        self.push(
+            DrawRange::Indexed {
+                indices,
+                base_vertex,
+            },
+            instances,
+        );
+    }
+    fn push(&mut self, range: DrawRange, instances: Range<u32>) {
+        let LeveledPipeline {
+            pipeline,
+            levels,
+            immediate_size,
+        } = self
+            .pipeline
+            .clone()
+            .expect("x_x :: queued a draw before setting a pipeline");
+        let mut immediates = self.immediates.clone();
+        if immediates.len() < immediate_size as usize {
+            immediates.resize(immediate_size as usize, 0);
+        }
+        self.calls.push(DrawCall {
+            pipeline,
+            levels,
+            bind_groups: self.bind_groups.clone(),
+            vertex_buffers: self.vertex_buffers.clone(),
+            index_buffer: self.index_buffer.clone(),
+            immediates,
+            range,
+            instances,
+            depth: self.eye.distance(self.origin),
+            transparent: self.transparent,
+        });
+    }
+
+    /// sorts the queued draws and hands them over, leaving the queue empty
+    pub fn sorted(&mut self) -> Vec<DrawCall> {
+        let mut calls = std::mem::take(&mut self.calls);
+        calls.sort_by(DrawCall::order);
+        calls
+    }
+
+    /// draws everything queued into `pass`, sorted, only setting state that changed
+    pub fn replay(&mut self, pass: &mut RenderPass) -> QueueStats {
+        let mut stats = QueueStats::default();
+        let mut pipeline = None;
+        let mut bindings = Bindings::new();
+        let mut vertex_buffers = Vec::new();
+        let mut index_buffer = None;
+        let mut immediates = Vec::new();
+        for call in self.sorted() {
+            if pipeline.as_ref() != Some(&call.pipeline) {
+                pass.set_pipeline(&call.pipeline);
+                stats.pipelines += 1;
+                pipeline = Some(call.pipeline);
+                immediates.clear();
+            }
+            bindings.set_levels(call.levels);
+            for (level, group) in Level::ALL.into_iter().zip(&call.bind_groups) {
+                if let Some(group) = group {
+                    bindings.bind(level, group);
+                }
+            }
+            stats.bind_groups += bindings.flush(pass);
+            if call.vertex_buffers != vertex_buffers {
+                for (slot, buffer) in call.vertex_buffers.iter().enumerate() {
+                    if let Some(buffer) = buffer {
+                        pass.set_vertex_buffer(slot as u32, buffer.slice(..));
+                    }
+                }
+                vertex_buffers = call.vertex_buffers;
+            }
+            if call.index_buffer != index_buffer {
+                if let Some((buffer, format)) = &call.index_buffer {
+                    pass.set_index_buffer(buffer.slice(..), *format);
+                }
+                index_buffer = call.index_buffer;
+            }
+            if call.immediates != immediates {
+                if !call.immediates.is_empty() {
+                    pass.set_immediates(0, &call.immediates);
+                }
+                immediates = call.immediates;
+            }
+            match call.range {
+                DrawRange::Vertices(vertices) => pass.draw(vertices, call.instances),
+                DrawRange::Indexed {
+                    indices,
+                    base_vertex,
+                } => pass.draw_indexed(indices, base_vertex, call.instances),
+            }
+            stats.draws += 1;
+        }
+        stats
+    }
+}
+
+/// something that can record its draws into a `RenderQueue`, like `Renderable` draws into a pass.
+/// the pipeline and any bind groups it doesn't set itself are whatever the queue has
+pub trait Queueable {
+    /// queue all instances possible
+    fn queue(&self, queue: &mut RenderQueue) {
+        self.queue_instances(queue, 0..1)
+    }
+    /// queue a range of instances
+    fn queue_instances(&self, queue: &mut RenderQueue, instances: Range<u32>);
+}
@@ -70,6 +70,28 @@ pub async fn load_texture(
     // }
     // id
 }
+/// load a yaml scene from res/ into `context.world`.
+/// returns the handle each id in the file was given; see `World::load_yaml`
+pub async fn load_scene(
+    file_name: &str,
+    registry: &crate::ecs::SceneRegistry,
+    context: &mut crate::Context,
+) -> anyhow::Result<std::collections::HashMap<u64, crate::ecs::Handle>> {
+    let yaml = load_string(file_name, &context.resources_path).await?;
+    context
+        .world
+        .load_yaml(registry, &yaml)
+        .with_context(|| file_name.to_owned())
+}
+/// load a yaml prefab from res/. see `Prefab::from_yaml`
+pub async fn load_prefab(
+    file_name: &str,
+    registry: &crate::ecs::SceneRegistry,
+    context: &crate::Context,
+) -> anyhow::Result<crate::ecs::Prefab> {
+    let yaml = load_string(file_name, &context.resources_path).await?;
+    crate::ecs::Prefab::from_yaml(registry, &yaml).with_context(|| file_name.to_owned())
+}
 /// blocks
 pub fn load_shader(
     shader_path: &str,
@@ -253,13 +275,16 @@ pub mod blinn_phong {
                 stencil: Default::default(),
                 bias: Default::default(),
             }),
-            multisample: wgpu::MultisampleState::default(),
+            multisample: wgpu::MultisampleState {
+                count: context.renderer.sample_count(),
+                ..Default::default()
+            },
             fragment: Some(wgpu::FragmentState {
                 module: &module,
                 entry_point: None,
                 compilation_options: Default::default(),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: context.renderer.config.format,
+                    format: context.renderer.scene_format(),
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::all(),
                 })],
@@ -268,27 +293,4 @@ pub mod blinn_phong {
             cache: None,
         })
     }
-    pub fn light_binding(device: &wgpu::Device, light_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
-        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: true },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-        device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: light_buffer.as_entire_binding(),
-            }],
-        })
-    }
 }
@@ -6,6 +6,7 @@ use wgpu::{BindGroupLayout, BindGroupLayoutEntry, BindingType, PrimitiveState, R
 
 use crate::{
     mesh::vertex,
+    renderer::{Level, LeveledPipeline, Levels},
     resources::{load_string, load_texture, texture},
 };
 
@@ -78,6 +79,21 @@ pub async fn load_pipeline(
     context: &mut crate::Context,
     primitive_state: Option<PrimitiveState>,
 ) -> anyhow::Result<RenderPipeline> {
+    Ok(load_leveled_pipeline(file_name, context, primitive_state)
+        .await?
+        .pipeline)
+}
+
+/// `load_pipeline`, but it also says which bind group levels the pipeline uses.
+///
+/// each bind group in the .omi can have a `level` (`GLOBAL`, `ENVIRONMENT`, `SHADER_TYPE` or
+/// `OBJECT`), in order. groups without one are at the level matching their index, and ones past
+/// the last level have none, so they're only bound by index
+pub async fn load_leveled_pipeline(
+    file_name: &str,
+    context: &mut crate::Context,
+    primitive_state: Option<PrimitiveState>,
+) -> anyhow::Result<LeveledPipeline> {
     // TODO: maybe use serde_yaml as intended. rather than manually parsing. idk
     let primitive_state = match primitive_state {
         Some(prim) => prim,
@@ -98,9 +114,38 @@ pub async fn load_pipeline(
         None => &Vec::new(),
     };
     let mut bind_group_layouts = Vec::with_capacity(bind_groups_yaml.len());
+    let mut levels = Levels::NONE;
+    let mut last_level = None;
+    let mut unleveled = false;
 
-    for group in bind_groups_yaml.iter() {
+    for (g, group) in bind_groups_yaml.iter().enumerate() {
         let label = parse_yaml!(group.get("label"), as_str, "label");
+        let level = match group.get("level") {
+            Some(level) => Some(
+                level
+                    .as_str()
+                    .ok_or(anyhow!("x_x :: invalid OMI yaml! field `level` is invalid"))?
+                    .parse()?,
+            ),
+            None => Level::ALL.get(g).copied(),
+        };
+        match level {
+            Some(_) if unleveled => {
+                return Err(anyhow!(
+                    "x_x :: invalid OMI yaml! `{label}` has a level, but comes after groups without one"
+                ));
+            }
+            Some(level) if last_level.is_some_and(|last| last >= level) => {
+                return Err(anyhow!(
+                    "x_x :: invalid OMI yaml! bind group levels must go up, but `{label}` is {level:?}"
+                ));
+            }
+            Some(level) => {
+                last_level = Some(level);
+                levels = levels.with(level);
+            }
+            None => unleveled = true,
+        }
         let entries_yaml = parse_yaml!(group.get("entries"), as_sequence, "entries");
         let mut entries = Vec::with_capacity(entries_yaml.len());
         for (e, entry) in entries_yaml.iter().enumerate() {
@@ -219,6 +264,17 @@ pub async fn load_pipeline(
     } else {
         wgpu::CompareFunction::Less
     };
+    let multisample = wgpu::MultisampleState {
+        // defaults to the renderer's; set it for pipelines drawing into something else
+        count: match root.get("sample_count") {
+            Some(val) => val.as_u64().ok_or(anyhow!(
+                "x_x :: invalid OMI yaml! field `sample_count` is invalid"
+            ))? as u32,
+            None => context.renderer.sample_count(),
+        },
+        mask: !0,
+        alpha_to_coverage_enabled: false,
+    };
     let pipeline = if frag_name == vert_name {
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some(file_name),
@@ -234,7 +290,7 @@ pub async fn load_pipeline(
                 entry_point: Some(frag_fn),
                 compilation_options: Default::default(),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: context.renderer.config.format,
+                    format: context.renderer.scene_format(),
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -247,11 +303,7 @@ pub async fn load_pipeline(
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
+            multisample,
             multiview_mask: None,
             cache: None,
         })
@@ -275,7 +327,7 @@ pub async fn load_pipeline(
                 entry_point: Some(frag_fn),
                 compilation_options: Default::default(),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: context.renderer.config.format,
+                    format: context.renderer.scene_format(),
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -288,17 +340,17 @@ pub async fn load_pipeline(
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
+            multisample,
             multiview_mask: None,
             cache: None,
         })
     };
 
-    Ok(pipeline)
+    Ok(LeveledPipeline {
+        pipeline,
+        levels,
+        immediate_size: immediate_size as u32,
+    })
 }
 async fn binding_type(entry: &Value, context: &mut crate::Context) -> anyhow::Result<BindingType> {
     Ok(match parse_yaml!(entry.get("type"), as_str, "type") {
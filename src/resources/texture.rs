@@ -19,6 +19,7 @@ impl Texture {
         Self::from_image(device, queue, &img, Some(label))
     }
 
+    /// srgb, so shaders sample linear colours. only hdr (`Renderer::set_hdr`) encodes them back
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -83,22 +84,33 @@ impl Texture {
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float; // 1.
 
-    pub fn create_depth_texture(device: &wgpu::Device, dim: (u32, u32), label: &str) -> Self {
+    /// `sample_count` has to match the color attachments it's drawn with
+    pub fn create_depth_texture(
+        device: &wgpu::Device,
+        dim: (u32, u32),
+        sample_count: u32,
+        label: &str,
+    ) -> Self {
         let size = wgpu::Extent3d {
             // 2.
             width: dim.0.max(1),
             height: dim.1.max(1),
             depth_or_array_layers: 1,
         };
+        // multisampled depth can't go through the comparison sampler anyway, and binding it
+        // breaks resolving on gl
+        let usage = match sample_count {
+            1 => wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING, // 3.
+            _ => wgpu::TextureUsages::RENDER_ATTACHMENT,
+        };
         let desc = wgpu::TextureDescriptor {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT // 3.
-                | wgpu::TextureUsages::TEXTURE_BINDING,
+            usage,
             view_formats: &[],
         };
         let texture = device.create_texture(&desc);
@@ -143,7 +155,10 @@ impl Texture {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // copied from for screenshots
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[],
         });
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
@@ -168,3 +183,36 @@ impl Texture {
         }
     }
 }
+
+impl Texture {
+    /// a multisampled `create_render_texture`, to draw into and resolve into a normal one.
+    /// it can't be read or copied, only drawn into
+    pub fn create_msaa_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("msaa texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // never sampled, but `Texture` wants one
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
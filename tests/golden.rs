@@ -0,0 +1,556 @@
+//! golden-image tests: fixed scenes go through the built-in pipelines on a headless renderer
+//! (the software adapter, if there's no gpu), and the frames get compared against the pngs in
+//! `tests/golden/`.
+//!
+//! on a mismatch the frame and a diff (differing pixels in red) are written to
+//! `target/tmp/golden/`. if the change is on purpose, rerun with `OSTINATO_BLESS=1` to overwrite
+//! the references
+
+use std::path::{Path, PathBuf};
+
+use glam::{Quat, Vec3};
+use image::{Rgba, RgbaImage};
+use ostinato::{
+    camera::{
+        Camera, CameraConfig,
+        light::{Environment, LightUniform},
+    },
+    mesh::{Model, ObjModel, StorageMesh, new_cube, vertex::ModelVertex},
+    prelude::*,
+    resources::blinn_phong::Material,
+};
+use wgpu::{PrimitiveState, RenderPass};
+
+const SIZE: u32 = 96;
+/// how far apart a channel can be before the pixel counts as different.
+/// software rasterisers don't all round the same
+const TOLERANCE: u8 = 4;
+/// how many pixels can differ before the test fails
+const MAX_DIFFERENT: f32 = 0.005;
+
+type Draw = Box<dyn FnMut(&mut Context, &mut RenderPass<'_>)>;
+
+/// draws one fixed frame
+struct Scene {
+    render: Draw,
+    /// `None` copies the scene as is, with `Context::pass_post_processing`
+    post: Option<Draw>,
+}
+
+impl AppHandler for Scene {
+    async fn new(_: &mut Context) -> Result<Self> {
+        anyhow::bail!("x_x :: golden scenes are built by the tests")
+    }
+    fn render(
+        &mut self,
+        context: &mut Context,
+        pass: &mut RenderPass<'_>,
+    ) -> Result<(), wgpu::SurfaceError> {
+        (self.render)(context, pass);
+        Ok(())
+    }
+    fn update(&mut self, _: &mut Context) -> Result<()> {
+        Ok(())
+    }
+    fn post_process(
+        &mut self,
+        context: &mut Context,
+        pass: &mut RenderPass<'_>,
+    ) -> Result<(), wgpu::SurfaceError> {
+        match &mut self.post {
+            Some(post) => post(context, pass),
+            None => context.pass_post_processing(pass)?,
+        }
+        Ok(())
+    }
+}
+
+/// draws passes added to the graph, then copies `SCENE` or whatever `POST` reads
+struct Passes(Vec<(PassId, Draw)>);
+
+impl AppHandler for Passes {
+    async fn new(_: &mut Context) -> Result<Self> {
+        anyhow::bail!("x_x :: golden scenes are built by the tests")
+    }
+    fn render(
+        &mut self,
+        _: &mut Context,
+        _: &mut RenderPass<'_>,
+    ) -> Result<(), wgpu::SurfaceError> {
+        Ok(())
+    }
+    fn update(&mut self, _: &mut Context) -> Result<()> {
+        Ok(())
+    }
+    fn post_process(
+        &mut self,
+        context: &mut Context,
+        pass: &mut RenderPass<'_>,
+    ) -> Result<(), wgpu::SurfaceError> {
+        context.pass_post_processing(pass)
+    }
+    fn graph_pass(
+        &mut self,
+        context: &mut Context,
+        pass: PassId,
+        render_pass: &mut RenderPass<'_>,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let (_, draw) = self.0.iter_mut().find(|(id, _)| *id == pass).unwrap();
+        draw(context, render_pass);
+        Ok(())
+    }
+}
+
+fn context() -> Context {
+    let mut context = pollster::block_on(Context::new_headless(SIZE, SIZE)).unwrap();
+    context.set_resource_directory(format!("{}/res", env!("CARGO_MANIFEST_DIR")));
+    context
+}
+
+/// renders `scene` and checks it against `tests/golden/<name>.png`
+fn check(name: &str, context: &mut Context, mut scene: impl AppHandler) {
+    context.frame(&mut scene).unwrap();
+    let frame = context.renderer.capture_frame().unwrap();
+
+    let reference = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/golden/{name}.png"));
+    if std::env::var_os("OSTINATO_BLESS").is_some() {
+        frame.save(&reference).unwrap();
+        return;
+    }
+    let expected = image::open(&reference)
+        .unwrap_or_else(|e| {
+            panic!("x_x :: no reference for `{name}` ({e}); run with OSTINATO_BLESS=1 to make one")
+        })
+        .into_rgba8();
+
+    let (diff, different) = diff(&expected, &frame);
+    let allowed = (MAX_DIFFERENT * (SIZE * SIZE) as f32) as usize;
+    if different > allowed {
+        let dir = out_dir();
+        frame.save(dir.join(format!("{name}.png"))).unwrap();
+        diff.save(dir.join(format!("{name}.diff.png"))).unwrap();
+        panic!(
+            "x_x :: `{name}` has {different} different pixels (at most {allowed} allowed). \
+             see {}",
+            dir.display()
+        );
+    }
+}
+
+/// differing pixels in red over a faded copy of `expected`, and how many there were.
+/// a different size is all different
+fn diff(expected: &RgbaImage, actual: &RgbaImage) -> (RgbaImage, usize) {
+    if expected.dimensions() != actual.dimensions() {
+        let (w, h) = actual.dimensions();
+        return (
+            RgbaImage::from_pixel(w, h, Rgba([255, 0, 0, 255])),
+            (w * h) as usize,
+        );
+    }
+    let mut different = 0;
+    let diff = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let (e, a) = (expected.get_pixel(x, y), actual.get_pixel(x, y));
+        if e.0.iter().zip(a.0).any(|(e, a)| e.abs_diff(a) > TOLERANCE) {
+            different += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let [r, g, b, _] = e.0.map(|c| c / 4);
+            Rgba([r, g, b, 255])
+        }
+    });
+    (diff, different)
+}
+
+fn out_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// scene pieces
+
+fn camera(context: &Context) -> Camera {
+    Camera::new(
+        CameraConfig {
+            eye: Vec3::new(0., 0., 4.),
+            rotation: Quat::IDENTITY,
+            fovy: 45.,
+            znear: 0.1,
+            zfar: 100.,
+        },
+        1.,
+        &context.renderer.device,
+    )
+}
+
+fn lights(context: &Context) -> Environment {
+    Environment::new(
+        &[LightUniform::new([-3., 4., 5.], [1., 1., 1.], 40.)],
+        &context.renderer.device,
+    )
+}
+
+/// a unit cube around the origin, turned so three faces show
+fn cube(context: &mut Context) -> Mesh<ModelVertex> {
+    new_cube(
+        Instance {
+            position: Vec3::splat(-0.5),
+            pivot: Vec3::splat(0.5),
+            rotation: Quat::from_rotation_x(0.5) * Quat::from_rotation_y(0.7),
+            scale: Vec3::ONE,
+        },
+        Material {
+            ambient: [0.05, 0.05, 0.1],
+            diffuse: [0.2, 0.6, 1.],
+            specular: [1.; 3],
+            shininess: 32.,
+        },
+        &mut context.renderer,
+    )
+}
+
+/// the cube through `blinn_phong`
+fn blinn_phong(context: &mut Context) -> Draw {
+    let pipeline =
+        pollster::block_on(load_pipeline("core_shaders/blinn_phong", context, None)).unwrap();
+    let (camera, lights, cube) = (camera(context), lights(context), cube(context));
+    Box::new(move |context, pass| {
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, Some(&camera.bind_group), &[]);
+        pass.set_bind_group(1, Some(lights.bind_group()), &[]);
+        cube.draw(pass, &mut context.renderer);
+    })
+}
+
+/// a post processing shader over the `blinn_phong` scene
+fn post(shader: &str, immediates: &'static [u8], context: &mut Context) -> Scene {
+    let pipeline = post_pipeline(shader, immediates.len() as u32, context);
+    Scene {
+        render: blinn_phong(context),
+        post: Some(Box::new(move |_, pass| {
+            pass.set_pipeline(&pipeline);
+            if !immediates.is_empty() {
+                pass.set_immediates(0, immediates);
+            }
+            pass.draw(0..3, 0..1);
+        })),
+    }
+}
+
+#[test]
+fn blinn_phong_cube() {
+    let mut context = context();
+    let render = blinn_phong(&mut context);
+    check("blinn_phong", &mut context, Scene { render, post: None });
+}
+
+#[test]
+fn msaa_blinn_phong_cube() {
+    let mut context = context();
+    context.renderer.set_sample_count(4).unwrap();
+    let render = blinn_phong(&mut context);
+    check(
+        "msaa_blinn_phong",
+        &mut context,
+        Scene { render, post: None },
+    );
+}
+
+#[test]
+fn hdr_blinn_phong_cube() {
+    let mut context = context();
+    let tonemap = Tonemap::new(Tonemapper::Aces, &mut context).with_exposure(1.5);
+    context.renderer.set_hdr(Some(tonemap));
+    let render = blinn_phong(&mut context);
+    check(
+        "hdr_blinn_phong",
+        &mut context,
+        Scene { render, post: None },
+    );
+}
+
+#[test]
+fn hdr_bloom_blinn_phong_cube() {
+    let mut context = context();
+    let tonemap = Tonemap::new(Tonemapper::Aces, &mut context).with_exposure(1.5);
+    context.renderer.set_hdr(Some(tonemap));
+    let bloom = pollster::block_on(Bloom::load(
+        "core_shaders/post_processing/bloom",
+        &mut context,
+    ))
+    .unwrap();
+    context.renderer.bloom = Some(bloom.with_settings(BloomSettings {
+        threshold: 0.5,
+        intensity: 0.3,
+        ..Default::default()
+    }));
+    let render = blinn_phong(&mut context);
+    check(
+        "hdr_bloom_blinn_phong",
+        &mut context,
+        Scene { render, post: None },
+    );
+}
+
+#[test]
+fn queued_blinn_phong_cube() {
+    // the same frame as `blinn_phong_cube`, replayed from a `RenderQueue`
+    let mut context = context();
+    let pipeline = pollster::block_on(load_pipeline(
+        "core_shaders/blinn_phong",
+        &mut context,
+        None,
+    ))
+    .unwrap();
+    let (camera, lights, cube) = (camera(&context), lights(&context), cube(&mut context));
+    let mut queue = RenderQueue::new();
+    let render = Box::new(move |_: &mut Context, pass: &mut RenderPass<'_>| {
+        queue.set_pipeline(&pipeline, 160);
+        queue.set_bind_group(Level::Global, &camera.bind_group);
+        queue.set_environment(&lights);
+        cube.queue(&mut queue);
+        queue.replay(pass);
+    });
+    check("blinn_phong", &mut context, Scene { render, post: None });
+}
+
+#[test]
+fn two_environments() {
+    // two cubes, each lit by its own environment. the right one starts like the left and is
+    // changed afterwards
+    let mut context = context();
+    let pipeline = pollster::block_on(load_leveled_pipeline(
+        "core_shaders/blinn_phong",
+        &mut context,
+        None,
+    ))
+    .unwrap();
+    let camera = camera(&context);
+    let (white, mut red) = (lights(&context), lights(&context));
+    let light = red.light_mut(0).unwrap();
+    light.color = [1., 0.2, 0.2];
+    light.position = [3., 4., 5.];
+    red.add_light(LightUniform::new([0., -4., 2.], [1., 0., 0.], 20.));
+    red.update(&context.renderer.device, &context.renderer.queue);
+
+    let mut cubes = [cube(&mut context), cube(&mut context)];
+    for (cube, x) in cubes.iter_mut().zip([-0.8, 0.8]) {
+        cube.transform.position.x += x;
+        cube.transform.scale = Vec3::splat(0.7);
+    }
+    let mut queue = RenderQueue::new();
+    let render = Box::new(move |_: &mut Context, pass: &mut RenderPass<'_>| {
+        queue.set_leveled_pipeline(&pipeline);
+        queue.set_bind_group(Level::Global, &camera.bind_group);
+        for (cube, environment) in cubes.iter().zip([&white, &red]) {
+            queue.set_environment(environment);
+            cube.queue(&mut queue);
+        }
+        queue.replay(pass);
+    });
+    check(
+        "two_environments",
+        &mut context,
+        Scene { render, post: None },
+    );
+}
+
+#[test]
+fn graph_pass_into_own_attachments() {
+    // the cube goes into attachments the test declares instead of `SCENE`, which is left empty,
+    // then `POST` reads them, so it's the same frame as `blinn_phong_cube`
+    let mut context = context();
+    let graph = &mut context.renderer.graph;
+    let color = graph.add_attachment(AttachmentDesc::color("cube"));
+    let depth = graph.add_attachment(AttachmentDesc::depth("cube depth"));
+    assert!(graph.remove_pass(PassId::SCENE).is_err());
+    let cube_pass = graph
+        .add_pass(
+            PassDesc::new("cube")
+                .with_color(color, Some(wgpu::Color::TRANSPARENT))
+                .with_depth(depth, Some(1.)),
+        )
+        .unwrap();
+    graph
+        .replace_pass(
+            PassId::POST,
+            PassDesc::new("post")
+                .with_color(AttachmentId::OUTPUT, Some(wgpu::Color::TRANSPARENT))
+                .with_read(color),
+        )
+        .unwrap();
+    assert_eq!(graph.order(), [PassId::SCENE, cube_pass, PassId::POST]);
+
+    let passes = Passes(vec![(cube_pass, blinn_phong(&mut context))]);
+    check("blinn_phong", &mut context, passes);
+}
+
+#[test]
+fn obj_cube() {
+    let mut context = context();
+    // an absolute path replaces the resource directory
+    let checker = out_dir().join("checker.png");
+    RgbaImage::from_fn(8, 8, |x, y| match (x + y) % 2 {
+        0 => Rgba([255, 200, 40, 255]),
+        _ => Rgba([40, 40, 40, 255]),
+    })
+    .save(&checker)
+    .unwrap();
+    let checker = checker.to_str().unwrap();
+
+    let pipeline =
+        pollster::block_on(load_pipeline("core_shaders/obj", &mut context, None)).unwrap();
+    let model = Model {
+        meshes: vec![cube(&mut context)],
+        transform: Instance {
+            scale: Vec3::ONE,
+            ..Default::default()
+        },
+    };
+    let model =
+        pollster::block_on(ObjModel::from_model(model, &[[checker; 2]], &mut context)).unwrap();
+    let (camera, lights) = (camera(&context), lights(&context));
+    let render = Box::new(move |context: &mut Context, pass: &mut RenderPass<'_>| {
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, Some(&camera.bind_group), &[]);
+        pass.set_bind_group(1, Some(lights.bind_group()), &[]);
+        model.draw(pass, &mut context.renderer);
+    });
+    check("obj", &mut context, Scene { render, post: None });
+}
+
+#[test]
+fn wireframe_cube() {
+    let mut context = context();
+    let pipeline = pollster::block_on(load_pipeline(
+        "core_shaders/wireframe",
+        &mut context,
+        Some(PrimitiveState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            cull_mode: None,
+            ..Default::default()
+        }),
+    ))
+    .unwrap();
+    let cube = cube(&mut context);
+    let wireframe = StorageMesh::from_mesh(cube, &context.renderer.device).unwrap();
+    let camera = camera(&context);
+    let render = Box::new(move |context: &mut Context, pass: &mut RenderPass<'_>| {
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, Some(&camera.bind_group), &[]);
+        wireframe.draw(pass, &mut context.renderer);
+    });
+    check("wireframe", &mut context, Scene { render, post: None });
+}
+
+#[test]
+fn queued_wireframe_cube() {
+    // the storage buffers are at the object level, which the wireframe shader puts at index 1
+    let mut context = context();
+    let pipeline = pollster::block_on(load_leveled_pipeline(
+        "core_shaders/wireframe",
+        &mut context,
+        Some(PrimitiveState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            cull_mode: None,
+            ..Default::default()
+        }),
+    ))
+    .unwrap();
+    assert_eq!(pipeline.levels.index(Level::Object), Some(1));
+    let cube = cube(&mut context);
+    let wireframe = StorageMesh::from_mesh(cube, &context.renderer.device).unwrap();
+    let (camera, lights) = (camera(&context), lights(&context));
+    let mut queue = RenderQueue::new();
+    let render = Box::new(move |_: &mut Context, pass: &mut RenderPass<'_>| {
+        queue.set_leveled_pipeline(&pipeline);
+        queue.set_bind_group(Level::Global, &camera.bind_group);
+        // not used by the wireframe shader, so never bound
+        queue.set_environment(&lights);
+        wireframe.queue(&mut queue);
+        queue.replay(pass);
+    });
+    check("wireframe", &mut context, Scene { render, post: None });
+}
+
+#[test]
+fn post_nothing() {
+    let mut context = context();
+    let scene = post(
+        "core_shaders/post_processing/nothing.wgsl",
+        &[],
+        &mut context,
+    );
+    check("post_nothing", &mut context, scene);
+}
+
+#[test]
+fn post_white_dither() {
+    let mut context = context();
+    let scene = post(
+        "core_shaders/post_processing/white_dither.wgsl",
+        &[],
+        &mut context,
+    );
+    check("post_white_dither", &mut context, scene);
+}
+
+#[test]
+fn post_stack_white_dither() {
+    // the effect draws into the stack's own texture and `POST` copies it out
+    let mut context = context();
+    let dither = PostEffect::new(
+        "core_shaders/post_processing/white_dither.wgsl",
+        0,
+        &mut context,
+    );
+    context.renderer.post_stack.push(dither);
+    let render = blinn_phong(&mut context);
+    check(
+        "post_white_dither",
+        &mut context,
+        Scene { render, post: None },
+    );
+}
+
+#[test]
+fn post_stack_chain() {
+    let mut context = context();
+    let blur = PostEffect::new("core_shaders/post_processing/blur.wgsl", 4, &mut context)
+        .with_immediates(bytemuck::bytes_of(&3f32));
+    let dither = PostEffect::new(
+        "core_shaders/post_processing/white_dither.wgsl",
+        0,
+        &mut context,
+    );
+    let mut mandelbrot = PostEffect::new(
+        "core_shaders/post_processing/mandelbrot.wgsl",
+        24,
+        &mut context,
+    );
+    // would cover everything if it ran
+    mandelbrot.enabled = false;
+    let stack = &mut context.renderer.post_stack;
+    stack.push(blur);
+    stack.push(mandelbrot);
+    stack.push(dither);
+    let render = blinn_phong(&mut context);
+    check(
+        "post_stack_chain",
+        &mut context,
+        Scene { render, post: None },
+    );
+}
+
+#[test]
+fn post_mandelbrot() {
+    // offset_x, offset_y and scale, as double-single floats
+    const VIEW: [f32; 6] = [-0.6, 0., 0., 0., 0.03, 0.];
+    let mut context = context();
+    let scene = post(
+        "core_shaders/post_processing/mandelbrot.wgsl",
+        bytemuck::cast_slice(&VIEW),
+        &mut context,
+    );
+    check("post_mandelbrot", &mut context, scene);
+}